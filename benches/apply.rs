@@ -0,0 +1,48 @@
+//! Compares creating many junctions to one target with `create` against
+//! encoding the reparse data once with `build_reparse_data` and writing it
+//! with `apply`.
+//!
+//! Usage:
+//!   cargo bench --bench apply [-- <count>]
+
+#[cfg(windows)]
+use std::env;
+#[cfg(windows)]
+use std::fs;
+#[cfg(windows)]
+use std::time::Instant;
+
+#[cfg(not(windows))]
+fn main() {
+    eprintln!("apply: junctions only exist on Windows, nothing to do");
+}
+
+#[cfg(windows)]
+fn main() {
+    // `cargo bench` passes `--bench`, which isn't a count.
+    let count = env::args().skip(1).find_map(|arg| arg.parse().ok()).unwrap_or(1000);
+    let tmpdir = tempfile::tempdir().expect("failed to create a temporary directory");
+    let target = tmpdir.path().join("target");
+    fs::create_dir_all(&target).expect("failed to create the target");
+    let created = tmpdir.path().join("created");
+    let applied = tmpdir.path().join("applied");
+    fs::create_dir_all(&created).expect("failed to create a directory");
+    fs::create_dir_all(&applied).expect("failed to create a directory");
+
+    let start = Instant::now();
+    for i in 0..count {
+        junction::create(&target, created.join(i.to_string())).expect("create failed");
+    }
+    let create = start.elapsed();
+
+    let start = Instant::now();
+    let data = junction::build_reparse_data(&target).expect("build_reparse_data failed");
+    for i in 0..count {
+        junction::apply(&data, applied.join(i.to_string())).expect("apply failed");
+    }
+    let apply = start.elapsed();
+
+    println!("{} junctions", count);
+    println!("create:                     {:?} ({:?} each)", create, create / count);
+    println!("build_reparse_data + apply: {:?} ({:?} each)", apply, apply / count);
+}
@@ -0,0 +1,104 @@
+//! Compares the allocations of creating junctions from several threads with
+//! `BufferStrategy::PerCall` against `BufferStrategy::ThreadLocal`.
+//!
+//! Usage:
+//!   cargo bench --bench buffers [-- <count per thread>]
+
+#[cfg(windows)]
+use std::alloc::{GlobalAlloc, Layout, System};
+#[cfg(windows)]
+use std::env;
+#[cfg(windows)]
+use std::fs;
+#[cfg(windows)]
+use std::path::Path;
+#[cfg(windows)]
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(windows)]
+use std::thread;
+
+#[cfg(windows)]
+use junction::{BufferStrategy, CreateOptions};
+
+#[cfg(windows)]
+const THREADS: usize = 8;
+
+/// Counts the allocations made, the bytes currently allocated, and the most
+/// bytes allocated at once.
+#[cfg(windows)]
+struct Counting;
+
+#[cfg(windows)]
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+#[cfg(windows)]
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+#[cfg(windows)]
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+#[cfg(windows)]
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        let current = CURRENT.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+        PEAK.fetch_max(current, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[cfg(windows)]
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+#[cfg(not(windows))]
+fn main() {
+    eprintln!("buffers: junctions only exist on Windows, nothing to do");
+}
+
+#[cfg(windows)]
+fn main() {
+    // `cargo bench` passes `--bench`, which isn't a count.
+    let count = env::args().skip(1).find_map(|arg| arg.parse().ok()).unwrap_or(500);
+    let tmpdir = tempfile::tempdir().expect("failed to create a temporary directory");
+    let target = tmpdir.path().join("target");
+    fs::create_dir_all(&target).expect("failed to create the target");
+
+    println!("{} threads creating {} junctions each", THREADS, count);
+    for (name, strategy) in [
+        ("per call", BufferStrategy::PerCall),
+        ("thread local", BufferStrategy::ThreadLocal),
+    ] {
+        let dir = tmpdir.path().join(name);
+        fs::create_dir_all(&dir).expect("failed to create a directory");
+        let base = CURRENT.load(Ordering::Relaxed);
+        ALLOCATIONS.store(0, Ordering::Relaxed);
+        PEAK.store(base, Ordering::Relaxed);
+        run(&target, &dir, count, strategy);
+        println!(
+            "{:<12}: {} allocations, peak {} KiB above baseline",
+            name,
+            ALLOCATIONS.load(Ordering::Relaxed),
+            (PEAK.load(Ordering::Relaxed) - base) / 1024
+        );
+    }
+}
+
+#[cfg(windows)]
+fn run(target: &Path, dir: &Path, count: usize, strategy: BufferStrategy) {
+    thread::scope(|scope| {
+        for t in 0..THREADS {
+            scope.spawn(move || {
+                let mut options = CreateOptions::new();
+                options.buffer_strategy(strategy);
+                for i in 0..count {
+                    let junction = dir.join(format!("{}-{}", t, i));
+                    options.create(target, junction).expect("create failed");
+                }
+            });
+        }
+    });
+}
@@ -0,0 +1,66 @@
+//! Compares finding the junctions of a large directory from the tags of its
+//! listing, as `list_junctions_with_targets` and `reparse_tag_histogram` do,
+//! against opening every entry to read its tag with `reparse_tag`.
+//!
+//! Usage:
+//!   cargo bench --bench listing [-- <count>]
+
+#[cfg(windows)]
+use std::env;
+#[cfg(windows)]
+use std::fs;
+#[cfg(windows)]
+use std::time::Instant;
+
+#[cfg(not(windows))]
+fn main() {
+    eprintln!("listing: junctions only exist on Windows, nothing to do");
+}
+
+#[cfg(windows)]
+fn main() {
+    // `cargo bench` passes `--bench`, which isn't a count.
+    let count: usize = env::args().skip(1).find_map(|arg| arg.parse().ok()).unwrap_or(10_000);
+    let tmpdir = tempfile::tempdir().expect("failed to create a temporary directory");
+    let target = tmpdir.path().join("target");
+    let dir = tmpdir.path().join("dir");
+    fs::create_dir_all(&target).expect("failed to create the target");
+    fs::create_dir_all(&dir).expect("failed to create a directory");
+    // One junction for every nine plain files, as in a typical build cache.
+    let junctions = count / 10;
+    for i in 0..count {
+        let entry = dir.join(i.to_string());
+        if i % 10 == 0 {
+            junction::create(&target, entry).expect("create failed");
+        } else {
+            fs::write(entry, "").expect("failed to create a file");
+        }
+    }
+
+    let start = Instant::now();
+    let listed = junction::list_junctions_with_targets(&dir).expect("list_junctions_with_targets failed");
+    let list = start.elapsed();
+    assert_eq!(listed.len(), junctions);
+
+    let start = Instant::now();
+    let histogram = junction::reparse_tag_histogram(&dir).expect("reparse_tag_histogram failed");
+    let tags = start.elapsed();
+    assert_eq!(histogram.values().sum::<usize>(), junctions);
+
+    let start = Instant::now();
+    let mut found = 0;
+    for entry in fs::read_dir(&dir).expect("failed to list the directory") {
+        let path = entry.expect("failed to read an entry").path();
+        // Errors for entries that aren't reparse points, after opening them.
+        if let Ok(junction::ReparseTag::MountPoint) = junction::reparse_tag(&path) {
+            found += 1;
+        }
+    }
+    let per_entry = start.elapsed();
+    assert_eq!(found, junctions);
+
+    println!("{} entries, {} of them junctions", count, junctions);
+    println!("list_junctions_with_targets: {:?} ({} opens)", list, junctions);
+    println!("reparse_tag_histogram:       {:?} (no opens)", tags);
+    println!("reparse_tag on every entry:  {:?} ({} opens)", per_entry, count);
+}
@@ -0,0 +1,30 @@
+#![no_main]
+
+#[cfg(windows)]
+use std::ffi::OsString;
+#[cfg(windows)]
+use std::os::windows::ffi::{OsStrExt, OsStringExt};
+#[cfg(windows)]
+use std::path::PathBuf;
+
+use junction::parse;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(mount_point) = parse::parse_reparse_buffer(data) else {
+        return;
+    };
+    let len = parse::buffer_len(data).expect("a parsed buffer has a valid length");
+    assert!(len <= data.len());
+    for name in [mount_point.substitute_name, mount_point.print_name] {
+        assert!(name.as_bytes().len() <= len);
+        let wide: Vec<u16> = name.units().collect();
+        assert_eq!(wide.len(), name.len());
+        // Unpaired surrogates must survive the round trip through a path.
+        #[cfg(windows)]
+        {
+            let path = PathBuf::from(OsString::from_wide(&wide));
+            assert_eq!(path.as_os_str().encode_wide().collect::<Vec<_>>(), wide);
+        }
+    }
+});
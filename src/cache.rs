@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::{internals, ReadOptions};
+
+/// A cache of junction targets, for callers that repeatedly read the same
+/// junctions, such as file watchers.
+///
+/// Entries are keyed by the identity of the junction directory itself (its
+/// volume serial number and file index), so a junction is found again under
+/// any path that leads to it. An entry is read again from the reparse point
+/// once the junction's last write time changes.
+///
+/// Looking up a junction still opens it to query its identity, but with
+/// attribute access only, and skips reading and decoding its reparse data
+/// when the cached target is fresh.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// # use std::fs;
+/// # use junction::{create, TargetCache};
+/// fn main() -> io::Result<()> {
+///     let tmpdir = tempfile::tempdir()?;
+///     let target = tmpdir.path().join("target");
+///     let junction = tmpdir.path().join("junction");
+///     # fs::create_dir_all(&target)?;
+///     create(&target, &junction)?;
+///     let mut cache = TargetCache::new();
+///     let first = cache.get_target(&junction)?;
+///     assert_eq!(cache.get_target(&junction)?, first);
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct TargetCache {
+    entries: HashMap<(u32, u64), Entry>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    last_write_time: u64,
+    target: PathBuf,
+}
+
+impl TargetCache {
+    /// Creates an empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gets the target of the specified junction point, like
+    /// [`get_target`](crate::get_target), reusing the cached target if the
+    /// junction didn't change since it was last read.
+    pub fn get_target<P: AsRef<Path>>(&mut self, junction: P) -> io::Result<PathBuf> {
+        let junction = junction.as_ref();
+        let id = internals::file_id(junction)?;
+        let key = (id.volume_serial_number, id.file_index);
+        if let Some(entry) = self.entries.get(&key) {
+            if entry.last_write_time == id.last_write_time {
+                return Ok(entry.target.clone());
+            }
+        }
+        let target = internals::get_target(junction, &ReadOptions::new())?;
+        let entry = Entry {
+            last_write_time: id.last_write_time,
+            target: target.clone(),
+        };
+        self.entries.insert(key, entry);
+        Ok(target)
+    }
+
+    /// Removes all cached targets.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
@@ -0,0 +1,118 @@
+use std::os::windows::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+use crate::ConvertOptions;
+
+const FILE_ATTRIBUTE_REPARSE_POINT: u32 = 0x400;
+
+pub fn convert_to_junction(dir: &Path, store_root: &Path, options: &ConvertOptions) -> io::Result<()> {
+    if !is_real_dir(dir)? {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "`dir` is not a directory, or is already a reparse point",
+        ));
+    }
+    let name = dir
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "`dir` has no final component"))?;
+    let store = store_root.join(name);
+    fs::create_dir_all(store_root)?;
+    match fs::create_dir(&store) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists && options.merge => {}
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("store entry {} already exists", store.display()),
+            ))
+        }
+        Err(e) => return Err(e),
+    }
+    move_entries(dir, &store)?;
+    fs::remove_dir(dir)?;
+    crate::create(&store, dir)
+}
+
+pub fn replace_dir_with_junction(target: &Path, dir: &Path) -> io::Result<PathBuf> {
+    if !is_real_dir(dir)? {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "`dir` is not a directory, or is already a reparse point",
+        ));
+    }
+    // Created next to `dir` beforehand, so only two renames are left to do.
+    let staged = free_sibling(dir, "new")?;
+    crate::create(target, &staged)?;
+    let stash = match free_sibling(dir, "old") {
+        Ok(stash) => stash,
+        Err(e) => {
+            let _ = fs::remove_dir(&staged);
+            return Err(e);
+        }
+    };
+    if let Err(e) = fs::rename(dir, &stash) {
+        let _ = fs::remove_dir(&staged);
+        return Err(e);
+    }
+    if let Err(e) = fs::rename(&staged, dir) {
+        // Put the directory back rather than leave nothing at `dir`.
+        let _ = fs::rename(&stash, dir);
+        let _ = fs::remove_dir(&staged);
+        return Err(e);
+    }
+    Ok(stash)
+}
+
+/// Returns the first path of `<name>.<suffix>`, `<name>.<suffix>.1`, ... next
+/// to `dir` that doesn't exist yet.
+fn free_sibling(dir: &Path, suffix: &str) -> io::Result<PathBuf> {
+    let name = dir
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "`dir` has no final component"))?
+        .to_string_lossy();
+    for i in 0u32.. {
+        let sibling = match i {
+            0 => dir.with_file_name(format!("{}.{}", name, suffix)),
+            _ => dir.with_file_name(format!("{}.{}.{}", name, suffix, i)),
+        };
+        if fs::symlink_metadata(&sibling).is_err() {
+            return Ok(sibling);
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        "no free name next to `dir`",
+    ))
+}
+
+/// Moves every entry of `from` into `to`, merging directories that exist in both.
+fn move_entries(from: &Path, to: &Path) -> io::Result<()> {
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let dest = to.join(entry.file_name());
+        if fs::symlink_metadata(&dest).is_err() {
+            relocate(&entry.path(), &dest)?;
+        } else if is_real_dir(&entry.path())? && is_real_dir(&dest)? {
+            move_entries(&entry.path(), &dest)?;
+            fs::remove_dir(entry.path())?;
+        } else {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} already exists in the store", dest.display()),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Moves a single file or directory, which must stay on the same volume.
+fn relocate(from: &Path, to: &Path) -> io::Result<()> {
+    fs::rename(from, to)
+}
+
+/// Whether `path` is a directory that is not a reparse point.
+fn is_real_dir(path: &Path) -> io::Result<bool> {
+    let meta = fs::symlink_metadata(path)?;
+    Ok(meta.is_dir() && meta.file_attributes() & FILE_ATTRIBUTE_REPARSE_POINT == 0)
+}
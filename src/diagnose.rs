@@ -0,0 +1,168 @@
+use std::os::windows::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+use crate::{internals, parse, ReadOptions};
+
+const FILE_ATTRIBUTE_REPARSE_POINT: u32 = 0x400;
+// The most reparse points Windows follows when opening a path.
+const MAX_HOPS: usize = 63;
+
+/// How bad a [`Diagnostic`] is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// The junction works, but may break elsewhere, such as when copied into
+    /// a Windows Container layer.
+    Warning,
+    /// The path is not a working junction.
+    Error,
+}
+
+/// Which check a [`Diagnostic`] comes from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Check {
+    /// The path exists.
+    Exists,
+    /// The path is a reparse point.
+    ReparsePoint,
+    /// The reparse point is tagged as a mount point.
+    MountPoint,
+    /// The reparse data decodes, and the SubstituteName is an NT path.
+    SubstituteName,
+    /// The PrintName is not empty.
+    PrintName,
+    /// The target, following any junctions it points to, doesn't lead back
+    /// to the junction itself.
+    SelfReference,
+    /// The target can be opened through the junction.
+    TargetResolves,
+}
+
+/// A problem found by [`diagnose`](crate::diagnose).
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Diagnostic {
+    /// How bad the problem is.
+    pub severity: Severity,
+    /// The check that failed.
+    pub check: Check,
+    /// A description of the problem, for users.
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn new(severity: Severity, check: Check, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            check,
+            message: message.into(),
+        }
+    }
+}
+
+pub fn diagnose(junction: &Path) -> io::Result<Vec<Diagnostic>> {
+    let meta = match fs::symlink_metadata(junction) {
+        Ok(meta) => meta,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Ok(vec![Diagnostic::new(
+                Severity::Error,
+                Check::Exists,
+                "path does not exist",
+            )]);
+        }
+        Err(e) => return Err(e),
+    };
+    if meta.file_attributes() & FILE_ATTRIBUTE_REPARSE_POINT == 0 {
+        return Ok(vec![Diagnostic::new(
+            Severity::Error,
+            Check::ReparsePoint,
+            "path is not a reparse point",
+        )]);
+    }
+    let raw = internals::raw_reparse_buffer(junction)?;
+    let mount_point = match parse::parse_reparse_buffer(&raw) {
+        Ok(mount_point) => mount_point,
+        Err(parse::Error::NotMountPoint { tag }) => {
+            return Ok(vec![Diagnostic::new(
+                Severity::Error,
+                Check::MountPoint,
+                format!("reparse point has tag {:#010x}, not a mount point", tag),
+            )]);
+        }
+        Err(e) => {
+            return Ok(vec![Diagnostic::new(
+                Severity::Error,
+                Check::SubstituteName,
+                format!("reparse data is malformed: {}", e),
+            )]);
+        }
+    };
+
+    let mut diagnostics = Vec::new();
+    let substitute_name: Vec<u16> = mount_point.substitute_name.units().collect();
+    if !substitute_name.starts_with(&parse::NT_PREFIX) {
+        diagnostics.push(Diagnostic::new(
+            Severity::Error,
+            Check::SubstituteName,
+            "SubstituteName does not start with the `\\??\\` prefix",
+        ));
+    } else if parse::strip_nt_prefix(&substitute_name).is_empty() {
+        diagnostics.push(Diagnostic::new(
+            Severity::Error,
+            Check::SubstituteName,
+            "SubstituteName is empty",
+        ));
+    }
+    if mount_point.print_name.is_empty() {
+        diagnostics.push(Diagnostic::new(
+            Severity::Warning,
+            Check::PrintName,
+            "PrintName is empty, which breaks the junction in Windows Container layers",
+        ));
+    }
+    // Checked first, as a loop only shows up as an obscure error when followed.
+    if refers_to_itself(junction) {
+        diagnostics.push(Diagnostic::new(
+            Severity::Error,
+            Check::SelfReference,
+            "target resolves back to the junction itself",
+        ));
+    // Follows the junction.
+    } else if let Err(e) = fs::metadata(junction) {
+        diagnostics.push(Diagnostic::new(
+            Severity::Error,
+            Check::TargetResolves,
+            format!("target does not resolve: {}", e),
+        ));
+    }
+    Ok(diagnostics)
+}
+
+/// Whether following the stored targets of `junction`, without letting
+/// Windows resolve them, leads back to `junction`.
+fn refers_to_itself(junction: &Path) -> bool {
+    let start = match internals::real_path(junction) {
+        Ok(path) => path_key(&path),
+        Err(_) => return false,
+    };
+    let mut current = junction.to_path_buf();
+    for _ in 0..MAX_HOPS {
+        // Stops at the first target that isn't a junction, or can't be read.
+        let next =
+            internals::get_target(&current, &ReadOptions::new()).and_then(|target| internals::real_path(&target));
+        current = match next {
+            Ok(next) => next,
+            Err(_) => return false,
+        };
+        if path_key(&current) == start {
+            return true;
+        }
+    }
+    false
+}
+
+// Paths are compared case-insensitively, like NTFS does by default.
+fn path_key(path: &Path) -> PathBuf {
+    PathBuf::from(internals::upcase(path.as_os_str()))
+}
@@ -0,0 +1,67 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Method-style access to junctions on [`Path`], for callers that prefer
+/// `path.is_junction()?` over `junction::exists(path)?`.
+///
+/// Each method calls the free function of the same purpose, which remains the
+/// canonical API and documents the details.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// # use std::fs;
+/// use junction::JunctionExt;
+/// fn main() -> io::Result<()> {
+///     let tmpdir = tempfile::tempdir()?;
+///     let target = tmpdir.path().join("target");
+///     let junction = tmpdir.path().join("junction");
+///     # fs::create_dir_all(&target)?;
+///     junction.create_junction_to(&target)?;
+///     assert!(junction.is_junction()?);
+///     assert!(junction.junction_target()?.ends_with("target"));
+///     junction.delete_junction()?;
+///     assert!(!junction.is_junction()?);
+///     Ok(())
+/// }
+/// ```
+pub trait JunctionExt {
+    /// Determines whether `self` exists and is a junction.
+    ///
+    /// See [`exists`](crate::exists).
+    fn is_junction(&self) -> io::Result<bool>;
+
+    /// Gets the target of the junction at `self`.
+    ///
+    /// See [`get_target`](crate::get_target).
+    fn junction_target(&self) -> io::Result<PathBuf>;
+
+    /// Creates a junction at `self` pointing to `target`.
+    ///
+    /// See [`create`](crate::create).
+    fn create_junction_to<P: AsRef<Path>>(&self, target: P) -> io::Result<()>;
+
+    /// Deletes the reparse point of the junction at `self`.
+    ///
+    /// See [`delete`](crate::delete).
+    fn delete_junction(&self) -> io::Result<()>;
+}
+
+impl JunctionExt for Path {
+    fn is_junction(&self) -> io::Result<bool> {
+        crate::exists(self)
+    }
+
+    fn junction_target(&self) -> io::Result<PathBuf> {
+        crate::get_target(self)
+    }
+
+    fn create_junction_to<P: AsRef<Path>>(&self, target: P) -> io::Result<()> {
+        crate::create(target, self)
+    }
+
+    fn delete_junction(&self) -> io::Result<()> {
+        crate::delete(self)
+    }
+}
@@ -1,17 +1,29 @@
 mod c;
 mod cast;
 mod helpers;
+mod usn;
 
-use std::ffi::OsString;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::ffi::{OsStr, OsString};
+use std::fs::File;
 use std::mem::size_of;
-use std::os::windows::ffi::OsStringExt;
+use std::os::windows::ffi::{OsStrExt, OsStringExt};
+use std::os::windows::fs::MetadataExt;
 use std::os::windows::io::AsRawHandle;
-use std::path::{Path, PathBuf};
+#[cfg(feature = "nt-api")]
+use std::os::windows::io::BorrowedHandle;
+use std::path::{Component, Path, PathBuf};
 use std::ptr::{addr_of_mut, copy_nonoverlapping};
-use std::{cmp, fs, io, slice};
+use std::{fs, io};
 
 use cast::BytesAsReparseDataBuffer;
 
+use crate::{
+    parse, security, BufferStrategy, CreateOptions, CreateReport, DeleteOptions, JunctionSpec, ReadOptions,
+    ReparseData, ReparsePoint, ReparseTag, Status,
+};
+
 /// This prefix indicates to NTFS that the path is to be treated as a non-interpreted
 /// path in the virtual file system.
 /// Ref: <https://learn.microsoft.com/windows-hardware/drivers/kernel/object-manager>
@@ -19,48 +31,401 @@ const NT_PREFIX: [u16; 4] = helpers::utf16s(br"\??\");
 /// Disables normalization and bypasses MAX_PATH.
 /// Ref: <https://learn.microsoft.com/en-us/windows/win32/fileio/maximum-file-path-limitation?tabs=registry>
 const VERBATIM_PREFIX: [u16; 4] = helpers::utf16s(br"\\?\");
+/// Device namespace paths, such as `\\.\C:`, name devices rather than directories.
+const DEVICE_PREFIX: [u16; 4] = helpers::utf16s(br"\\.\");
+
+/// Reported for entries that aren't reparse points, as `DeviceIoControl` does.
+const ERROR_NOT_A_REPARSE_POINT: i32 = 4390;
+/// Reported for directories that still have contents, as `RemoveDirectoryW` does.
+const ERROR_DIR_NOT_EMPTY: i32 = 145;
+/// The only errors following a junction fails with that mean its target is
+/// gone, rather than unreachable. `io::ErrorKind::NotFound` also covers
+/// network paths that can't be reached, such as `ERROR_BAD_NETPATH`.
+const TARGET_GONE: [i32; 2] = [2 /* ERROR_FILE_NOT_FOUND */, 3 /* ERROR_PATH_NOT_FOUND */];
 
 const WCHAR_SIZE: u16 = size_of::<u16>() as _;
 
-pub fn create(target: &Path, junction: &Path) -> io::Result<()> {
-    const UNICODE_NULL_SIZE: u16 = WCHAR_SIZE;
-    const MAX_PATH_BUFFER: u16 = c::MAXIMUM_REPARSE_DATA_BUFFER_SIZE as u16
-        - c::REPARSE_DATA_BUFFER_HEADER_SIZE
-        - c::MOUNT_POINT_REPARSE_BUFFER_HEADER_SIZE;
-
-    // We're using low-level APIs to create the junction, and these are more picky about paths.
-    // For example, forward slashes cannot be used as a path separator, so we should try to
-    // canonicalize the path first.
-    let target = helpers::get_full_path(target)?;
-    // Strip Win32 verbatim prefix (\\?\) if present - we add NT prefix (\??\) ourselves
-    let target = target.strip_prefix(VERBATIM_PREFIX.as_slice()).unwrap_or(&target);
+const UNICODE_NULL_SIZE: u16 = WCHAR_SIZE;
+/// Room left for the names after the headers of a mount point reparse data buffer.
+pub const MAX_PATH_BUFFER: u16 = c::MAXIMUM_REPARSE_DATA_BUFFER_SIZE as u16
+    - c::REPARSE_DATA_BUFFER_HEADER_SIZE
+    - c::MOUNT_POINT_REPARSE_BUFFER_HEADER_SIZE;
+
+pub fn create(target: &Path, junction: &Path, options: &CreateOptions) -> io::Result<CreateReport> {
+    check_os_support()?;
+    // Reverted when dropped, on every return below.
+    let _impersonation = match &options.impersonate {
+        Some(token) => Some(helpers::Impersonation::new(token)?),
+        None => None,
+    };
+    let junction = resolve_junction(junction)?;
+    let junction = junction.as_path();
+
+    // Checked before resolving, which strips trailing spaces and dots.
+    if options.strict_target {
+        check_strict_target(target)?;
+    }
+    let mut target = if options.raw_target {
+        raw_target(target)?
+    } else {
+        resolve_target(target)?
+    };
+    // Only needs the resolved paths, so checked before touching the volume.
+    check_not_self_target(&target, junction)?;
+    if !options.skip_filesystem_check {
+        // The junction doesn't exist yet, but the volume path of its parent is the same.
+        let parent = junction.parent().unwrap_or(junction);
+        let (flags, name) = helpers::volume_information(parent)?;
+        check_filesystem(flags, &name)?;
+    }
+    // As given, before the steps below may replace it with its on-disk form.
+    let input = if options.print_name_from_input {
+        Some(target.clone())
+    } else {
+        None
+    };
+    if options.expand_short_names {
+        target = expand_short_names(&target)?;
+    }
+    if options.validate_target_open {
+        target = open_target(&target)?;
+    }
+    let print_name = if options.empty_print_name {
+        Cow::Borrowed(&[][..])
+    } else {
+        default_print_name(input.as_deref().unwrap_or(&target))
+    };
+    // Checked before creating the directory, so that an overlong target
+    // doesn't leave it behind.
+    print_name_len(name_lengths(target.len())?.0, &print_name)?;
+    let times = if options.inherit_target_times {
+        let metadata = fs::metadata(OsString::from_wide(&default_print_name(&target)))?;
+        Some((metadata.creation_time(), metadata.last_write_time()))
+    } else {
+        None
+    };
+    // Held until the junction is created.
+    let _parents = if options.no_follow_parents {
+        helpers::pin_parents(junction)?
+    } else {
+        Vec::new()
+    };
+    if options.overwrite {
+        remove_existing(&target, junction, options.force_replace_directory)?;
+    }
     fs::create_dir(junction)?;
-    let file = helpers::open_reparse_point(junction, true)?;
+    // Removed again if any step below fails, so no half-made junction is left.
+    let created = scopeguard::guard(junction, |junction| {
+        let _ = fs::remove_dir(junction);
+    });
+    let file = match helpers::retry_sharing_violation(options.retry, || helpers::open_reparse_point(junction, true)) {
+        Ok(file) => file,
+        // The new directory only has the ACL inherited from its parent, which
+        // granted creating it but not writing to it.
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "the ACL the junction directory inherits from its parent denies writing its reparse point",
+            ));
+        }
+        Err(e) => return Err(e),
+    };
+    let prefix: &[u16] = if options.no_nt_prefix { &[] } else { &NT_PREFIX };
+    let encoded = encode_mount_point_with_prefix(prefix, &target, &print_name, options.buffer_strategy)?;
+    let report = write_mount_point(&file, encoded)?;
+    // Set after writing the reparse point, which updates the last write time.
+    if let Some((created, written)) = times {
+        helpers::set_file_times(&file, created, written)?;
+    }
+    // The reparse point is opened without sharing, so close it first.
+    drop(file);
+    if options.attributes != 0 {
+        helpers::set_attributes(junction, options.attributes)?;
+    }
+    if options.owner.is_some() || options.integrity_level.is_some() {
+        let mut information = 0;
+        if options.owner.is_some() {
+            information |= c::OWNER_SECURITY_INFORMATION;
+        }
+        if options.integrity_level.is_some() {
+            information |= c::LABEL_SECURITY_INFORMATION;
+        }
+        let descriptor = security::self_relative_descriptor(options.owner.as_ref(), options.integrity_level);
+        helpers::set_security(junction, information, &descriptor)?;
+    }
+    scopeguard::ScopeGuard::into_inner(created);
+    Ok(report)
+}
 
-    // SubstituteName = "\??\" + target (NT path)
-    let substitute_len_in_bytes = {
-        let len = NT_PREFIX.len().saturating_add(target.len());
-        let min_len = cmp::min(len, u16::MAX as usize) as u16;
-        min_len.saturating_mul(WCHAR_SIZE)
+/// Removes what `create` overwrites at `junction`: a directory reparse point,
+/// or a plain directory that is empty, or also one with contents if `force`.
+fn remove_existing(target: &[u16], junction: &Path, force: bool) -> io::Result<()> {
+    let metadata = match fs::symlink_metadata(junction) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
     };
+    if metadata.file_attributes() & c::FILE_ATTRIBUTE_DIRECTORY == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "`junction` already exists as a file",
+        ));
+    }
+    // Junctions and directory symbolic links are removed without following them.
+    if metadata.file_attributes() & c::FILE_ATTRIBUTE_REPARSE_POINT != 0 {
+        return fs::remove_dir(junction);
+    }
+    match fs::remove_dir(junction) {
+        Err(e) if e.raw_os_error() == Some(ERROR_DIR_NOT_EMPTY) && force => {
+            let target = PathBuf::from(OsString::from_wide(target));
+            // Targets are stored without the verbatim prefix the junction has.
+            let wide: Vec<u16> = junction.as_os_str().encode_wide().collect();
+            let unprefixed = wide.strip_prefix(VERBATIM_PREFIX.as_slice()).unwrap_or(&wide);
+            if strip_prefix_ignore_case(&target, Path::new(&OsString::from_wide(unprefixed))).is_some() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "`target` lies inside the directory `junction` would replace",
+                ));
+            }
+            fs::remove_dir_all(junction)
+        }
+        result => result,
+    }
+}
 
-    // PrintName = target (Win32 path, without the \??\ prefix)
-    let print_name_len_in_bytes = {
-        let min_len = cmp::min(target.len(), u16::MAX as usize) as u16;
-        min_len.saturating_mul(WCHAR_SIZE)
+pub fn fits(target: &Path) -> io::Result<bool> {
+    // The same resolved lengths `create` checks, not the length as given.
+    let target = resolve_target(target)?;
+    Ok(name_lengths(target.len())
+        .is_ok_and(|(substitute, _)| print_name_len(substitute, &default_print_name(&target)).is_ok()))
+}
+
+#[cfg(feature = "nt-api")]
+pub fn create_relative_to_handle(parent: BorrowedHandle<'_>, leaf: &OsStr, target: &Path) -> io::Result<()> {
+    check_os_support()?;
+    let wide: Vec<u16> = leaf.encode_wide().collect();
+    // Anything but a single component would be resolved against `parent`.
+    let separator = |unit: u16| unit == u16::from(b'\\') || unit == u16::from(b'/');
+    if wide.is_empty() || wide.iter().copied().any(separator) || leaf == "." || leaf == ".." {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "`leaf` is not a single file name",
+        ));
+    }
+    // `NtCreateFile` would create these, though Win32 paths can't reach them.
+    if is_reserved_name(leaf) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "`leaf` is a reserved device name",
+        ));
+    }
+    let target = resolve_target(target)?;
+    let print_name = default_print_name(&target);
+    // Checked before creating the directory, like `create` does.
+    print_name_len(name_lengths(target.len())?.0, &print_name)?;
+    let file = helpers::create_dir_at(parent.as_raw_handle(), &wide)?;
+    set_mount_point(&file, &target, &print_name)?;
+    Ok(())
+}
+
+/// Resolves the path of a junction to be created once, so that a concurrent
+/// change of the current directory can't make the steps creating it act on
+/// different directories.
+fn resolve_junction(junction: &Path) -> io::Result<PathBuf> {
+    if junction.file_name().is_some_and(is_reserved_name) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "`junction` is a reserved device name",
+        ));
+    }
+    // Verbatim, so that junctions deeper than `MAX_PATH` can be created and
+    // opened by the APIs that don't lift the limit themselves.
+    let junction = PathBuf::from(OsString::from_wide(&to_verbatim(helpers::get_full_path(junction)?)));
+    // Drive and share roots have no parent, and already exist as directories.
+    if junction.parent().is_none() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "`junction` is the root of a volume",
+        ));
+    }
+    Ok(junction)
+}
+
+/// Adds the verbatim prefix to the absolute `path`, as `\\?\C:\...` or
+/// `\\?\UNC\server\share\...`, unless it has a verbatim or device prefix
+/// already.
+fn to_verbatim(path: Vec<u16>) -> Vec<u16> {
+    const UNC_PREFIX: [u16; 8] = helpers::utf16s(br"\\?\UNC\");
+    let sep = u16::from(b'\\');
+    if path.starts_with(&VERBATIM_PREFIX) || path.starts_with(&DEVICE_PREFIX) {
+        return path;
+    }
+    match path.as_slice() {
+        [a, b, rest @ ..] if *a == sep && *b == sep => UNC_PREFIX.iter().chain(rest).copied().collect(),
+        [drive, colon, ..] if *colon == u16::from(b':') && is_ascii_alpha(*drive) => {
+            VERBATIM_PREFIX.iter().chain(&path).copied().collect()
+        }
+        _ => path,
+    }
+}
+
+/// Resolves `target` to the absolute path, without any prefix, that a junction
+/// to it stores.
+fn resolve_target(target: &Path) -> io::Result<Vec<u16>> {
+    // Accepted: drive absolute, relative, UNC, and verbatim (`\\?\`) paths.
+    let wide: Vec<u16> = target.as_os_str().encode_wide().collect();
+    let target = match wide.strip_prefix(VERBATIM_PREFIX.as_slice()) {
+        // Verbatim paths are fully qualified already, and must not be
+        // reinterpreted. We add NT prefix (\??\) ourselves.
+        Some(verbatim) => verbatim.to_vec(),
+        None => {
+            // We're using low-level APIs to create the junction, and these are more picky about paths.
+            // For example, forward slashes cannot be used as a path separator, so we should try to
+            // canonicalize the path first.
+            let target = helpers::get_full_path(target)?;
+            if target.starts_with(&DEVICE_PREFIX) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "`target` is a device path (`\\\\.\\`), which is not a valid junction target",
+                ));
+            }
+            // Forward slashes may still spell out a verbatim prefix.
+            match target.strip_prefix(VERBATIM_PREFIX.as_slice()) {
+                Some(verbatim) => verbatim.to_vec(),
+                // The NT path of `\\server\share` is `\??\UNC\server\share`.
+                None => match target.strip_prefix(&helpers::utf16s(br"\\")[..]) {
+                    Some(unc) => helpers::utf16s(br"UNC\").iter().chain(unc).copied().collect(),
+                    None => target,
+                },
+            }
+        }
     };
+    let target = target.as_slice();
+    // Verbatim paths are left as is, and some consumers reject `.` or `..` in
+    // a junction's target.
+    let collapsed = collapse_dots(target);
+    let target = collapsed.as_deref().unwrap_or(target);
+    // An empty PrintName breaks junctions across Windows Container layer snapshots,
+    // so never write one.
+    if target.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "`target` resolves to an empty path",
+        ));
+    }
+    Ok(target.to_vec())
+}
 
-    // Check for buffer overflow: both names + their null terminators must fit
-    let total_path_buffer = substitute_len_in_bytes
-        .saturating_add(UNICODE_NULL_SIZE)
-        .saturating_add(print_name_len_in_bytes)
-        .saturating_add(UNICODE_NULL_SIZE);
-    if total_path_buffer > MAX_PATH_BUFFER {
-        return Err(io::Error::new(io::ErrorKind::InvalidInput, "`target` is too long"));
+/// Takes `target` as the junction stores it, only stripping a verbatim prefix,
+/// if it is absolute, or resolves it as usual otherwise.
+fn raw_target(target: &Path) -> io::Result<Vec<u16>> {
+    if !target.is_absolute() {
+        return resolve_target(target);
     }
+    let wide: Vec<u16> = target.as_os_str().encode_wide().collect();
+    if wide.starts_with(&DEVICE_PREFIX) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "`target` is a device path (`\\\\.\\`), which is not a valid junction target",
+        ));
+    }
+    Ok(match wide.strip_prefix(VERBATIM_PREFIX.as_slice()) {
+        Some(verbatim) => verbatim.to_vec(),
+        None => wide,
+    })
+}
+
+/// The PrintName stored for the resolved `target`: the target itself, or
+/// for a volume GUID target, which isn't a usable path without it, the target
+/// with the verbatim prefix `\\?\`, and for a UNC target, stored as
+/// `UNC\server\share`, the usual `\\server\share` form.
+fn default_print_name(target: &[u16]) -> Cow<'_, [u16]> {
+    if parse::split_volume_guid(target).is_some() {
+        Cow::Owned(VERBATIM_PREFIX.iter().chain(target).copied().collect())
+    } else if let Some(unc) = target.strip_prefix(&helpers::utf16s(br"UNC\")[..]) {
+        Cow::Owned(helpers::utf16s(br"\\").iter().chain(unc).copied().collect())
+    } else {
+        Cow::Borrowed(target)
+    }
+}
+
+/// Writes a mount point reparse point to `target`, an already resolved path,
+/// on the directory opened as `file`.
+///
+/// `print_name` is the [`default_print_name`], unless reproducing legacy
+/// junctions.
+fn set_mount_point(file: &File, target: &[u16], print_name: &[u16]) -> io::Result<CreateReport> {
+    write_mount_point(file, encode_mount_point(target, print_name)?)
+}
+
+/// Writes a reparse data buffer from [`encode_mount_point`] on the directory
+/// opened as `file`.
+fn write_mount_point(file: &File, encoded: (BytesAsReparseDataBuffer, CreateReport)) -> io::Result<CreateReport> {
+    let (mut data, report) = encoded;
+    // SAFETY: every field and name up to `in_buffer_size` was written.
+    validate_buffer(unsafe { data.assume_init_bytes(usize::from(report.in_buffer_size)) })?;
+    helpers::set_reparse_point(
+        file.as_raw_handle(),
+        data.as_mut_ptr(),
+        u32::from(report.in_buffer_size),
+    )?;
+    Ok(report)
+}
+
+/// Checks that the fields of the mount point reparse data buffer `buf`, as
+/// [`encode_mount_point`] lays it out, agree with each other and with its
+/// size, so that a bug in encoding never writes a malformed buffer to disk.
+///
+/// Errors with `InvalidData` otherwise.
+fn validate_buffer(buf: &[u8]) -> io::Result<()> {
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, format!("malformed reparse data: {}", msg));
+    let data_len = usize::from(parse::data_len(buf)?);
+    if parse::HEADER_SIZE + data_len != buf.len() {
+        return Err(invalid("ReparseDataLength doesn't match the buffer size"));
+    }
+    // Checks that both names lie within the path buffer.
+    parse::parse_reparse_buffer(buf)?;
+    let field = |i: usize| usize::from(u16::from_le_bytes([buf[8 + 2 * i], buf[9 + 2 * i]]));
+    let (substitute_offset, substitute_len) = (field(0), field(1));
+    let (print_name_offset, print_name_len) = (field(2), field(3));
+    let null = usize::from(UNICODE_NULL_SIZE);
+    if substitute_offset != 0 || print_name_offset != substitute_len + null {
+        return Err(invalid(
+            "the PrintName doesn't follow the SubstituteName and its null terminator",
+        ));
+    }
+    if parse::MOUNT_POINT_HEADER_SIZE + print_name_offset + print_name_len + null != data_len {
+        return Err(invalid(
+            "ReparseDataLength doesn't end after the PrintName and its null terminator",
+        ));
+    }
+    Ok(())
+}
+
+/// Encodes a mount point reparse data buffer for `target`, an already
+/// resolved path, along with the sizes written.
+fn encode_mount_point(target: &[u16], print_name: &[u16]) -> io::Result<(BytesAsReparseDataBuffer, CreateReport)> {
+    encode_mount_point_with_prefix(&NT_PREFIX, target, print_name, BufferStrategy::PerCall)
+}
 
+/// Like [`encode_mount_point`], but with `prefix`, which is either the
+/// [`NT_PREFIX`] or empty, before `target` in the SubstituteName, and with
+/// the buffer allocated following `strategy`.
+fn encode_mount_point_with_prefix(
+    prefix: &[u16],
+    target: &[u16],
+    print_name: &[u16],
+    strategy: BufferStrategy,
+) -> io::Result<(BytesAsReparseDataBuffer, CreateReport)> {
+    // SubstituteName = "\??\" + target (NT path)
+    // PrintName = target (Win32 path, without the \??\ prefix)
+    let (substitute_len_in_bytes, _) = name_lengths(target.len())?;
+    // `name_lengths` counts the NT prefix, which is at least as long.
+    let substitute_len_in_bytes = substitute_len_in_bytes - (NT_PREFIX.len() - prefix.len()) as u16 * WCHAR_SIZE;
+    // Usually derived from `target`, but `apply_spec` may store any PrintName.
+    let print_name_len_in_bytes = print_name_len(substitute_len_in_bytes, print_name)?;
     // Redefine the above char array into a ReparseDataBuffer we can work with
-    let mut data = BytesAsReparseDataBuffer::new();
+    let mut data = BytesAsReparseDataBuffer::with_strategy(strategy);
     let rdb = data.as_mut_ptr();
     let in_buffer_size: u16 = unsafe {
         // Set the type of reparse point we are creating
@@ -78,8 +443,8 @@ pub fn create(target: &Path, junction: &Path) -> io::Result<()> {
         let mut path_buffer_ptr: *mut u16 = addr_of_mut!((*rdb).ReparseBuffer.PathBuffer).cast();
 
         // Write SubstituteName: "\??\" + target
-        copy_nonoverlapping(NT_PREFIX.as_ptr(), path_buffer_ptr, NT_PREFIX.len());
-        path_buffer_ptr = path_buffer_ptr.add(NT_PREFIX.len());
+        copy_nonoverlapping(prefix.as_ptr(), path_buffer_ptr, prefix.len());
+        path_buffer_ptr = path_buffer_ptr.add(prefix.len());
         copy_nonoverlapping(target.as_ptr(), path_buffer_ptr, target.len());
         path_buffer_ptr = path_buffer_ptr.add(target.len());
 
@@ -88,8 +453,8 @@ pub fn create(target: &Path, junction: &Path) -> io::Result<()> {
         path_buffer_ptr = path_buffer_ptr.add(1);
 
         // Write PrintName: target (Win32 path without \??\ prefix)
-        copy_nonoverlapping(target.as_ptr(), path_buffer_ptr, target.len());
-        path_buffer_ptr = path_buffer_ptr.add(target.len());
+        copy_nonoverlapping(print_name.as_ptr(), path_buffer_ptr, print_name.len());
+        path_buffer_ptr = path_buffer_ptr.add(print_name.len());
 
         // Null terminator after PrintName
         path_buffer_ptr.write(0);
@@ -104,97 +469,2250 @@ pub fn create(target: &Path, junction: &Path) -> io::Result<()> {
         size.wrapping_add(c::REPARSE_DATA_BUFFER_HEADER_SIZE)
     };
 
-    helpers::set_reparse_point(file.as_raw_handle(), rdb, u32::from(in_buffer_size))
+    let report = CreateReport {
+        substitute_len_in_bytes,
+        print_name_len_in_bytes,
+        in_buffer_size,
+    };
+    Ok((data, report))
+}
+
+/// Writes the raw reparse data buffer `buf` on the reparse point opened as
+/// `file`.
+fn write_reparse_data(file: &File, buf: &[u8]) -> io::Result<()> {
+    let mut data = BytesAsReparseDataBuffer::new();
+    // Callers pass buffers that `parse` accepted, which are never larger.
+    let len = buf.len().min(parse::MAXIMUM_BUFFER_SIZE);
+    unsafe { copy_nonoverlapping(buf.as_ptr(), data.as_mut_ptr().cast::<u8>(), len) };
+    helpers::set_reparse_point(file.as_raw_handle(), data.as_mut_ptr(), len as u32)
+}
+
+pub fn build_reparse_data(target: &Path) -> io::Result<ReparseData> {
+    let target = resolve_target(target)?;
+    let (data, report) = encode_mount_point(&target, &default_print_name(&target))?;
+    let len = usize::from(report.in_buffer_size);
+    // SAFETY: every field and name up to `in_buffer_size` was written.
+    let bytes = unsafe { data.assume_init_bytes(len) }.to_vec();
+    Ok(ReparseData { bytes, report })
+}
+
+pub fn apply(data: &ReparseData, junction: &Path) -> io::Result<()> {
+    // Checked again, since the buffer is written as is.
+    let mount_point = parse::parse_reparse_buffer(&data.bytes)?;
+    if parse::buffer_len(&data.bytes)? != data.bytes.len() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "reparse data length doesn't match its header",
+        ));
+    }
+    check_os_support()?;
+    let junction = resolve_junction(junction)?;
+    let target: Vec<u16> = mount_point.substitute_name.units().collect();
+    check_not_self_target(parse::strip_nt_prefix(&target), &junction)?;
+    fs::create_dir(&junction)?;
+    let file = helpers::open_reparse_point(&junction, true)?;
+    write_reparse_data(&file, &data.bytes)
+}
+
+/// Joins `drive` and `relative` into `<drive>:\<relative>`.
+pub fn drive_target(drive: char, relative: &Path) -> io::Result<PathBuf> {
+    if !drive.is_ascii_alphabetic() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "`drive` is not a drive letter",
+        ));
+    }
+    // A root or a prefix, such as in `\dir` or `C:dir`, would replace the
+    // drive when joined.
+    if matches!(
+        relative.components().next(),
+        Some(Component::Prefix(_) | Component::RootDir)
+    ) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "`relative_target` is not a relative path",
+        ));
+    }
+    Ok(PathBuf::from(format!(r"{}:\", drive.to_ascii_uppercase())).join(relative))
+}
+
+/// Returns the SubstituteName and PrintName lengths, in bytes, for a target of
+/// `target_len` UTF-16 units.
+///
+/// Both names and their null terminators must fit in the reparse data buffer.
+/// Errors rather than clamping, since a clamped length would silently truncate
+/// the stored target.
+fn name_lengths(target_len: usize) -> io::Result<(u16, u16)> {
+    let too_long = || io::Error::new(io::ErrorKind::InvalidInput, "`target` is too long");
+    let wchar_size = usize::from(WCHAR_SIZE);
+    let print_name = target_len.checked_mul(wchar_size).ok_or_else(too_long)?;
+    let substitute = print_name
+        .checked_add(NT_PREFIX.len() * wchar_size)
+        .ok_or_else(too_long)?;
+    let total = substitute
+        .checked_add(print_name)
+        .and_then(|len| len.checked_add(2 * usize::from(UNICODE_NULL_SIZE)))
+        .ok_or_else(too_long)?;
+    if total > usize::from(MAX_PATH_BUFFER) {
+        return Err(too_long());
+    }
+    // Both are at most `MAX_PATH_BUFFER`, so they fit in a `u16`.
+    Ok((substitute as u16, print_name as u16))
+}
+
+/// Returns the length in bytes of `print_name`, checking that it fits in the
+/// reparse data buffer after a SubstituteName of `substitute_len_in_bytes`.
+fn print_name_len(substitute_len_in_bytes: u16, print_name: &[u16]) -> io::Result<u16> {
+    print_name
+        .len()
+        .checked_mul(usize::from(WCHAR_SIZE))
+        .filter(|&len| {
+            usize::from(substitute_len_in_bytes) + len + 2 * usize::from(UNICODE_NULL_SIZE)
+                <= usize::from(MAX_PATH_BUFFER)
+        })
+        .map(|len| len as u16)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "the PrintName is too long"))
+}
+
+/// Lexically removes `.` and `..` components from an absolute `path`, never
+/// going above its root. Returns `None` if there are none to remove.
+fn collapse_dots(path: &[u16]) -> Option<Vec<u16>> {
+    const SEP: u16 = b'\\' as u16;
+    const DOT: u16 = b'.' as u16;
+    let is_dots = |component: &[u16]| matches!(component, [DOT] | [DOT, DOT]);
+    if !path.split(|&unit| unit == SEP).any(is_dots) {
+        return None;
+    }
+    let (root, rest) = path.split_at(root_len(path));
+    let mut components: Vec<&[u16]> = Vec::new();
+    for component in rest.split(|&unit| unit == SEP) {
+        match component {
+            [DOT] => {}
+            [DOT, DOT] => {
+                components.pop();
+            }
+            _ => components.push(component),
+        }
+    }
+    let mut collapsed = root.to_vec();
+    collapsed.extend(components.join(&SEP));
+    Some(collapsed)
+}
+
+/// Length of the root of an absolute path, separator included: `C:\`,
+/// `\\server\share\` or, with the verbatim prefix stripped, `UNC\server\share\`
+/// or `Volume{GUID}\`.
+fn root_len(path: &[u16]) -> usize {
+    const SEP: u16 = b'\\' as u16;
+    if let Some((_, rest)) = parse::split_volume_guid(path) {
+        return path.len() - rest.len();
+    }
+    let unc = if path.starts_with(&[SEP, SEP]) {
+        2
+    } else if path.starts_with(&helpers::utf16s(br"UNC\")) {
+        4
+    } else {
+        return match path {
+            [drive, colon, SEP, ..] if *colon == u16::from(b':') && is_ascii_alpha(*drive) => 3,
+            _ => 0,
+        };
+    };
+    // Skip the server and share names.
+    let mut len = unc;
+    for _ in 0..2 {
+        match path[len..].iter().position(|&unit| unit == SEP) {
+            Some(sep) => len += sep + 1,
+            None => return path.len(),
+        }
+    }
+    len
+}
+
+/// Whether `name` refers to a DOS device such as `NUL` or `COM1` rather than a
+/// file, in any case and with or without an extension.
+/// Ref: <https://learn.microsoft.com/en-us/windows/win32/fileio/naming-a-file#naming-conventions>
+fn is_reserved_name(name: &OsStr) -> bool {
+    const RESERVED: [&str; 6] = ["CON", "PRN", "AUX", "NUL", "CONIN$", "CONOUT$"];
+    let name = match name.to_str() {
+        Some(name) => name,
+        None => return false,
+    };
+    let stem = name.split('.').next().unwrap_or(name).trim_end_matches(' ');
+    let stem = stem.to_ascii_uppercase();
+    if RESERVED.contains(&stem.as_str()) {
+        return true;
+    }
+    // COM1-COM9 and LPT1-LPT9, including the superscript digits ¹, ² and ³.
+    match (stem.get(..3), stem.get(3..)) {
+        (Some("COM" | "LPT"), Some(n)) => {
+            let mut digits = n.chars();
+            matches!(
+                (digits.next(), digits.next()),
+                (Some('1'..='9' | '¹' | '²' | '³'), None)
+            )
+        }
+        _ => false,
+    }
+}
+
+/// `dwPlatformId` of the Windows NT family, as opposed to Windows 9x.
+const VER_PLATFORM_WIN32_NT: u32 = 2;
+
+/// Checks that the running Windows supports junctions, which first shipped
+/// with NTFS 5 in Windows 2000 (NT 5.0).
+fn check_os_version(platform: u32, major: u32, minor: u32) -> io::Result<()> {
+    if platform != VER_PLATFORM_WIN32_NT || major < 5 {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!(
+                "junctions need Windows 2000 or later, but this is Windows {}.{} (platform {})",
+                major, minor, platform
+            ),
+        ));
+    }
+    Ok(())
+}
+
+fn check_os_support() -> io::Result<()> {
+    let (platform, major, minor) = helpers::os_version()?;
+    check_os_version(platform, major, minor)
+}
+
+fn check_filesystem(flags: u32, name: &OsStr) -> io::Result<()> {
+    if flags & c::FILE_SUPPORTS_REPARSE_POINTS == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!(
+                "the {} file system of `junction` does not support reparse points",
+                name.to_string_lossy()
+            ),
+        ));
+    }
+    Ok(())
+}
+
+fn check_strict_target(target: &Path) -> io::Result<()> {
+    if target.as_os_str().encode_wide().any(|unit| unit < 0x20 || unit == 0x7f) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "`target` contains control characters",
+        ));
+    }
+    let name: Vec<u16> = match target.file_name() {
+        Some(name) => name.encode_wide().collect(),
+        None => return Ok(()),
+    };
+    let (space, dot) = (u16::from(b' '), u16::from(b'.'));
+    if name.first() == Some(&space) || name.last().is_some_and(|&last| last == space || last == dot) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "the final component of `target` starts or ends with a space, or ends with a dot",
+        ));
+    }
+    Ok(())
+}
+
+/// Fails if the resolved `target` is the resolved `junction` itself, which
+/// Windows would treat as the same entry whatever their casing.
+fn check_not_self_target(target: &[u16], junction: &Path) -> io::Result<()> {
+    let junction: Vec<u16> = junction.as_os_str().encode_wide().collect();
+    let junction = junction.strip_prefix(VERBATIM_PREFIX.as_slice()).unwrap_or(&junction);
+    if same_path(target, junction) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "`target` and `junction` are the same path",
+        ));
+    }
+    Ok(())
+}
+
+/// Whether two absolute paths name the same entry, comparing them
+/// case-insensitively like NTFS does by default.
+fn same_path(a: &[u16], b: &[u16]) -> bool {
+    let trimmed = |path: &[u16]| {
+        let len = path
+            .iter()
+            .rposition(|&unit| unit != u16::from(b'\\'))
+            .map_or(0, |i| i + 1);
+        OsString::from_wide(&path[..len])
+    };
+    helpers::eq_ignore_case(&trimmed(a), &trimmed(b))
+}
+
+/// Expands the short names in the resolved `target`, which must exist.
+fn expand_short_names(target: &[u16]) -> io::Result<Vec<u16>> {
+    // The verbatim prefix keeps the path exactly as resolved.
+    let path: Vec<u16> = VERBATIM_PREFIX.iter().chain(target).copied().collect();
+    let long = helpers::get_long_path(Path::new(&OsString::from_wide(&path)))?;
+    match long.strip_prefix(VERBATIM_PREFIX.as_slice()) {
+        Some(long) => Ok(long.to_vec()),
+        None => Ok(long),
+    }
+}
+
+/// Opens the resolved `target` as the junction would, and returns the path
+/// it actually leads to, without any prefix.
+fn open_target(target: &[u16]) -> io::Result<Vec<u16>> {
+    // The verbatim prefix opens exactly what `\??\` would.
+    let path: Vec<u16> = VERBATIM_PREFIX.iter().chain(target).copied().collect();
+    let opened = helpers::final_directory_path(Path::new(&OsString::from_wide(&path)))?;
+    match opened.strip_prefix(VERBATIM_PREFIX.as_slice()) {
+        Some(opened) => Ok(opened.to_vec()),
+        None => Ok(opened),
+    }
+}
+
+/// Access requested by [`delete`] by default. `GENERIC_WRITE` includes the
+/// `FILE_WRITE_ATTRIBUTES` access that removing a reparse point needs.
+pub const DELETE_ACCESS: u32 = c::GENERIC_READ | c::GENERIC_WRITE;
+
+pub fn delete(junction: &Path, options: &DeleteOptions) -> io::Result<()> {
+    // Retried separately, since once the reparse point is gone opening and
+    // deleting it again would fail.
+    helpers::retry_transient(options.retry, || {
+        let file = helpers::open_reparse_point_with_access(junction, options.access, true)?;
+        helpers::delete_reparse_point(file.as_raw_handle())
+    })?;
+    if options.remove_dir {
+        helpers::retry_transient(options.retry, || fs::remove_dir(junction))?;
+    }
+    Ok(())
+}
+
+pub fn delete_many<I>(junctions: I, options: &DeleteOptions) -> Vec<(PathBuf, io::Result<()>)>
+where
+    I: IntoIterator<Item = PathBuf>,
+{
+    junctions
+        .into_iter()
+        .map(|junction| {
+            let result = delete(&junction, options);
+            (junction, result)
+        })
+        .collect()
 }
 
-pub fn delete(junction: &Path) -> io::Result<()> {
+pub fn disable(junction: &Path) -> io::Result<PathBuf> {
+    let target = get_target(junction, &ReadOptions::new())?;
+    delete(junction, &DeleteOptions::new())?;
+    Ok(target)
+}
+
+pub fn enable(junction: &Path, target: &Path) -> io::Result<()> {
+    let target = resolve_target(target)?;
+    // Fails if `junction` is not an empty directory.
     let file = helpers::open_reparse_point(junction, true)?;
-    helpers::delete_reparse_point(file.as_raw_handle())
+    set_mount_point(&file, &target, &default_print_name(&target)).map(drop)
 }
 
-pub fn exists(junction: &Path) -> io::Result<bool> {
-    if !junction.exists() {
-        return Ok(false);
+pub fn ensure(target: &Path, junction: &Path, options: &CreateOptions) -> io::Result<bool> {
+    // Held until the junction is checked, and created if missing.
+    let _lock = if options.lock {
+        let mut name = junction.file_name().unwrap_or_default().to_owned();
+        name.push(".lock");
+        Some(helpers::lock_file(&junction.with_file_name(name))?)
+    } else {
+        None
+    };
+    match fs::symlink_metadata(junction) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return create(target, junction, options).map(|_| true),
+        Err(e) => return Err(e),
+        Ok(_) => {}
     }
-    let file = helpers::open_reparse_point(junction, false)?;
-    // Allocate enough space to fit the maximum sized reparse data buffer
-    let mut data = BytesAsReparseDataBuffer::new();
+    let existing = get_target(junction, &ReadOptions::new())?;
+    let existing: Vec<u16> = existing.as_os_str().encode_wide().collect();
+    if same_path(&existing, &resolve_target(target)?) {
+        Ok(false)
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "`junction` already exists with another target",
+        ))
+    }
+}
+
+pub fn exists(junction: &Path, options: &ReadOptions) -> io::Result<bool> {
+    // Only the entry itself is checked. Following a junction to a
+    // disconnected network share hangs until the SMB timeout, and following
+    // other reparse points, such as Projected File System placeholders, may
+    // call back into the application providing them.
+    match fs::symlink_metadata(junction) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e),
+        Ok(_) => {}
+    }
+    let file = open_to_read(junction, options)?;
     // XXX: Could also use FindFirstFile to read the reparse point type
     // Ref https://learn.microsoft.com/en-us/windows/win32/fileio/reparse-point-tags
-    helpers::get_reparse_data_point(file.as_raw_handle(), data.as_mut_ptr())?;
-    // SATETY: rdb should be initialized now
-    let rdb = unsafe { data.assume_init() };
     // The reparse tag indicates if this is a junction or not
-    Ok(rdb.ReparseTag == c::IO_REPARSE_TAG_MOUNT_POINT)
+    Ok(tag_from_file(&file)? == ReparseTag::MountPoint)
 }
 
-pub fn get_target(junction: &Path) -> io::Result<PathBuf> {
-    // MSRV(1.63): use Path::try_exists instead
-    if !junction.exists() {
-        return Err(io::Error::new(io::ErrorKind::NotFound, "`junction` does not exist"));
+pub fn get_target(junction: &Path, options: &ReadOptions) -> io::Result<PathBuf> {
+    // Not followed, so that an unreachable target doesn't stall reading it.
+    match fs::symlink_metadata(junction) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "`junction` does not exist"))
+        }
+        Err(e) => return Err(e),
+        Ok(_) => {}
     }
-    let file = helpers::open_reparse_point(junction, false)?;
-    let mut data = BytesAsReparseDataBuffer::new();
-    helpers::get_reparse_data_point(file.as_raw_handle(), data.as_mut_ptr())?;
-    // SAFETY: rdb should be initialized now
-    let rdb = unsafe { data.assume_init() };
-    if rdb.ReparseTag == c::IO_REPARSE_TAG_MOUNT_POINT {
-        let offset = rdb.ReparseBuffer.SubstituteNameOffset / WCHAR_SIZE;
-        let len = rdb.ReparseBuffer.SubstituteNameLength / WCHAR_SIZE;
-        let wide = unsafe {
-            let buf = rdb.ReparseBuffer.PathBuffer.as_ptr().add(offset as usize);
-            slice::from_raw_parts(buf, len as usize)
-        };
-        // In case of "\??\C:\foo\bar"
-        let wide = wide.strip_prefix(&NT_PREFIX).unwrap_or(wide);
-        Ok(PathBuf::from(OsString::from_wide(wide)))
+    let read = open_to_read(junction, options).and_then(|file| get_target_from_file(&file));
+    match read {
+        // The listing still tells whether it is a junction at all, though
+        // only the reparse data holds its target.
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => match find_tag(junction)? {
+            ReparseTag::MountPoint => Err(e),
+            _ => Err(io::Error::new(io::ErrorKind::Other, "not a reparse tag mount point")),
+        },
+        read => read,
+    }
+}
+
+pub fn reparse_tag(path: &Path) -> io::Result<ReparseTag> {
+    match helpers::open_reparse_point(path, false).and_then(|file| tag_from_file(&file)) {
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => find_tag(path),
+        read => read,
+    }
+}
+
+/// Reads the tag of the reparse point at `path` from its parent's listing,
+/// for when opening it or reading its reparse data is denied.
+fn find_tag(path: &Path) -> io::Result<ReparseTag> {
+    match helpers::find_entry(path)?.reparse_tag {
+        Some(tag) => Ok(ReparseTag::from(tag)),
+        None => Err(io::Error::from_raw_os_error(ERROR_NOT_A_REPARSE_POINT)),
+    }
+}
+
+/// Opens the reparse point at `junction` for reading with `options`.
+fn open_to_read(junction: &Path, options: &ReadOptions) -> io::Result<helpers::ReparseHandle> {
+    if !options.no_follow_parents {
+        return helpers::retry_sharing_violation(options.retry, || helpers::open_reparse_point(junction, false));
+    }
+    // Absolute, so that the parents pinned are those the path is opened through.
+    let junction = PathBuf::from(OsString::from_wide(&helpers::get_full_path(junction)?));
+    // Held until the junction is opened.
+    let _parents = helpers::pin_parents(&junction)?;
+    helpers::retry_sharing_violation(options.retry, || helpers::open_reparse_point(&junction, false))
+}
+
+pub fn get_target_resolve_volume(junction: &Path, options: &ReadOptions) -> io::Result<PathBuf> {
+    let target = get_target(junction, options)?;
+    let wide: Vec<u16> = target.as_os_str().encode_wide().collect();
+    let (volume, rest) = match parse::split_volume_guid(&wide) {
+        Some(split) => split,
+        None => return Ok(target),
+    };
+    match helpers::volume_path_name(volume)? {
+        // The mount path ends with a separator already.
+        Some(mut path) => {
+            path.extend_from_slice(rest);
+            Ok(PathBuf::from(OsString::from_wide(&path)))
+        }
+        None => Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "the target's volume is not mounted at any path",
+        )),
+    }
+}
+
+pub fn get_target_win32(junction: &Path, options: &ReadOptions) -> io::Result<PathBuf> {
+    let target = get_target(junction, options)?;
+    let wide: Vec<u16> = target.as_os_str().encode_wide().collect();
+    if let Some(drive) = drive_form(&wide) {
+        return Ok(PathBuf::from(OsString::from_wide(drive)));
+    }
+    let (volume, rest) = match parse::split_volume_guid(&wide) {
+        Some(split) => split,
+        None => return Ok(target),
+    };
+    match helpers::volume_path_name(volume)? {
+        // Only a volume mounted at a drive letter gives a drive-letter path.
+        Some(mut path) if drive_form(&path).is_some() => {
+            path.extend_from_slice(rest);
+            Ok(PathBuf::from(OsString::from_wide(&path)))
+        }
+        _ => Ok(target),
+    }
+}
+
+/// Returns the drive-letter path, such as `C:\dir`, that `path` spells out
+/// with or without a verbatim (`\\?\`), device (`\\.\`) or NT (`\??\`)
+/// prefix, or `None` if it doesn't start with a drive.
+fn drive_form(path: &[u16]) -> Option<&[u16]> {
+    let path = [VERBATIM_PREFIX, DEVICE_PREFIX, NT_PREFIX]
+        .iter()
+        .find_map(|prefix| path.strip_prefix(prefix.as_slice()))
+        .unwrap_or(path);
+    match path {
+        [drive, colon, ..] if *colon == u16::from(b':') && is_ascii_alpha(*drive) => Some(path),
+        _ => None,
+    }
+}
+
+pub fn display_target(junction: &Path) -> io::Result<PathBuf> {
+    let buf = raw_reparse_buffer(junction)?;
+    let mount_point = parse::parse_reparse_buffer(&buf)?;
+    let name: Vec<u16> = if mount_point.print_name.is_empty() {
+        let wide: Vec<u16> = mount_point.substitute_name.units().collect();
+        parse::strip_nt_prefix(&wide).to_vec()
     } else {
-        Err(io::Error::new(io::ErrorKind::Other, "not a reparse tag mount point"))
+        mount_point.print_name.units().collect()
+    };
+    Ok(PathBuf::from(OsString::from_wide(&name)))
+}
+
+pub fn matches_spec(junction: &Path, spec: &JunctionSpec) -> io::Result<bool> {
+    let file = helpers::open_reparse_point(junction, false)?;
+    // Plain files and directories aren't junctions, so they can't match.
+    if file.metadata()?.file_attributes() & c::FILE_ATTRIBUTE_REPARSE_POINT == 0 {
+        return Ok(false);
     }
+    Ok(spec_matches(&read_reparse_data(&file)?, spec)?.unwrap_or(false))
 }
 
-#[cfg(test)]
-mod tests {
-    use std::ffi::OsString;
-    use std::os::windows::ffi::OsStringExt;
-    use std::os::windows::io::AsRawHandle;
-    use std::{fs, slice};
+/// Whether the reparse data in `buf` is a mount point as described by `spec`,
+/// or `None` if it is another kind of reparse point.
+fn spec_matches(buf: &[u8], spec: &JunctionSpec) -> io::Result<Option<bool>> {
+    let mount_point = match parse::parse_reparse_buffer(buf) {
+        Ok(mount_point) => mount_point,
+        Err(parse::Error::NotMountPoint { .. }) => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let stored: Vec<u16> = mount_point.substitute_name.units().collect();
+    if !same_path(parse::strip_nt_prefix(&stored), &resolve_target(&spec.target)?) {
+        return Ok(Some(false));
+    }
+    Ok(Some(match &spec.print_name {
+        Some(print_name) => {
+            let stored: Vec<u16> = mount_point.print_name.units().collect();
+            same_path(&stored, &print_name.as_os_str().encode_wide().collect::<Vec<u16>>())
+        }
+        None => true,
+    }))
+}
 
-    use super::*;
+pub fn apply_spec(junction: &Path, spec: &JunctionSpec) -> io::Result<bool> {
+    match fs::symlink_metadata(junction) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => create_spec(junction, spec),
+        Err(e) => Err(e),
+        Ok(metadata) if metadata.file_attributes() & c::FILE_ATTRIBUTE_REPARSE_POINT == 0 => Err(not_a_junction()),
+        Ok(_) => converge_spec(junction, spec),
+    }
+}
 
-    #[test]
-    fn create_populates_print_name() {
-        // Regression test: the junction reparse point must have a non-empty PrintName
-        // so that Windows Container layer snapshots correctly preserve the junction target.
-        let tmpdir = tempfile::tempdir().unwrap();
-        let target = tmpdir.path().join("target");
-        let junction = tmpdir.path().join("junction");
-        fs::create_dir_all(&target).unwrap();
+fn not_a_junction() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        "`junction` already exists and is not a junction",
+    )
+}
 
-        create(&target, &junction).unwrap();
+/// Creates the missing `junction` as described by `spec`.
+fn create_spec(junction: &Path, spec: &JunctionSpec) -> io::Result<bool> {
+    create(&spec.target, junction, &CreateOptions::new())?;
+    // Only a custom PrintName is left to write.
+    if spec.print_name.is_some() && !matches_spec(junction, spec)? {
+        converge_spec(junction, spec)?;
+    }
+    Ok(true)
+}
 
-        // Read back the raw reparse data
-        let file = helpers::open_reparse_point(&junction, false).unwrap();
-        let mut data = cast::BytesAsReparseDataBuffer::new();
-        helpers::get_reparse_data_point(file.as_raw_handle(), data.as_mut_ptr()).unwrap();
-        let rdb = unsafe { data.assume_init() };
+/// Rewrites the existing reparse point `junction` as described by `spec`,
+/// unless it already is.
+fn converge_spec(junction: &Path, spec: &JunctionSpec) -> io::Result<bool> {
+    let file = helpers::open_reparse_point(junction, true)?;
+    match spec_matches(&read_reparse_data(&file)?, spec)? {
+        Some(true) => return Ok(false),
+        Some(false) => {}
+        None => return Err(not_a_junction()),
+    }
+    let target = resolve_target(&spec.target)?;
+    check_not_self_target(&target, &resolve_junction(junction)?)?;
+    let print_name = match &spec.print_name {
+        Some(print_name) => print_name.as_os_str().encode_wide().collect(),
+        None => default_print_name(&target).into_owned(),
+    };
+    set_mount_point(&file, &target, &print_name)?;
+    Ok(true)
+}
 
-        assert_eq!(rdb.ReparseTag, c::IO_REPARSE_TAG_MOUNT_POINT);
+/// The listing of a directory holding junctions passed to `ensure_many`.
+struct SpecDir<'a> {
+    dir: &'a Path,
+    entries: io::Result<Vec<helpers::FindEntry>>,
+    names: Vec<&'a OsStr>,
+}
 
-        // Read PrintName
-        let print_offset = (rdb.ReparseBuffer.PrintNameOffset / WCHAR_SIZE) as usize;
-        let print_len = (rdb.ReparseBuffer.PrintNameLength / WCHAR_SIZE) as usize;
-        let print_name = unsafe {
-            let buf = rdb.ReparseBuffer.PathBuffer.as_ptr().add(print_offset);
-            slice::from_raw_parts(buf, print_len)
+pub fn ensure_many(specs: &[(PathBuf, JunctionSpec)], prune: bool) -> Vec<(PathBuf, io::Result<bool>)> {
+    // Each directory holding a junction is listed once, up front.
+    let mut dirs: Vec<SpecDir<'_>> = Vec::new();
+    for (junction, _) in specs {
+        let dir = junction.parent().unwrap_or(Path::new(""));
+        let name = junction.file_name().unwrap_or_default();
+        match dirs
+            .iter_mut()
+            .find(|listed| helpers::eq_ignore_case(listed.dir.as_os_str(), dir.as_os_str()))
+        {
+            Some(listed) => listed.names.push(name),
+            None => dirs.push(SpecDir {
+                dir,
+                entries: helpers::find_entries(dir),
+                names: vec![name],
+            }),
+        }
+    }
+
+    let mut results = Vec::new();
+    for (junction, spec) in specs {
+        let dir = junction.parent().unwrap_or(Path::new(""));
+        let name = junction.file_name().unwrap_or_default();
+        let listed = dirs
+            .iter()
+            .find(|listed| helpers::eq_ignore_case(listed.dir.as_os_str(), dir.as_os_str()))
+            .expect("listed above");
+        let result = match &listed.entries {
+            Ok(entries) => match entries.iter().find(|entry| helpers::eq_ignore_case(&entry.name, name)) {
+                None => create_spec(junction, spec),
+                Some(entry) if entry.reparse_tag == Some(c::IO_REPARSE_TAG_MOUNT_POINT) => {
+                    converge_spec(junction, spec)
+                }
+                Some(_) => Err(not_a_junction()),
+            },
+            // Left to `apply_spec` to report, such as a missing parent.
+            Err(_) => apply_spec(junction, spec),
         };
+        results.push((junction.clone(), result));
+    }
 
-        // PrintName must not be empty
-        assert!(print_len > 0, "PrintName must not be empty");
+    if prune {
+        let mut options = DeleteOptions::new();
+        options.remove_dir(true);
+        for listed in &dirs {
+            let entries = match &listed.entries {
+                Ok(entries) => entries,
+                Err(_) => continue,
+            };
+            for entry in entries {
+                let wanted = listed
+                    .names
+                    .iter()
+                    .any(|name| helpers::eq_ignore_case(name, &entry.name));
+                if entry.reparse_tag == Some(c::IO_REPARSE_TAG_MOUNT_POINT) && !wanted {
+                    let junction = listed.dir.join(&entry.name);
+                    let result = delete(&junction, &options).map(|()| true);
+                    results.push((junction, result));
+                }
+            }
+        }
+    }
+    results
+}
 
-        // PrintName should match what get_target returns (the Win32 path without \??\ prefix)
-        let print_path = PathBuf::from(OsString::from_wide(print_name));
-        let target_path = get_target(&junction).unwrap();
-        assert_eq!(print_path, target_path, "PrintName should match the target path");
+pub fn resolved_target(junction: &Path) -> io::Result<PathBuf> {
+    // Checked from the attributes alone, leaving the reparse data unread.
+    if fs::symlink_metadata(junction)?.file_attributes() & c::FILE_ATTRIBUTE_REPARSE_POINT == 0 {
+        return Err(io::Error::new(io::ErrorKind::Other, "not a reparse point"));
+    }
+    // Opened following the junction, so this is where it leads.
+    let resolved = helpers::final_directory_path(junction)?;
+    let resolved = match resolved.strip_prefix(VERBATIM_PREFIX.as_slice()) {
+        Some(unc) if unc.starts_with(&helpers::utf16s(br"UNC\")) => {
+            helpers::utf16s(br"\\").iter().chain(&unc[4..]).copied().collect()
+        }
+        Some(rest) => rest.to_vec(),
+        None => resolved,
+    };
+    Ok(PathBuf::from(OsString::from_wide(&resolved)))
+}
+
+pub fn get_target_relative_to(junction: &Path, base: &Path) -> io::Result<PathBuf> {
+    let target = strip_verbatim(&get_target(junction, &ReadOptions::new())?);
+    let base = PathBuf::from(OsString::from_wide(&helpers::get_full_path(base)?));
+    relative_to(&target, &strip_verbatim(&base)).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "the target and `base` share no common prefix",
+        )
+    })
+}
+
+pub fn target_within(junction: &Path, allowed_root: &Path) -> io::Result<bool> {
+    let target = get_target(junction, &ReadOptions::new())?;
+    // Resolves `..` components and further links, which could otherwise
+    // lead out of the root.
+    let target = fs::canonicalize(target)?;
+    let allowed_root = fs::canonicalize(allowed_root)?;
+    Ok(strip_prefix_ignore_case(&target, &allowed_root).is_some())
+}
+
+/// Expresses the absolute `path` relative to the absolute `base`, or `None` if
+/// they are on different volumes. Components are compared ignoring ASCII case,
+/// as NTFS does by default.
+fn relative_to(path: &Path, base: &Path) -> Option<PathBuf> {
+    let mut path = path.components().peekable();
+    let mut base = base.components().peekable();
+    // The prefix and root, such as `C:` and `\`, must match.
+    while let Some(component @ (Component::Prefix(_) | Component::RootDir)) = base.peek().copied() {
+        match path.next() {
+            Some(other) if helpers::eq_ignore_case(other.as_os_str(), component.as_os_str()) => base.next(),
+            _ => return None,
+        };
+    }
+    if matches!(path.peek(), Some(Component::Prefix(_) | Component::RootDir)) {
+        return None;
+    }
+    while let (Some(a), Some(b)) = (path.peek(), base.peek()) {
+        if !helpers::eq_ignore_case(a.as_os_str(), b.as_os_str()) {
+            break;
+        }
+        path.next();
+        base.next();
+    }
+    let mut relative: PathBuf = base.map(|_| Component::ParentDir).collect();
+    relative.extend(path);
+    if relative.as_os_str().is_empty() {
+        relative.push(Component::CurDir);
+    }
+    Some(relative)
+}
+
+pub(crate) use helpers::upcase;
+#[cfg(feature = "watch")]
+pub use helpers::{cancel_io, eq_ignore_case, open_dir_for_changes, read_directory_changes};
+
+pub fn tag_from_file(file: &File) -> io::Result<ReparseTag> {
+    let buf = read_reparse_data(file)?;
+    Ok(ReparseTag::from(parse::reparse_tag(&buf)?))
+}
+
+/// Reads the reparse data buffer of the reparse point opened as `file`.
+///
+/// The buffer is returned as bytes so that callers decode it with the bounds-checked
+/// [`parse`] module, rather than reading fields through raw pointers.
+fn read_reparse_data(file: &File) -> io::Result<Vec<u8>> {
+    helpers::get_reparse_data_point(file.as_raw_handle())
+}
+
+pub fn get_target_from_file(file: &File) -> io::Result<PathBuf> {
+    read_mount_point(file)?.ok_or_else(|| io::Error::new(io::ErrorKind::Other, "not a reparse tag mount point"))
+}
+
+/// Reads the target of the mount point opened as `file`, or `None` if it is
+/// another kind of reparse point.
+fn read_mount_point(file: &File) -> io::Result<Option<PathBuf>> {
+    mount_point_target(&read_reparse_data(file)?)
+}
+
+/// Decodes the target of the mount point reparse data buffer `buf`, or `None`
+/// if it belongs to another kind of reparse point.
+fn mount_point_target(buf: &[u8]) -> io::Result<Option<PathBuf>> {
+    let mount_point = match parse::parse_reparse_buffer(buf) {
+        Ok(mount_point) => mount_point,
+        Err(parse::Error::NotMountPoint { .. }) => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let wide: Vec<u16> = mount_point.substitute_name.units().collect();
+    // In case of "\??\C:\foo\bar"
+    let wide = parse::strip_nt_prefix(&wide);
+    Ok(Some(PathBuf::from(OsString::from_wide(wide))))
+}
+
+pub fn raw_reparse_buffer(path: &Path) -> io::Result<Vec<u8>> {
+    let file = helpers::open_reparse_point(path, false)?;
+    read_reparse_data(&file)
+}
+
+pub fn reparse_data_len(path: &Path) -> io::Result<u16> {
+    Ok(parse::data_len(&raw_reparse_buffer(path)?)?)
+}
+
+pub fn diff_reparse(a: &Path, b: &Path) -> io::Result<Option<parse::ReparseDiff>> {
+    Ok(parse::diff_reparse_buffers(
+        &raw_reparse_buffer(a)?,
+        &raw_reparse_buffer(b)?,
+    ))
+}
+
+pub fn lx_symlink_target(path: &Path) -> io::Result<String> {
+    let raw = raw_reparse_buffer(path)?;
+    Ok(parse::parse_lx_symlink_buffer(&raw)?.to_owned())
+}
+
+pub fn get_symlink_target(path: &Path) -> io::Result<PathBuf> {
+    let file = helpers::open_symlink(path)?;
+    let buf = read_reparse_data(&file)?;
+    let wide: Vec<u16> = parse::parse_symlink_buffer(&buf)?.substitute_name.units().collect();
+    let wide = match wide.strip_prefix(&NT_PREFIX[..]) {
+        // The NT path of `\\server\share` is `\??\UNC\server\share`.
+        Some(unc) if unc.starts_with(&helpers::utf16s(br"UNC\")) => {
+            helpers::utf16s(br"\\").iter().chain(&unc[4..]).copied().collect()
+        }
+        _ => parse::strip_nt_prefix(&wide).to_vec(),
+    };
+    Ok(PathBuf::from(OsString::from_wide(&wide)))
+}
+
+pub fn read_reparse_point(path: &Path, options: &ReadOptions) -> io::Result<ReparsePoint> {
+    let file = open_to_read(path, options)?;
+    let buf = &read_reparse_data(&file)?[..];
+    let (tag, substitute_name, relative) = match parse::reparse_tag(buf)? {
+        // Mount points can only hold absolute paths.
+        parse::IO_REPARSE_TAG_MOUNT_POINT => (
+            ReparseTag::MountPoint,
+            parse::parse_reparse_buffer(buf)?.substitute_name,
+            false,
+        ),
+        parse::IO_REPARSE_TAG_SYMLINK => {
+            let link = parse::parse_symlink_buffer(buf)?;
+            (ReparseTag::Symlink, link.substitute_name, link.relative)
+        }
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "not a reparse tag mount point or symlink",
+            ))
+        }
+    };
+    let wide: Vec<u16> = substitute_name.units().collect();
+    Ok(ReparsePoint {
+        tag,
+        target: PathBuf::from(OsString::from_wide(parse::strip_nt_prefix(&wide))),
+        relative,
+    })
+}
+
+pub fn status(path: &Path) -> io::Result<Status> {
+    // Opens files and directories alike, without following them.
+    let file = match helpers::open_symlink(path) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Status::Missing),
+        file => file?,
+    };
+    let buf = match read_reparse_data(&file) {
+        Err(e) if e.raw_os_error() == Some(ERROR_NOT_A_REPARSE_POINT) => return Ok(Status::NotAReparsePoint),
+        buf => buf?,
+    };
+    drop(file);
+    let name = |name: parse::Name<'_>| {
+        let wide: Vec<u16> = name.units().collect();
+        PathBuf::from(OsString::from_wide(parse::strip_nt_prefix(&wide)))
+    };
+    match parse::reparse_tag(&buf)? {
+        parse::IO_REPARSE_TAG_MOUNT_POINT => {
+            let mount_point = parse::parse_reparse_buffer(&buf)?;
+            Ok(Status::Junction {
+                target: name(mount_point.substitute_name),
+                print_name: name(mount_point.print_name),
+                target_exists: path.exists(),
+                buffer_used: parse::buffer_len(&buf)? as u16,
+            })
+        }
+        parse::IO_REPARSE_TAG_SYMLINK => {
+            let link = parse::parse_symlink_buffer(&buf)?;
+            Ok(Status::Symlink {
+                target: name(link.substitute_name),
+                relative: link.relative,
+            })
+        }
+        tag => Ok(Status::OtherReparse(tag)),
+    }
+}
+
+pub fn resolution_chain(start: &Path, max_hops: usize) -> io::Result<Vec<(PathBuf, ReparseTag)>> {
+    let mut chain: Vec<(PathBuf, ReparseTag)> = Vec::new();
+    // Absolute, so it compares with the targets stored.
+    let mut current = PathBuf::from(OsString::from_wide(&helpers::get_full_path(start)?));
+    while chain.len() < max_hops {
+        // A missing target ends the chain like a real directory does.
+        match fs::symlink_metadata(&current) {
+            Ok(meta) if meta.file_attributes() & c::FILE_ATTRIBUTE_REPARSE_POINT != 0 => {}
+            _ => break,
+        }
+        let tag = reparse_tag(&current)?;
+        let next = match tag {
+            ReparseTag::MountPoint | ReparseTag::Symlink => {
+                let reparse_point = read_reparse_point(&current, &ReadOptions::new())?;
+                match current.parent() {
+                    Some(parent) if reparse_point.relative => parent.join(reparse_point.target),
+                    // Stored as `UNC\server\share` or `Volume{GUID}`, which
+                    // would otherwise be taken as relative to the current directory.
+                    _ => {
+                        let target: Vec<u16> = reparse_point.target.as_os_str().encode_wide().collect();
+                        PathBuf::from(OsString::from_wide(&default_print_name(&target)))
+                    }
+                }
+            }
+            // Only junctions and symbolic links store a target to follow.
+            _ => {
+                chain.push((current, tag));
+                break;
+            }
+        };
+        chain.push((current, tag));
+        let key: Vec<u16> = next.as_os_str().encode_wide().collect();
+        let seen = chain.iter().any(|(hop, _)| {
+            let hop: Vec<u16> = hop.as_os_str().encode_wide().collect();
+            same_path(&hop, &key)
+        });
+        if seen {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("reparse points form a cycle back to {}", next.display()),
+            ));
+        }
+        current = next;
+    }
+    Ok(chain)
+}
+
+pub fn convert_symlink_to_junction(path: &Path) -> io::Result<()> {
+    // Junctions are directories, and symbolic links to files are not.
+    if fs::symlink_metadata(path)?.file_attributes() & c::FILE_ATTRIBUTE_DIRECTORY == 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "`path` is not a directory"));
+    }
+    // Not shared, so nothing else can open the link until it is converted.
+    let file = helpers::open_reparse_point(path, true)?;
+    let buf = read_reparse_data(&file)?;
+    let link = parse::parse_symlink_buffer(&buf)?;
+    if link.relative {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "the symbolic link's target is relative, but junctions need absolute targets",
+        ));
+    }
+    let wide: Vec<u16> = link.substitute_name.units().collect();
+    let target = match wide.strip_prefix(NT_PREFIX.as_slice()) {
+        Some(target) => target.to_vec(),
+        None => resolve_target(Path::new(&OsString::from_wide(&wide)))?,
+    };
+    helpers::delete_reparse_point(file.as_raw_handle())?;
+    if let Err(e) = set_mount_point(&file, &target, &default_print_name(&target)) {
+        // Put the symbolic link back rather than leave an empty directory.
+        let _ = write_reparse_data(&file, &buf);
+        return Err(e);
+    }
+    Ok(())
+}
+
+pub fn list_junctions_with_targets(dir: &Path) -> io::Result<Vec<(PathBuf, PathBuf)>> {
+    let mut junctions = Vec::new();
+    // Tags come from the directory listing, so only junctions are opened.
+    for entry in helpers::find_entries(dir)? {
+        if entry.reparse_tag != Some(c::IO_REPARSE_TAG_MOUNT_POINT) {
+            continue;
+        }
+        let path = dir.join(entry.name);
+        let read = helpers::open_reparse_point(&path, false).and_then(|file| read_mount_point(&file));
+        match read {
+            Ok(Some(target)) => junctions.push((path, target)),
+            Ok(None) => {}
+            // Deleted since the listing, or not ours to read, which shouldn't
+            // cost the caller the other entries.
+            Err(e) if matches!(e.kind(), io::ErrorKind::NotFound | io::ErrorKind::PermissionDenied) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(junctions)
+}
+
+pub fn find_junctions_usn(volume: &Path) -> io::Result<Vec<(PathBuf, PathBuf)>> {
+    let root = PathBuf::from(OsString::from_wide(&helpers::volume_root(volume)?));
+    let mut junctions = match find_junctions_mft(&root) {
+        Ok(junctions) => junctions,
+        // Without access to the volume or its journal, walk the directories.
+        Err(_) => find_junctions_walk(&root)?,
+    };
+    junctions.sort();
+    Ok(junctions)
+}
+
+/// Finds the junctions on the volume whose root is `root` by reading its MFT.
+fn find_junctions_mft(root: &Path) -> io::Result<Vec<(PathBuf, PathBuf)>> {
+    let volume = helpers::open_volume(&root.as_os_str().encode_wide().collect::<Vec<_>>())?;
+    let high_usn = helpers::query_usn_journal(&volume)?;
+    let info = helpers::file_information(root)?;
+    let root_id = (u64::from(info.nFileIndexHigh) << 32) | u64::from(info.nFileIndexLow);
+
+    let mut dirs = HashMap::new();
+    let mut candidates = Vec::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut start = 0;
+    while let Some(len) = helpers::enum_usn_data(&volume, start, high_usn, &mut buf)? {
+        let (next, records) = usn::parse_records(&buf[..len])?;
+        for record in records {
+            if record.attributes & c::FILE_ATTRIBUTE_DIRECTORY == 0 {
+                continue;
+            }
+            if record.attributes & c::FILE_ATTRIBUTE_REPARSE_POINT != 0 {
+                candidates.push(record.id);
+            }
+            dirs.insert(record.id, (record.parent, record.name));
+        }
+        start = next;
+    }
+
+    let mut junctions = Vec::new();
+    for id in candidates {
+        let path = match usn::path_of(id, root_id, &dirs) {
+            Some(relative) => root.join(relative),
+            None => continue,
+        };
+        // The reparse attribute is set for every tag, so confirm it's a
+        // junction, skipping entries removed since or that can't be read.
+        let file = match helpers::open_reparse_point(&path, false) {
+            Ok(file) => file,
+            Err(_) => continue,
+        };
+        if let Ok(Some(target)) = read_mount_point(&file) {
+            junctions.push((path, target));
+        }
+    }
+    Ok(junctions)
+}
+
+/// Finds the junctions under `root` by listing every directory, skipping those
+/// that can't be listed.
+fn find_junctions_walk(root: &Path) -> io::Result<Vec<(PathBuf, PathBuf)>> {
+    let mut junctions = Vec::new();
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let entries = match helpers::find_entries(&dir) {
+            Ok(entries) => entries,
+            Err(e) if dir == root => return Err(e),
+            Err(_) => continue,
+        };
+        for entry in entries {
+            let path = dir.join(entry.name);
+            if entry.reparse_tag == Some(c::IO_REPARSE_TAG_MOUNT_POINT) {
+                let file = match helpers::open_reparse_point(&path, false) {
+                    Ok(file) => file,
+                    Err(_) => continue,
+                };
+                if let Ok(Some(target)) = read_mount_point(&file) {
+                    junctions.push((path, target));
+                }
+            } else if entry.reparse_tag.is_none() && entry.attributes & c::FILE_ATTRIBUTE_DIRECTORY != 0 {
+                dirs.push(path);
+            }
+        }
+    }
+    Ok(junctions)
+}
+
+pub fn retarget_prefix(root: &Path, old_prefix: &Path, new_prefix: &Path) -> io::Result<usize> {
+    // Compared against stored targets, which are absolute and prefix-free.
+    let old_prefix = PathBuf::from(OsString::from_wide(&resolve_target(old_prefix)?));
+    let mut changed = 0;
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in helpers::find_entries(&dir)? {
+            let path = dir.join(entry.name);
+            if entry.reparse_tag == Some(c::IO_REPARSE_TAG_MOUNT_POINT) {
+                let file = helpers::open_reparse_point(&path, false)?;
+                let stored = read_mount_point(&file)?;
+                // Opened without sharing, so close it before opening it to write.
+                drop(file);
+                let rest = match stored.and_then(|stored| strip_prefix_ignore_case(&stored, &old_prefix)) {
+                    Some(rest) => rest,
+                    None => continue,
+                };
+                // Joining an empty path would add a trailing separator.
+                let target = if rest.as_os_str().is_empty() {
+                    resolve_target(new_prefix)?
+                } else {
+                    resolve_target(&new_prefix.join(rest))?
+                };
+                let file = helpers::open_reparse_point(&path, true)?;
+                set_mount_point(&file, &target, &default_print_name(&target))?;
+                changed += 1;
+            } else if entry.reparse_tag.is_none() && entry.attributes & c::FILE_ATTRIBUTE_DIRECTORY != 0 {
+                dirs.push(path);
+            }
+        }
+    }
+    Ok(changed)
+}
+
+/// Strips `prefix` from `path` if it starts with all of its components,
+/// compared ignoring ASCII case.
+fn strip_prefix_ignore_case(path: &Path, prefix: &Path) -> Option<PathBuf> {
+    let mut path = path.components();
+    for component in prefix.components() {
+        match path.next() {
+            Some(other) if helpers::eq_ignore_case(other.as_os_str(), component.as_os_str()) => {}
+            _ => return None,
+        }
+    }
+    Some(path.collect())
+}
+
+pub fn reparse_tag_histogram(root: &Path) -> io::Result<HashMap<u32, usize>> {
+    let mut histogram = HashMap::new();
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        // Tags come from the directory listing, without opening any entry.
+        for entry in helpers::find_entries(&dir)? {
+            if let Some(tag) = entry.reparse_tag {
+                *histogram.entry(tag).or_insert(0) += 1;
+            } else if entry.attributes & c::FILE_ATTRIBUTE_DIRECTORY != 0 {
+                dirs.push(dir.join(entry.name));
+            }
+        }
+    }
+    Ok(histogram)
+}
+
+pub fn group_by_target(root: &Path) -> io::Result<HashMap<PathBuf, Vec<PathBuf>>> {
+    let mut groups: HashMap<PathBuf, Vec<PathBuf>> = HashMap::new();
+    // Keyed by the first spelling seen of each target, matched by its
+    // canonical path ignoring case.
+    let mut keys: HashMap<OsString, PathBuf> = HashMap::new();
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in helpers::find_entries(&dir)? {
+            let path = dir.join(entry.name);
+            if entry.reparse_tag == Some(c::IO_REPARSE_TAG_MOUNT_POINT) {
+                let file = helpers::open_reparse_point(&path, false)?;
+                let target = match read_mount_point(&file)? {
+                    Some(target) => target,
+                    None => continue,
+                };
+                // Falls back to the stored target when it is gone.
+                let canonical = fs::canonicalize(&target).unwrap_or_else(|_| target.clone());
+                let key = keys
+                    .entry(helpers::upcase(canonical.as_os_str()))
+                    .or_insert(target)
+                    .clone();
+                groups.entry(key).or_default().push(path);
+            } else if entry.reparse_tag.is_none() && entry.attributes & c::FILE_ATTRIBUTE_DIRECTORY != 0 {
+                dirs.push(path);
+            }
+        }
+    }
+    Ok(groups)
+}
+
+pub fn is_broken(junction: &Path) -> io::Result<bool> {
+    // Fails unless it is a junction, before following it.
+    let target = get_target(junction, &ReadOptions::new())?;
+    match fs::metadata(junction) {
+        Ok(_) => Ok(false),
+        Err(e) if e.raw_os_error().is_some_and(|code| TARGET_GONE.contains(&code)) => {
+            // `ERROR_PATH_NOT_FOUND` also comes from a drive or volume that
+            // isn't mounted, which says nothing about the target on it.
+            let mut verbatim = OsString::from(r"\\?\");
+            verbatim.push(target);
+            let root: PathBuf = Path::new(&verbatim).components().take(2).collect();
+            fs::metadata(root)?;
+            Ok(true)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+pub fn prune_broken(root: &Path) -> io::Result<Vec<PathBuf>> {
+    let options = DeleteOptions {
+        remove_dir: true,
+        ..DeleteOptions::new()
+    };
+    let mut removed = Vec::new();
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in helpers::find_entries(&dir)? {
+            let path = dir.join(entry.name);
+            if entry.reparse_tag == Some(c::IO_REPARSE_TAG_MOUNT_POINT) {
+                // Junctions that can't be checked, such as those to a share
+                // that is only unreachable for now, are kept.
+                if let Ok(true) = is_broken(&path) {
+                    delete(&path, &options)?;
+                    removed.push(path);
+                }
+            } else if entry.reparse_tag.is_none() && entry.attributes & c::FILE_ATTRIBUTE_DIRECTORY != 0 {
+                dirs.push(path);
+            }
+        }
+    }
+    Ok(removed)
+}
+
+pub fn target_size(junction: &Path) -> io::Result<u64> {
+    let target = get_target(junction, &ReadOptions::new())?;
+    let mut visited = HashSet::new();
+    let mut size = 0;
+    let mut dirs = vec![fs::canonicalize(target)?];
+    while let Some(dir) = dirs.pop() {
+        // Directories reached more than once, such as through a junction
+        // pointing back up, are only counted the first time.
+        if !visited.insert(dir.clone()) {
+            continue;
+        }
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let meta = entry.metadata()?;
+            if meta.file_attributes() & c::FILE_ATTRIBUTE_REPARSE_POINT != 0 {
+                // Files whose reparse point isn't a link, such as deduplicated
+                // files or cloud files placeholders, count with their logical
+                // size, without being read or recalled.
+                if !meta.is_dir() && !meta.file_type().is_symlink() {
+                    size += meta.len();
+                // Only links to directories are followed, and broken ones are skipped.
+                } else if let Ok(resolved) = fs::canonicalize(entry.path()) {
+                    if resolved.is_dir() {
+                        dirs.push(resolved);
+                    }
+                }
+            } else if meta.is_dir() {
+                dirs.push(entry.path());
+            } else {
+                size += meta.len();
+            }
+        }
+    }
+    Ok(size)
+}
+
+pub fn copy_tree_flatten(src: &Path, dst: &Path) -> io::Result<()> {
+    copy_dir_flatten(src, dst, &mut Vec::new())
+}
+
+/// Copies the contents of `src` into `dst`, materializing junctions.
+/// `ancestors` holds the canonical paths of the directories being copied
+/// above `src`, which a junction leading back to would copy forever.
+fn copy_dir_flatten(src: &Path, dst: &Path, ancestors: &mut Vec<PathBuf>) -> io::Result<()> {
+    let canonical = fs::canonicalize(src)?;
+    if ancestors.contains(&canonical) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("`{}` leads back to a directory being copied", src.display()),
+        ));
+    }
+    fs::create_dir_all(dst)?;
+    ancestors.push(canonical);
+    for entry in helpers::find_entries(src)? {
+        let from = src.join(&entry.name);
+        let to = dst.join(&entry.name);
+        if entry.reparse_tag == Some(c::IO_REPARSE_TAG_MOUNT_POINT) {
+            let target = get_target(&from, &ReadOptions::new())?;
+            copy_dir_flatten(&target, &to, ancestors)?;
+        } else if entry.attributes & c::FILE_ATTRIBUTE_DIRECTORY != 0 {
+            // Symbolic links to directories are followed, and so flattened too.
+            copy_dir_flatten(&from, &to, ancestors)?;
+        } else {
+            fs::copy(&from, &to)?;
+        }
+    }
+    ancestors.pop();
+    Ok(())
+}
+
+/// Identity of a file, and when it last changed.
+pub struct FileId {
+    pub volume_serial_number: u32,
+    pub file_index: u64,
+    pub last_write_time: u64,
+}
+
+pub fn file_id(path: &Path) -> io::Result<FileId> {
+    let info = helpers::file_information(path)?;
+    let time = info.ftLastWriteTime;
+    Ok(FileId {
+        volume_serial_number: info.dwVolumeSerialNumber,
+        file_index: u64::from(info.nFileIndexHigh) << 32 | u64::from(info.nFileIndexLow),
+        last_write_time: u64::from(time.dwHighDateTime) << 32 | u64::from(time.dwLowDateTime),
+    })
+}
+
+pub fn real_path(junction: &Path) -> io::Result<PathBuf> {
+    let leaf = match junction.file_name() {
+        Some(leaf) => leaf,
+        None => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "`junction` has no final component",
+            ))
+        }
+    };
+    let parent = match junction.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let parent = fs::canonicalize(parent)?;
+    Ok(strip_verbatim(&parent).join(leaf))
+}
+
+/// Strips the verbatim prefix from `\\?\C:\...` paths, leaving other paths
+/// (such as verbatim UNC or volume paths) intact.
+fn strip_verbatim(path: &Path) -> PathBuf {
+    let wide: Vec<u16> = path.as_os_str().encode_wide().collect();
+    match wide.strip_prefix(VERBATIM_PREFIX.as_slice()) {
+        Some(rest @ [drive, colon, ..]) if *colon == u16::from(b':') && is_ascii_alpha(*drive) => {
+            PathBuf::from(OsString::from_wide(rest))
+        }
+        _ => path.to_path_buf(),
+    }
+}
+
+fn is_ascii_alpha(unit: u16) -> bool {
+    u8::try_from(unit).is_ok_and(|b| b.is_ascii_alphabetic())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsString;
+    use std::fs;
+    use std::os::windows::ffi::OsStringExt;
+    use std::os::windows::io::AsRawHandle;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::options::Retry;
+
+    #[test]
+    fn name_lengths_boundary() {
+        // 4 * len + the NT prefix and two null terminators fill the buffer exactly.
+        let max = (usize::from(MAX_PATH_BUFFER) - 12) / 4;
+        let (substitute, print_name) = name_lengths(max).unwrap();
+        assert_eq!(usize::from(print_name), max * 2);
+        assert_eq!(usize::from(substitute), (max + NT_PREFIX.len()) * 2);
+        assert!(substitute + print_name + 2 * UNICODE_NULL_SIZE <= MAX_PATH_BUFFER);
+
+        for len in [max + 1, usize::from(u16::MAX), usize::MAX / 2, usize::MAX] {
+            let err = name_lengths(len).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput, "length {}", len);
+        }
+    }
+
+    #[test]
+    fn fits_agrees_with_create_on_relative_target() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let max = (usize::from(MAX_PATH_BUFFER) - 12) / 4;
+        // What resolving puts in front of a relative target, separator included.
+        let prefix = helpers::get_full_path(Path::new("a")).unwrap().len() - 1;
+        let relative = |len: usize| {
+            let mut target = String::new();
+            while len - target.len() > 201 {
+                target.push_str(&"a".repeat(200));
+                target.push('\\');
+            }
+            target.push_str(&"a".repeat(len - target.len()));
+            PathBuf::from(target)
+        };
+
+        // Short enough as given, but not once resolved.
+        let over = relative(max - prefix + 1);
+        assert!(over.as_os_str().len() <= max);
+        assert!(!fits(&over).unwrap());
+        let junction = tmpdir.path().join("over");
+        let err = create(&over, &junction, &CreateOptions::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(!junction.exists());
+
+        let exact = relative(max - prefix);
+        assert!(fits(&exact).unwrap());
+        create(&exact, &tmpdir.path().join("exact"), &CreateOptions::new()).unwrap();
+    }
+
+    #[test]
+    fn name_lengths_count_surrogate_pairs() {
+        // Lengths are in UTF-16 units, so an astral character counts twice
+        // and a pair is never split.
+        let target: Vec<u16> = "C:\\\u{1F980}".encode_utf16().collect();
+        assert_eq!(target.len(), 5);
+        let (substitute, print_name) = name_lengths(target.len()).unwrap();
+        assert_eq!(print_name, 10);
+        assert_eq!(substitute, 18);
+    }
+
+    #[test]
+    fn drive_target_components() {
+        assert_eq!(drive_target('d', Path::new(r"a\b")).unwrap(), Path::new(r"D:\a\b"));
+        assert_eq!(drive_target('C', Path::new("")).unwrap(), Path::new(r"C:\"));
+        for drive in ['1', ':', 'é', '\\'] {
+            let err = drive_target(drive, Path::new("a")).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput, "{}", drive);
+        }
+        for relative in [r"\a", r"C:\a", "C:a", r"\\server\share\a"] {
+            let err = drive_target('C', Path::new(relative)).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput, "{}", relative);
+        }
+    }
+
+    #[test]
+    fn mount_point_target_malformed() {
+        let mut buf = crate::parse::tests::mount_point(r"\??\C:\target", r"C:\target");
+        assert_eq!(mount_point_target(&buf).unwrap(), Some(PathBuf::from(r"C:\target")));
+
+        // SubstituteNameLength reaching past the end of the buffer.
+        buf[parse::HEADER_SIZE + 2..parse::HEADER_SIZE + 4].copy_from_slice(&0x4000u16.to_le_bytes());
+        let err = mount_point_target(&buf).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("SubstituteName"), "{}", err);
+        // Truncated before the names.
+        let err = mount_point_target(&buf[..parse::HEADER_SIZE + 4]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        buf[..4].copy_from_slice(&crate::tags::SYMLINK.to_le_bytes());
+        assert_eq!(mount_point_target(&buf).unwrap(), None);
+    }
+
+    #[test]
+    fn filesystem_flags() {
+        // FILE_CASE_SENSITIVE_SEARCH | FILE_CASE_PRESERVED_NAMES | FILE_UNICODE_ON_DISK
+        let fat = 0x1 | 0x2 | 0x4;
+        let err = check_filesystem(fat, OsStr::new("FAT32")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+        assert!(err.to_string().contains("FAT32"), "{}", err);
+        check_filesystem(fat | c::FILE_SUPPORTS_REPARSE_POINTS, OsStr::new("NTFS")).unwrap();
+    }
+
+    #[test]
+    fn os_versions() {
+        // Windows 2000, XP, 7, and 10 or later as reported without a manifest.
+        for (major, minor) in [(5, 0), (5, 1), (6, 1), (6, 2), (10, 0)] {
+            assert!(check_os_version(VER_PLATFORM_WIN32_NT, major, minor).is_ok());
+        }
+        // Windows NT 4.0, and Windows 98 on the 9x platform.
+        for (platform, major, minor) in [(VER_PLATFORM_WIN32_NT, 4, 0), (1, 4, 10)] {
+            let err = check_os_version(platform, major, minor).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+            assert!(err.to_string().contains(&format!("{}.{}", major, minor)), "{}", err);
+        }
+        // The running system supports them, of course.
+        assert!(check_os_support().is_ok());
+    }
+
+    #[test]
+    fn strict_target_names() {
+        for target in [r"C:\target", r"C:\tar get", r"C:\.target", r"C:\target\", r"C:\"] {
+            assert!(check_strict_target(Path::new(target)).is_ok(), "{}", target);
+        }
+        for target in [
+            r"C:\target ",
+            r"C:\ target",
+            r"C:\target.",
+            "C:\\tar\tget",
+            "C:\\dir\n\\target",
+        ] {
+            let err = check_strict_target(Path::new(target)).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput, "{:?}", target);
+        }
+    }
+
+    #[test]
+    fn strip_prefix_components() {
+        let prefix = Path::new(r"D:\store");
+        for (path, expected) in [
+            (r"D:\store\pkg\v1", Some(r"pkg\v1")),
+            (r"d:\STORE\pkg", Some("pkg")),
+            (r"D:\store", Some("")),
+            (r"D:\store2\pkg", None),
+            (r"D:\", None),
+            (r"E:\store\pkg", None),
+        ] {
+            assert_eq!(
+                strip_prefix_ignore_case(Path::new(path), prefix).as_deref(),
+                expected.map(Path::new),
+                "{}",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn drive_form_prefixes() {
+        let wide = |path: &str| path.encode_utf16().collect::<Vec<u16>>();
+        for (path, expected) in [
+            (r"C:\dir", Some(r"C:\dir")),
+            (r"\\?\C:\dir", Some(r"C:\dir")),
+            (r"\\.\d:\dir", Some(r"d:\dir")),
+            (r"\??\C:\", Some(r"C:\")),
+            (r"\\?\UNC\server\share", None),
+            (r"Volume{00000000-0000-0000-0000-000000000000}\", None),
+            (r"\\server\share", None),
+        ] {
+            let drive = drive_form(&wide(path)).map(String::from_utf16_lossy);
+            assert_eq!(drive.as_deref(), expected, "{}", path);
+        }
+    }
+
+    #[test]
+    fn read_growing_retries_more_data() {
+        let more_data = || io::Error::from_raw_os_error(c::ERROR_MORE_DATA as i32);
+        let header = |buf: &mut [u8], data_len: u16| {
+            buf[..4].copy_from_slice(&c::IO_REPARSE_TAG_MOUNT_POINT.to_le_bytes());
+            buf[4..6].copy_from_slice(&data_len.to_le_bytes());
+        };
+
+        // The header of the first, partial read reports the size needed.
+        let mut sizes = Vec::new();
+        let buf = helpers::read_growing(|buf| {
+            sizes.push(buf.len());
+            header(buf, 2000);
+            if buf.len() < 2008 {
+                Err((more_data(), parse::HEADER_SIZE))
+            } else {
+                Ok(2008)
+            }
+        })
+        .unwrap();
+        assert_eq!(sizes, [1024, 2008]);
+        assert_eq!(buf.len(), 2008);
+
+        // Without a header, the buffer doubles up to the maximum.
+        let mut sizes = Vec::new();
+        let err = helpers::read_growing(|buf| {
+            sizes.push(buf.len());
+            Err((more_data(), 0))
+        })
+        .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(sizes, [1024, 2048, 4096, 8192, 16384]);
+
+        // Other errors are returned as is.
+        let err = helpers::read_growing(|_| Err((io::Error::from_raw_os_error(5), 0))).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(5));
+    }
+
+    #[test]
+    fn same_path_ignores_case() {
+        let wide = |path: &str| path.encode_utf16().collect::<Vec<u16>>();
+        for (a, b, same) in [
+            (r"C:\Dir\Link", r"c:\dir\LINK", true),
+            (r"C:\Dir\Link", r"C:\Dir\Link\", true),
+            (r"C:\Dir\Link", r"C:\Dir\Link2", false),
+            (r"C:\Dir\Link", r"C:\Dir", false),
+        ] {
+            assert_eq!(same_path(&wide(a), &wide(b)), same, "{} {}", a, b);
+        }
+    }
+
+    #[test]
+    fn relative_to_base() {
+        let base = Path::new(r"C:\install\root");
+        for (path, expected) in [
+            (r"C:\install\root\lib\v1", Some(r"lib\v1")),
+            (r"c:\INSTALL\Root\lib", Some("lib")),
+            (r"C:\install\root", Some(".")),
+            (r"C:\install\other", Some(r"..\other")),
+            (r"C:\data", Some(r"..\..\data")),
+            (r"D:\install\root", None),
+            (r"\\server\share\root", None),
+        ] {
+            assert_eq!(
+                relative_to(Path::new(path), base),
+                expected.map(PathBuf::from),
+                "{}",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn junction_paths_made_verbatim() {
+        let verbatim = |path: &str| {
+            let path = to_verbatim(path.encode_utf16().collect());
+            String::from_utf16(&path).unwrap()
+        };
+        assert_eq!(verbatim(r"C:\a\junction"), r"\\?\C:\a\junction");
+        assert_eq!(verbatim(r"\\server\share\junction"), r"\\?\UNC\server\share\junction");
+        for path in [r"\\?\C:\a", r"\\?\UNC\server\share\a", r"\\.\C:\a"] {
+            assert_eq!(verbatim(path), path);
+        }
+    }
+
+    #[test]
+    fn unc_target_names() {
+        let wide = |path: &str| path.encode_utf16().collect::<Vec<u16>>();
+        for target in [r"\\server\share\dir", r"\\?\UNC\server\share\dir"] {
+            let data = build_reparse_data(Path::new(target)).unwrap();
+            let bytes = data.as_bytes();
+            assert_eq!(raw_name(bytes, 8), wide(r"\??\UNC\server\share\dir"), "{}", target);
+            assert_eq!(raw_name(bytes, 12), wide(r"\\server\share\dir"));
+        }
+    }
+
+    #[test]
+    fn volume_guid_target_names() {
+        let volume = r"Volume{01234567-89ab-cdef-0123-456789abcdef}";
+        let data = build_reparse_data(Path::new(&format!(r"\\?\{}\a\..\b", volume))).unwrap();
+        let mount_point = parse::parse_reparse_buffer(data.as_bytes()).unwrap();
+        let name = |name: parse::Name<'_>| String::from_utf16(&name.units().collect::<Vec<u16>>()).unwrap();
+        assert_eq!(name(mount_point.substitute_name), format!(r"\??\{}\b", volume));
+        assert_eq!(name(mount_point.print_name), format!(r"\\?\{}\b", volume));
+    }
+
+    #[test]
+    fn find_tag_classifies() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let target = tmpdir.path().join("target");
+        let junction = tmpdir.path().join("junction");
+        fs::create_dir_all(&target).unwrap();
+        crate::create(&target, &junction).unwrap();
+
+        assert_eq!(find_tag(&junction).unwrap(), ReparseTag::MountPoint);
+        let err = find_tag(&target).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(ERROR_NOT_A_REPARSE_POINT));
+        // Needs either privileges or developer mode, it's fine to skip.
+        let symlink = tmpdir.path().join("symlink");
+        if std::os::windows::fs::symlink_dir(&target, &symlink).is_ok() {
+            assert_eq!(find_tag(&symlink).unwrap(), ReparseTag::Symlink);
+        }
+    }
+
+    #[test]
+    fn names_compared_ignoring_case() {
+        let eq = |a: &str, b: &str| helpers::eq_ignore_case(OsStr::new(a), OsStr::new(b));
+        assert!(eq(r"C:\Temp\Dir", r"c:\TEMP\dir"));
+        assert!(eq("Äpfel", "äPFEL"));
+        assert!(eq("Σ", "σ"));
+        // NTFS upcases unit by unit, so `ß` doesn't match `SS`.
+        assert!(!eq("straße", "STRASSE"));
+        assert!(!eq("dir", "dir2"));
+        assert!(same_path(&helpers::utf16s(br"C:\Dir\"), &helpers::utf16s(br"c:\dIR")));
+        assert_eq!(helpers::upcase(OsStr::new("Äb")), OsStr::new("ÄB"));
+    }
+
+    #[test]
+    fn exists_on_deduplicated_directory() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let dir = tmpdir.path().join("dir");
+        fs::create_dir(&dir).unwrap();
+        // A Microsoft tag, so no GUID follows the header.
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&crate::tags::DEDUP.to_le_bytes());
+        buf.extend_from_slice(&4u16.to_le_bytes());
+        buf.extend_from_slice(&[0; 2]);
+        buf.extend_from_slice(&[1, 2, 3, 4]);
+        let file = helpers::open_reparse_point(&dir, true).unwrap();
+        write_reparse_data(&file, &buf).unwrap();
+        drop(file);
+
+        assert_eq!(reparse_tag(&dir).unwrap(), ReparseTag::Other(crate::tags::DEDUP));
+        assert!(!exists(&dir, &ReadOptions::new()).unwrap());
+    }
+
+    #[test]
+    fn target_size_counts_other_reparse_files() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let target = tmpdir.path().join("target");
+        let junction = tmpdir.path().join("junction");
+        fs::create_dir_all(&target).unwrap();
+        fs::write(target.join("plain"), "contents").unwrap();
+        let placeholder = target.join("placeholder");
+        fs::write(&placeholder, [0; 100]).unwrap();
+        set_other_reparse_point(&placeholder);
+        create(&target, &junction, &CreateOptions::new()).unwrap();
+
+        assert_eq!(target_size(&junction).unwrap(), 108);
+    }
+
+    #[test]
+    fn status_of_other_reparse_point() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let placeholder = tmpdir.path().join("placeholder");
+        fs::create_dir(&placeholder).unwrap();
+        set_other_reparse_point(&placeholder);
+
+        assert_eq!(status(&placeholder).unwrap(), Status::OtherReparse(0x42));
+    }
+
+    #[test]
+    fn delete_refuses_other_reparse_point() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let placeholder = tmpdir.path().join("placeholder");
+        fs::create_dir(&placeholder).unwrap();
+        let buf = set_other_reparse_point(&placeholder);
+
+        let err = delete(&placeholder, &DeleteOptions::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Other);
+        assert_eq!(raw_reparse_buffer(&placeholder).unwrap(), buf);
+    }
+
+    #[test]
+    fn thread_local_buffer_is_reused() {
+        let mut first = BytesAsReparseDataBuffer::with_strategy(BufferStrategy::ThreadLocal);
+        let ptr = first.as_mut_ptr();
+        // Still in use, so another one is allocated.
+        let mut nested = BytesAsReparseDataBuffer::with_strategy(BufferStrategy::ThreadLocal);
+        assert_ne!(nested.as_mut_ptr(), ptr);
+        drop(nested);
+        drop(first);
+        let mut again = BytesAsReparseDataBuffer::with_strategy(BufferStrategy::ThreadLocal);
+        assert_eq!(again.as_mut_ptr(), ptr);
+
+        let tmpdir = tempfile::tempdir().unwrap();
+        let target = tmpdir.path().join("target");
+        fs::create_dir_all(&target).unwrap();
+        let mut options = CreateOptions::new();
+        options.buffer_strategy(BufferStrategy::ThreadLocal);
+        for i in 0..3 {
+            let junction = tmpdir.path().join(format!("junction{}", i));
+            options.create(&target, &junction).unwrap();
+            assert_eq!(crate::get_target(&junction).unwrap(), target);
+        }
+    }
+
+    #[test]
+    fn validate_buffer_rejects_inconsistent_fields() {
+        let data = build_reparse_data(Path::new(r"C:\target")).unwrap();
+        let good = data.as_bytes();
+        validate_buffer(good).unwrap();
+        // An empty PrintName is consistent too.
+        let (empty, report) = encode_mount_point(&helpers::utf16s(br"C:\target"), &[]).unwrap();
+        validate_buffer(unsafe { empty.assume_init_bytes(usize::from(report.in_buffer_size)) }).unwrap();
+
+        let set = |offset: usize, value: u16| {
+            let mut buf = good.to_vec();
+            buf[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+            buf
+        };
+        let field = |offset: usize| u16::from_le_bytes([good[offset], good[offset + 1]]);
+        let bad = [
+            // ReparseDataLength
+            set(4, field(4) - 2),
+            set(4, field(4) + 2),
+            // SubstituteNameOffset
+            set(8, 2),
+            // SubstituteNameLength
+            set(10, field(10) - 2),
+            // PrintNameOffset
+            set(12, field(12) + 2),
+            // PrintNameLength
+            set(14, field(14) - 2),
+            set(14, field(14) + 2),
+        ];
+        for buf in bad {
+            let err = validate_buffer(&buf).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{:?}", buf);
+        }
+        // Cut short of what its header declares.
+        let err = validate_buffer(&good[..good.len() - 2]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn collapse_dots_components() {
+        let collapse = |path: &str| {
+            let wide: Vec<u16> = path.encode_utf16().collect();
+            collapse_dots(&wide).map(|wide| String::from_utf16(&wide).unwrap())
+        };
+        assert_eq!(collapse(r"C:\a\..\b"), Some(r"C:\b".to_owned()));
+        assert_eq!(collapse(r"C:\a\.\b\."), Some(r"C:\a\b".to_owned()));
+        assert_eq!(collapse(r"C:\..\..\a"), Some(r"C:\a".to_owned()));
+        assert_eq!(collapse(r"C:\a\.."), Some(r"C:\".to_owned()));
+        assert_eq!(collapse(r"\\server\share\..\a"), Some(r"\\server\share\a".to_owned()));
+        assert_eq!(
+            collapse(r"UNC\server\share\a\..\b"),
+            Some(r"UNC\server\share\b".to_owned())
+        );
+        // `..` doesn't climb out of a volume GUID root.
+        assert_eq!(
+            collapse(r"Volume{01234567-89ab-cdef-0123-456789abcdef}\a\..\..\b"),
+            Some(r"Volume{01234567-89ab-cdef-0123-456789abcdef}\b".to_owned())
+        );
+        // Dots within names are left alone.
+        assert_eq!(collapse(r"C:\a.\..b\...\.c"), None);
+        assert_eq!(collapse(r"C:\a\b"), None);
+    }
+
+    #[test]
+    fn retry_sharing_violation_eventually_succeeds() {
+        let sharing_violation = || io::Error::from_raw_os_error(c::ERROR_SHARING_VIOLATION as i32);
+        let retry = Retry {
+            attempts: 3,
+            backoff: Duration::from_millis(1),
+        };
+
+        let mut calls = 0;
+        let opened = helpers::retry_sharing_violation(retry, || {
+            calls += 1;
+            if calls < 3 {
+                Err(sharing_violation())
+            } else {
+                Ok("opened")
+            }
+        });
+        assert_eq!(opened.unwrap(), "opened");
+        assert_eq!(calls, 3);
+
+        // Gives up once the retries are exhausted.
+        let mut calls = 0;
+        let err = helpers::retry_sharing_violation(retry, || -> io::Result<()> {
+            calls += 1;
+            Err(sharing_violation())
+        })
+        .unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(c::ERROR_SHARING_VIOLATION as i32));
+        assert_eq!(calls, 4);
+
+        // Other errors and the default options don't retry.
+        let mut calls = 0;
+        let err = helpers::retry_sharing_violation(retry, || -> io::Result<()> {
+            calls += 1;
+            Err(io::ErrorKind::PermissionDenied.into())
+        })
+        .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        assert_eq!(calls, 1);
+        let mut calls = 0;
+        let _ = helpers::retry_sharing_violation(Retry::default(), || -> io::Result<()> {
+            calls += 1;
+            Err(sharing_violation())
+        });
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn delete_retries_transient_access_denied() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let target = tmpdir.path().join("target");
+        let junction = tmpdir.path().join("junction");
+        fs::create_dir_all(&target).unwrap();
+        crate::create(&target, &junction).unwrap();
+        let retry = Retry {
+            attempts: 3,
+            backoff: Duration::from_millis(1),
+        };
+
+        // As an antivirus scanner holding the junction would.
+        let mut calls = 0;
+        helpers::retry_transient(retry, || {
+            calls += 1;
+            if calls == 1 {
+                Err(io::Error::from_raw_os_error(5))
+            } else {
+                delete(&junction, &DeleteOptions::new())
+            }
+        })
+        .unwrap();
+        assert_eq!(calls, 2);
+        assert!(!crate::exists(&junction).unwrap());
+
+        // Not retried without options, nor for other errors.
+        let mut calls = 0;
+        let _ = helpers::retry_transient(Retry::default(), || -> io::Result<()> {
+            calls += 1;
+            Err(io::ErrorKind::PermissionDenied.into())
+        });
+        assert_eq!(calls, 1);
+        let mut calls = 0;
+        let _ = helpers::retry_transient(retry, || -> io::Result<()> {
+            calls += 1;
+            Err(io::ErrorKind::NotFound.into())
+        });
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn public_functions_close_their_handles() {
+        let open = || helpers::OPEN_REPARSE_HANDLES.with(|open| open.get());
+        let tmpdir = tempfile::tempdir().unwrap();
+        let target = tmpdir.path().join("target");
+        let junction = tmpdir.path().join("junction");
+        let other = tmpdir.path().join("other");
+        let file = tmpdir.path().join("file");
+        let missing = tmpdir.path().join("missing");
+        fs::create_dir_all(&target).unwrap();
+        fs::write(&file, "").unwrap();
+        crate::create(&target, &junction).unwrap();
+        assert_eq!(open(), 0);
+
+        // Each call, whether it succeeds or fails, leaves no handle open.
+        macro_rules! closes_handles {
+            ($($call:expr;)*) => {$(
+                let _ = $call;
+                assert_eq!(open(), 0, "{}", stringify!($call));
+            )*};
+        }
+        closes_handles! {
+            crate::exists(&junction);
+            crate::exists(&target);
+            crate::exists(&missing);
+            crate::get_target(&junction);
+            crate::get_target(&target);
+            crate::get_target(&file);
+            crate::get_target_resolve_volume(&junction);
+            crate::get_target_win32(&target);
+            crate::get_target_relative_to(&junction, tmpdir.path());
+            crate::read_reparse_point(&junction);
+            crate::read_reparse_point(&target);
+            crate::raw_reparse_buffer(&target);
+            crate::reparse_data_len(&junction);
+            crate::diff_reparse(&junction, &target);
+            crate::list_junctions_with_targets(tmpdir.path());
+            crate::retarget_prefix(tmpdir.path(), &missing, &target);
+            crate::diagnose(&junction);
+            crate::ensure(&target, &junction);
+            crate::create(&target, &junction);
+            crate::create(&target, &other);
+            crate::disable(&other);
+            crate::enable(&other, &target);
+            crate::enable(tmpdir.path(), &target);
+            crate::delete(&target);
+            crate::delete(&other);
+            crate::delete_many(vec![junction.clone(), missing.clone()]);
+        }
+    }
+
+    #[test]
+    fn listing_opens_only_junctions() {
+        let opens = || helpers::REPARSE_POINT_OPENS.with(|opens| opens.get());
+        let tmpdir = tempfile::tempdir().unwrap();
+        let target = tmpdir.path().join("target");
+        let root = tmpdir.path().join("root");
+        fs::create_dir_all(&target).unwrap();
+        fs::create_dir_all(root.join("nested")).unwrap();
+        for i in 0..100 {
+            fs::write(root.join(format!("file{}", i)), "").unwrap();
+        }
+        for i in 0..10 {
+            create(&target, &root.join(format!("junction{}", i)), &CreateOptions::new()).unwrap();
+        }
+        create(&target, &root.join("nested").join("junction"), &CreateOptions::new()).unwrap();
+
+        let before = opens();
+        assert_eq!(list_junctions_with_targets(&root).unwrap().len(), 10);
+        assert_eq!(opens(), before + 10, "one open per junction, none for other entries");
+
+        let before = opens();
+        let histogram = reparse_tag_histogram(&root).unwrap();
+        assert_eq!(histogram.get(&c::IO_REPARSE_TAG_MOUNT_POINT), Some(&11));
+        assert_eq!(opens(), before, "tags come from the listing");
+    }
+
+    #[test]
+    fn target_cache_hit_skips_reparse_read() {
+        let opens = || helpers::REPARSE_POINT_OPENS.with(|opens| opens.get());
+        let tmpdir = tempfile::tempdir().unwrap();
+        let target = tmpdir.path().join("target");
+        let other = tmpdir.path().join("other");
+        let junction = tmpdir.path().join("junction");
+        fs::create_dir_all(&target).unwrap();
+        fs::create_dir_all(&other).unwrap();
+        create(&target, &junction, &CreateOptions::new()).unwrap();
+
+        let mut cache = crate::TargetCache::new();
+        let before = opens();
+        assert_eq!(cache.get_target(&junction).unwrap(), target);
+        assert_eq!(opens(), before + 1, "first lookup reads the reparse point");
+        assert_eq!(cache.get_target(&junction).unwrap(), target);
+        assert_eq!(opens(), before + 1, "cache hit must not reopen the reparse point");
+
+        // Recreating the junction changes its identity, so the cache misses.
+        delete(&junction, &DeleteOptions::new()).unwrap();
+        fs::remove_dir(&junction).unwrap();
+        create(&other, &junction, &CreateOptions::new()).unwrap();
+        let before = opens();
+        assert_eq!(cache.get_target(&junction).unwrap(), other);
+        assert_eq!(opens(), before + 1);
+    }
+
+    /// Sets a reparse point with the third-party tag `0x42` on `path`, as
+    /// other software such as cloud files providers would, and returns its
+    /// reparse data buffer.
+    fn set_other_reparse_point(path: &Path) -> Vec<u8> {
+        // A third-party tag, so the header carries the owner's GUID.
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&0x0000_0042u32.to_le_bytes());
+        buf.extend_from_slice(&4u16.to_le_bytes());
+        buf.extend_from_slice(&[0; 2]);
+        buf.extend_from_slice(&[0x11; 16]);
+        buf.extend_from_slice(&[1, 2, 3, 4]);
+        let file = helpers::open_reparse_point(path, true).unwrap();
+        write_reparse_data(&file, &buf).unwrap();
+        buf
+    }
+
+    /// Reads the little-endian `u16` at byte `at` of a reparse data buffer.
+    fn raw_field(buf: &[u8], at: usize) -> u16 {
+        u16::from_le_bytes([buf[at], buf[at + 1]])
+    }
+
+    /// Reads the name whose offset and length are at byte `at` of a mount
+    /// point reparse data buffer: 8 for SubstituteName, 12 for PrintName.
+    fn raw_name(buf: &[u8], at: usize) -> Vec<u16> {
+        // PathBuffer follows the four offset and length fields.
+        let offset = 16 + usize::from(raw_field(buf, at));
+        let len = usize::from(raw_field(buf, at + 2));
+        buf[offset..offset + len]
+            .chunks_exact(2)
+            .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+            .collect()
+    }
+
+    #[test]
+    fn create_populates_print_name() {
+        // Regression test: the junction reparse point must have a non-empty PrintName
+        // so that Windows Container layer snapshots correctly preserve the junction target.
+        let tmpdir = tempfile::tempdir().unwrap();
+        let target = tmpdir.path().join("target");
+        let junction = tmpdir.path().join("junction");
+        fs::create_dir_all(&target).unwrap();
+
+        create(&target, &junction, &CreateOptions::new()).unwrap();
+
+        // Read back the raw reparse data
+        let buf = raw_reparse_buffer(&junction).unwrap();
+        let tag = u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]);
+        assert_eq!(tag, c::IO_REPARSE_TAG_MOUNT_POINT);
+
+        // Read PrintName
+        let print_name = raw_name(&buf, 12);
+
+        // PrintName must not be empty
+        assert!(!print_name.is_empty(), "PrintName must not be empty");
+
+        // PrintName should match what get_target returns (the Win32 path without \??\ prefix)
+        let print_path = PathBuf::from(OsString::from_wide(&print_name));
+        let target_path = get_target(&junction, &ReadOptions::new()).unwrap();
+        assert_eq!(print_path, target_path, "PrintName should match the target path");
+    }
+
+    #[test]
+    fn parse_matches_raw_fields() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let target = tmpdir.path().join("target");
+        let junction = tmpdir.path().join("junction");
+        fs::create_dir_all(&target).unwrap();
+        create(&target, &junction, &CreateOptions::new()).unwrap();
+
+        let buf = raw_reparse_buffer(&junction).unwrap();
+        let mount_point = parse::parse_reparse_buffer(&buf).unwrap();
+        assert_eq!(
+            mount_point.substitute_name.units().collect::<Vec<_>>(),
+            raw_name(&buf, 8)
+        );
+        assert_eq!(mount_point.print_name.units().collect::<Vec<_>>(), raw_name(&buf, 12));
+        assert_eq!(parse::data_len(&buf).unwrap(), raw_field(&buf, 4));
+    }
+
+    #[test]
+    fn set_mount_point_replaces_target() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let first = tmpdir.path().join("first");
+        let second = tmpdir.path().join("second");
+        let junction = tmpdir.path().join("junction");
+        fs::create_dir_all(&first).unwrap();
+        fs::create_dir_all(&second).unwrap();
+        create(&first, &junction, &CreateOptions::new()).unwrap();
+
+        // With `FSCTL_SET_REPARSE_POINT_EX` where supported, then without.
+        for (skip_ex, target) in [(false, &second), (true, &first)] {
+            helpers::SKIP_SET_REPARSE_POINT_EX.with(|skip| skip.set(skip_ex));
+            let target = resolve_target(target).unwrap();
+            let file = helpers::open_reparse_point(&junction, true).unwrap();
+            set_mount_point(&file, &target, &target).unwrap();
+            drop(file);
+            assert_eq!(
+                get_target(&junction, &ReadOptions::new()).unwrap(),
+                PathBuf::from(OsString::from_wide(&target))
+            );
+        }
+        helpers::SKIP_SET_REPARSE_POINT_EX.with(|skip| skip.set(false));
+    }
+
+    #[test]
+    fn create_denied_without_privilege() {
+        use std::process::Command;
+
+        let tmpdir = tempfile::tempdir().unwrap();
+        let target = tmpdir.path().join("target");
+        let parent = tmpdir.path().join("parent");
+        let junction = parent.join("junction");
+        fs::create_dir_all(&target).unwrap();
+        fs::create_dir_all(&parent).unwrap();
+        // Children inherit a denial of writing data to Everyone, the parent doesn't.
+        let status = Command::new("icacls")
+            .arg(&parent)
+            .args(["/deny", "*S-1-1-0:(CI)(IO)(WD)"])
+            .status();
+        if !status.is_ok_and(|status| status.success()) {
+            return;
+        }
+
+        // As for a user who can't enable the privilege bypassing the ACL.
+        helpers::SKIP_SET_PRIVILEGE.with(|skip| skip.set(true));
+        let result = create(&target, &junction, &CreateOptions::new());
+        helpers::SKIP_SET_PRIVILEGE.with(|skip| skip.set(false));
+        let err = result.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        assert!(err.to_string().contains("inherits from its parent"), "{}", err);
+        assert!(fs::symlink_metadata(&junction).is_err(), "the directory is removed");
+    }
+
+    #[test]
+    fn reparse_tag_falls_back_to_listing_when_denied() {
+        use std::process::Command;
+
+        let opens = || helpers::REPARSE_POINT_OPENS.with(|opens| opens.get());
+        let tmpdir = tempfile::tempdir().unwrap();
+        let target = tmpdir.path().join("target");
+        let junction = tmpdir.path().join("junction");
+        fs::create_dir_all(&target).unwrap();
+        create(&target, &junction, &CreateOptions::new()).unwrap();
+        // Deny reading data to Everyone, which GENERIC_READ asks for.
+        let status = Command::new("icacls")
+            .arg(&junction)
+            .args(["/deny", "*S-1-1-0:(RD)"])
+            .status();
+        if !status.is_ok_and(|status| status.success()) {
+            return;
+        }
+        assert_eq!(find_tag(&junction).unwrap(), ReparseTag::MountPoint);
+
+        // As for a user who can't enable the privilege bypassing the ACL.
+        helpers::SKIP_SET_PRIVILEGE.with(|skip| skip.set(true));
+        let before = opens();
+        let tag = reparse_tag(&junction);
+        let read = get_target(&junction, &ReadOptions::new());
+        helpers::SKIP_SET_PRIVILEGE.with(|skip| skip.set(false));
+        assert_eq!(tag.unwrap(), ReparseTag::MountPoint);
+        // A junction, so the denial is reported rather than "not a junction".
+        assert_eq!(read.unwrap_err().kind(), io::ErrorKind::PermissionDenied);
+        assert_eq!(opens(), before + 2, "one denied open each, then the listing");
+    }
+
+    #[test]
+    fn list_junctions_skips_unreadable() {
+        use std::process::Command;
+
+        let tmpdir = tempfile::tempdir().unwrap();
+        let target = tmpdir.path().join("target");
+        let dir = tmpdir.path().join("dir");
+        fs::create_dir_all(&target).unwrap();
+        fs::create_dir_all(dir.join("plain")).unwrap();
+        fs::write(dir.join("file"), "").unwrap();
+        create(&target, &dir.join("readable"), &CreateOptions::new()).unwrap();
+        create(&target, &dir.join("unreadable"), &CreateOptions::new()).unwrap();
+        // Deny reading data to Everyone, which GENERIC_READ asks for.
+        let status = Command::new("icacls")
+            .arg(dir.join("unreadable"))
+            .args(["/deny", "*S-1-1-0:(RD)"])
+            .status();
+        if !status.is_ok_and(|status| status.success()) {
+            return;
+        }
+
+        // As for a user who can't enable the privilege bypassing the ACL.
+        helpers::SKIP_SET_PRIVILEGE.with(|skip| skip.set(true));
+        let listed = list_junctions_with_targets(&dir);
+        helpers::SKIP_SET_PRIVILEGE.with(|skip| skip.set(false));
+        assert_eq!(listed.unwrap(), vec![(dir.join("readable"), target)]);
+    }
+
+    #[test]
+    fn create_impersonating_reverts() {
+        use std::os::windows::io::{FromRawHandle, OwnedHandle};
+
+        const TOKEN_DUPLICATE: u32 = 0x0002;
+        const TOKEN_QUERY: u32 = 0x0008;
+        let impersonating = || helpers::IMPERSONATING.with(|impersonating| impersonating.get());
+        let tmpdir = tempfile::tempdir().unwrap();
+        let target = tmpdir.path().join("target");
+        let junction = tmpdir.path().join("junction");
+        fs::create_dir_all(&target).unwrap();
+
+        // The process's own token stands in for another user's.
+        let mut handle: c::HANDLE = c::INVALID_HANDLE_VALUE;
+        if unsafe { c::OpenProcessToken(c::GetCurrentProcess(), TOKEN_QUERY | TOKEN_DUPLICATE, &mut handle) } == 0 {
+            panic!("{}", io::Error::last_os_error());
+        }
+        let mut options = CreateOptions::new();
+        options.impersonate(unsafe { OwnedHandle::from_raw_handle(handle) });
+
+        create(&target, &junction, &options).unwrap();
+        assert!(!impersonating());
+        assert_eq!(get_target(&junction, &ReadOptions::new()).unwrap(), target);
+
+        // Reverted on the way out of a failure too.
+        let err = create(&target, &junction, &options).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert!(!impersonating());
+    }
+
+    #[test]
+    #[cfg(feature = "nt-api")]
+    fn nt_api_matches_win32() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let target = tmpdir.path().join("target");
+        let nt = tmpdir.path().join("nt");
+        let win32 = tmpdir.path().join("win32");
+        fs::create_dir_all(&target).unwrap();
+
+        // Opened and written through ntdll.
+        create(&target, &nt, &CreateOptions::new()).unwrap();
+
+        // Opened and written through `CreateFileW` and `DeviceIoControl`.
+        let resolved = resolve_target(&target).unwrap();
+        let (mut data, report) = encode_mount_point(&resolved, &resolved).unwrap();
+        fs::create_dir(&win32).unwrap();
+        let file = helpers::open_exclusive_win32(&win32, c::GENERIC_READ | c::GENERIC_WRITE).unwrap();
+        helpers::fs_control_win32(
+            file.as_raw_handle(),
+            c::FSCTL_SET_REPARSE_POINT,
+            data.as_mut_ptr().cast(),
+            u32::from(report.in_buffer_size),
+        )
+        .unwrap();
+        drop(file);
+
+        assert_eq!(raw_reparse_buffer(&nt).unwrap(), raw_reparse_buffer(&win32).unwrap());
+        assert_eq!(
+            get_target(&nt, &ReadOptions::new()).unwrap(),
+            get_target(&win32, &ReadOptions::new()).unwrap()
+        );
+        delete(&nt, &DeleteOptions::new()).unwrap();
+        assert!(!exists(&nt, &ReadOptions::new()).unwrap());
+    }
+
+    #[test]
+    fn create_report_matches_reparse_data() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let target = tmpdir.path().join("target");
+        let junction = tmpdir.path().join("junction");
+        fs::create_dir_all(&target).unwrap();
+
+        let report = create(&target, &junction, &CreateOptions::new()).unwrap();
+
+        let buf = raw_reparse_buffer(&junction).unwrap();
+        // SubstituteNameLength and PrintNameLength.
+        assert_eq!(report.substitute_len_in_bytes, raw_field(&buf, 10));
+        assert_eq!(report.print_name_len_in_bytes, raw_field(&buf, 14));
+        // The header before ReparseDataLength.
+        assert_eq!(report.in_buffer_size, raw_field(&buf, 4) + 8);
+        assert_eq!(usize::from(report.in_buffer_size), buf.len());
+        let target_len = get_target(&junction, &ReadOptions::new())
+            .unwrap()
+            .as_os_str()
+            .encode_wide()
+            .count();
+        assert_eq!(usize::from(report.print_name_len_in_bytes), target_len * 2);
+    }
+
+    #[test]
+    fn diff_reparse_empty_print_name() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let target = tmpdir.path().join("target");
+        let good = tmpdir.path().join("good");
+        let empty = tmpdir.path().join("empty");
+        fs::create_dir_all(&target).unwrap();
+        create(&target, &good, &CreateOptions::new()).unwrap();
+        assert_eq!(diff_reparse(&good, &good).unwrap(), None);
+
+        // Same buffer, but with PrintNameLength zeroed, as written before
+        // PrintName was populated.
+        let mut raw = raw_reparse_buffer(&good).unwrap();
+        raw[parse::HEADER_SIZE + 6..parse::HEADER_SIZE + 8].copy_from_slice(&0u16.to_le_bytes());
+        fs::create_dir(&empty).unwrap();
+        let file = helpers::open_reparse_point(&empty, true).unwrap();
+        let mut data = cast::BytesAsReparseDataBuffer::new();
+        unsafe { copy_nonoverlapping(raw.as_ptr(), data.as_mut_ptr().cast::<u8>(), raw.len()) };
+        helpers::set_reparse_point(file.as_raw_handle(), data.as_mut_ptr(), raw.len() as u32).unwrap();
+        drop(file);
+
+        let diff = diff_reparse(&good, &empty).unwrap().unwrap();
+        assert_eq!(diff.field, parse::Field::PrintNameLength);
+        assert_eq!(diff.offset, parse::HEADER_SIZE + 6);
     }
 }
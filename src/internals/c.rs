@@ -8,25 +8,16 @@ use std::alloc::Layout;
 use std::os::raw::{c_ulong, c_ushort};
 use std::os::windows::io::RawHandle;
 
-pub use windows_sys::Win32::Foundation::{
-    CloseHandle, GetLastError, SetLastError, ERROR_INSUFFICIENT_BUFFER, FALSE, GENERIC_READ, GENERIC_WRITE, HANDLE,
-    INVALID_HANDLE_VALUE,
-};
-pub use windows_sys::Win32::Security::{
-    AdjustTokenPrivileges, LookupPrivilegeValueW, SE_PRIVILEGE_ENABLED, TOKEN_ADJUST_PRIVILEGES, TOKEN_PRIVILEGES,
-};
-// See more in <https://learn.microsoft.com/en-us/windows/win32/secauthz/privilege-constants>.
-pub use windows_sys::Win32::Security::{SE_BACKUP_NAME, SE_CREATE_SYMBOLIC_LINK_NAME, SE_RESTORE_NAME};
-pub use windows_sys::Win32::Storage::FileSystem::{
-    GetFullPathNameW, FILE_FLAG_BACKUP_SEMANTICS, FILE_FLAG_OPEN_REPARSE_POINT, MAXIMUM_REPARSE_DATA_BUFFER_SIZE,
-    REPARSE_GUID_DATA_BUFFER,
-};
-pub use windows_sys::Win32::System::Ioctl::{
-    FSCTL_DELETE_REPARSE_POINT, FSCTL_GET_REPARSE_POINT, FSCTL_SET_REPARSE_POINT,
-};
-pub use windows_sys::Win32::System::SystemServices::IO_REPARSE_TAG_MOUNT_POINT;
-pub use windows_sys::Win32::System::Threading::{GetCurrentProcess, OpenProcessToken};
-pub use windows_sys::Win32::System::IO::DeviceIoControl;
+// Bindings come from `windows-sys` by default, or from the `windows` crate
+// with the `windows-rs` feature.
+#[cfg(not(feature = "windows-rs"))]
+mod sys;
+#[cfg(not(feature = "windows-rs"))]
+pub use sys::*;
+#[cfg(feature = "windows-rs")]
+mod windows_rs;
+#[cfg(feature = "windows-rs")]
+pub use windows_rs::*;
 
 // Makes sure layout of RawHandle and windows-sys's HANDLE are the same
 // for pointer casts between them.
@@ -38,6 +29,9 @@ const _: () = {
     assert!(std_layout.align() == win_sys_layout.align());
 };
 
+// Makes sure the filesystem-free decoder agrees on the buffer limit.
+const _: () = assert!(MAXIMUM_REPARSE_DATA_BUFFER_SIZE as usize == crate::parse::MAXIMUM_BUFFER_SIZE);
+
 // NOTE: to use `size_of` operator, below structs should be packed.
 /// Reparse Data Buffer header size
 pub const REPARSE_DATA_BUFFER_HEADER_SIZE: u16 = 8;
@@ -45,6 +39,12 @@ pub const REPARSE_DATA_BUFFER_HEADER_SIZE: u16 = 8;
 pub const REPARSE_GUID_DATA_BUFFER_HEADER_SIZE: u16 = 24;
 /// MountPointReparseBuffer header size
 pub const MOUNT_POINT_REPARSE_BUFFER_HEADER_SIZE: u16 = 8;
+/// Size of the fields of REPARSE_DATA_BUFFER_EX before the reparse data buffer:
+/// Flags, ExistingReparseTag, ExistingReparseGuid and Reserved.
+pub const REPARSE_DATA_BUFFER_EX_HEADER_SIZE: usize = 32;
+/// Only set the reparse point if there is none, or the existing one has the
+/// given tag.
+pub const REPARSE_DATA_EX_FLAG_GIVEN_TAG_OR_NONE: u32 = 1;
 
 #[cfg(feature = "nightly")]
 #[allow(clippy::assertions_on_constants)]
@@ -0,0 +1,53 @@
+//! Bindings taken from `windows-sys`.
+
+pub use windows_sys::Win32::Foundation::{
+    CloseHandle, GetLastError, SetLastError, ERROR_HANDLE_EOF, ERROR_INSUFFICIENT_BUFFER, ERROR_INVALID_FUNCTION,
+    ERROR_MORE_DATA, ERROR_NOT_SUPPORTED, ERROR_NO_MORE_FILES, ERROR_SHARING_VIOLATION, FALSE, FILETIME, GENERIC_READ,
+    GENERIC_WRITE, HANDLE, INVALID_HANDLE_VALUE,
+};
+pub use windows_sys::Win32::Security::{
+    AdjustTokenPrivileges, ImpersonateLoggedOnUser, LookupPrivilegeValueW, RevertToSelf, SetFileSecurityW,
+    LABEL_SECURITY_INFORMATION, OWNER_SECURITY_INFORMATION, SE_PRIVILEGE_ENABLED, TOKEN_ADJUST_PRIVILEGES,
+    TOKEN_PRIVILEGES,
+};
+// See more in <https://learn.microsoft.com/en-us/windows/win32/secauthz/privilege-constants>.
+pub use windows_sys::Win32::Security::{SE_BACKUP_NAME, SE_CREATE_SYMBOLIC_LINK_NAME, SE_RESTORE_NAME};
+pub use windows_sys::Win32::Storage::FileSystem::{
+    FindClose, FindExInfoBasic, FindExSearchNameMatch, FindFirstFileExW, FindNextFileW, GetFileInformationByHandle,
+    GetFinalPathNameByHandleW, GetFullPathNameW, GetLongPathNameW, GetVolumeInformationW, GetVolumePathNameW,
+    GetVolumePathNamesForVolumeNameW, LockFileEx, SetFileAttributesW, SetFileTime, BY_HANDLE_FILE_INFORMATION,
+    FILE_ATTRIBUTE_DIRECTORY, FILE_ATTRIBUTE_REPARSE_POINT, FILE_FLAG_BACKUP_SEMANTICS, FILE_FLAG_DELETE_ON_CLOSE,
+    FILE_FLAG_OPEN_NO_RECALL, FILE_FLAG_OPEN_REPARSE_POINT, FILE_READ_ATTRIBUTES, FILE_SHARE_DELETE, FILE_SHARE_READ,
+    FILE_SHARE_WRITE, FIND_FIRST_EX_LARGE_FETCH, LOCKFILE_EXCLUSIVE_LOCK, MAXIMUM_REPARSE_DATA_BUFFER_SIZE,
+    REPARSE_GUID_DATA_BUFFER, WIN32_FIND_DATAW,
+};
+pub use windows_sys::Win32::System::Ioctl::{
+    FSCTL_DELETE_REPARSE_POINT, FSCTL_ENUM_USN_DATA, FSCTL_GET_REPARSE_POINT, FSCTL_QUERY_USN_JOURNAL,
+    FSCTL_SET_REPARSE_POINT, FSCTL_SET_REPARSE_POINT_EX,
+};
+pub use windows_sys::Win32::System::SystemInformation::{GetVersionExW, OSVERSIONINFOW};
+pub use windows_sys::Win32::System::SystemServices::{FILE_SUPPORTS_REPARSE_POINTS, IO_REPARSE_TAG_MOUNT_POINT};
+pub use windows_sys::Win32::System::Threading::{GetCurrentProcess, OpenProcessToken};
+pub use windows_sys::Win32::System::IO::{DeviceIoControl, OVERLAPPED};
+// Used by `watch_target`.
+#[cfg(feature = "watch")]
+pub use windows_sys::Win32::Storage::FileSystem::{
+    ReadDirectoryChangesW, FILE_LIST_DIRECTORY, FILE_NOTIFY_CHANGE_ATTRIBUTES, FILE_NOTIFY_CHANGE_DIR_NAME,
+    FILE_NOTIFY_CHANGE_LAST_WRITE,
+};
+#[cfg(feature = "watch")]
+pub use windows_sys::Win32::System::IO::CancelIoEx;
+// Used by the `nt-api` feature.
+#[cfg(feature = "nt-api")]
+pub use windows_sys::Wdk::Foundation::OBJECT_ATTRIBUTES;
+#[cfg(feature = "nt-api")]
+pub use windows_sys::Wdk::Storage::FileSystem::{
+    NtCreateFile, NtFsControlFile, FILE_CREATE, FILE_DIRECTORY_FILE, FILE_OPEN, FILE_OPEN_FOR_BACKUP_INTENT,
+    FILE_OPEN_NO_RECALL, FILE_OPEN_REPARSE_POINT, FILE_SYNCHRONOUS_IO_NONALERT,
+};
+#[cfg(feature = "nt-api")]
+pub use windows_sys::Win32::Foundation::{RtlNtStatusToDosError, OBJ_CASE_INSENSITIVE, UNICODE_STRING};
+#[cfg(feature = "nt-api")]
+pub use windows_sys::Win32::Storage::FileSystem::SYNCHRONIZE;
+#[cfg(feature = "nt-api")]
+pub use windows_sys::Win32::System::IO::IO_STATUS_BLOCK;
@@ -0,0 +1,455 @@
+//! Bindings taken from the `windows` crate.
+//!
+//! The `windows` crate wraps most items in newtypes and returns `Result`s,
+//! so this module unwraps them back into the raw, `windows-sys`-shaped
+//! signatures that the rest of the crate is written against. Failing calls
+//! still return `0`, leaving the thread's last error untouched for
+//! `io::Error::last_os_error`.
+#![allow(clippy::missing_safety_doc, clippy::upper_case_acronyms)]
+
+use std::ffi::c_void;
+use std::slice;
+
+use windows::core::{PCWSTR, PWSTR};
+pub use windows::Win32::Foundation::FILETIME;
+pub use windows::Win32::Security::TOKEN_PRIVILEGES;
+use windows::Win32::Storage::FileSystem as fs;
+pub use windows::Win32::Storage::FileSystem::{
+    BY_HANDLE_FILE_INFORMATION, MAXIMUM_REPARSE_DATA_BUFFER_SIZE, REPARSE_GUID_DATA_BUFFER, WIN32_FIND_DATAW,
+};
+pub use windows::Win32::System::Ioctl::{
+    FSCTL_DELETE_REPARSE_POINT, FSCTL_ENUM_USN_DATA, FSCTL_GET_REPARSE_POINT, FSCTL_QUERY_USN_JOURNAL,
+    FSCTL_SET_REPARSE_POINT, FSCTL_SET_REPARSE_POINT_EX,
+};
+pub use windows::Win32::System::SystemInformation::OSVERSIONINFOW;
+pub use windows::Win32::System::SystemServices::{FILE_SUPPORTS_REPARSE_POINTS, IO_REPARSE_TAG_MOUNT_POINT};
+pub use windows::Win32::System::IO::OVERLAPPED;
+use windows::Win32::System::{SystemInformation as sysinfo, Threading as threading, IO as io};
+use windows::Win32::{Foundation as foundation, Security as security};
+
+pub type BOOL = i32;
+pub type HANDLE = *mut c_void;
+
+pub const FALSE: BOOL = 0;
+pub const INVALID_HANDLE_VALUE: HANDLE = foundation::INVALID_HANDLE_VALUE.0;
+pub const ERROR_HANDLE_EOF: u32 = foundation::ERROR_HANDLE_EOF.0;
+pub const ERROR_INSUFFICIENT_BUFFER: u32 = foundation::ERROR_INSUFFICIENT_BUFFER.0;
+pub const ERROR_INVALID_FUNCTION: u32 = foundation::ERROR_INVALID_FUNCTION.0;
+pub const ERROR_MORE_DATA: u32 = foundation::ERROR_MORE_DATA.0;
+pub const ERROR_NO_MORE_FILES: u32 = foundation::ERROR_NO_MORE_FILES.0;
+pub const ERROR_NOT_SUPPORTED: u32 = foundation::ERROR_NOT_SUPPORTED.0;
+pub const ERROR_SHARING_VIOLATION: u32 = foundation::ERROR_SHARING_VIOLATION.0;
+pub const GENERIC_READ: u32 = foundation::GENERIC_READ.0;
+pub const GENERIC_WRITE: u32 = foundation::GENERIC_WRITE.0;
+pub const OWNER_SECURITY_INFORMATION: u32 = security::OWNER_SECURITY_INFORMATION.0;
+pub const LABEL_SECURITY_INFORMATION: u32 = security::LABEL_SECURITY_INFORMATION.0;
+pub const SE_PRIVILEGE_ENABLED: security::TOKEN_PRIVILEGES_ATTRIBUTES = security::SE_PRIVILEGE_ENABLED;
+pub const TOKEN_ADJUST_PRIVILEGES: u32 = security::TOKEN_ADJUST_PRIVILEGES.0;
+// See more in <https://learn.microsoft.com/en-us/windows/win32/secauthz/privilege-constants>.
+pub const SE_BACKUP_NAME: *const u16 = security::SE_BACKUP_NAME.0;
+pub const SE_CREATE_SYMBOLIC_LINK_NAME: *const u16 = security::SE_CREATE_SYMBOLIC_LINK_NAME.0;
+pub const SE_RESTORE_NAME: *const u16 = security::SE_RESTORE_NAME.0;
+pub const FILE_ATTRIBUTE_DIRECTORY: u32 = fs::FILE_ATTRIBUTE_DIRECTORY.0;
+pub const FILE_ATTRIBUTE_REPARSE_POINT: u32 = fs::FILE_ATTRIBUTE_REPARSE_POINT.0;
+pub const FILE_FLAG_BACKUP_SEMANTICS: u32 = fs::FILE_FLAG_BACKUP_SEMANTICS.0;
+pub const FILE_FLAG_DELETE_ON_CLOSE: u32 = fs::FILE_FLAG_DELETE_ON_CLOSE.0;
+#[cfg(feature = "watch")]
+pub const FILE_LIST_DIRECTORY: u32 = fs::FILE_LIST_DIRECTORY.0;
+#[cfg(feature = "watch")]
+pub const FILE_NOTIFY_CHANGE_ATTRIBUTES: u32 = fs::FILE_NOTIFY_CHANGE_ATTRIBUTES.0;
+#[cfg(feature = "watch")]
+pub const FILE_NOTIFY_CHANGE_DIR_NAME: u32 = fs::FILE_NOTIFY_CHANGE_DIR_NAME.0;
+#[cfg(feature = "watch")]
+pub const FILE_NOTIFY_CHANGE_LAST_WRITE: u32 = fs::FILE_NOTIFY_CHANGE_LAST_WRITE.0;
+pub const FILE_FLAG_OPEN_NO_RECALL: u32 = fs::FILE_FLAG_OPEN_NO_RECALL.0;
+pub const FILE_FLAG_OPEN_REPARSE_POINT: u32 = fs::FILE_FLAG_OPEN_REPARSE_POINT.0;
+pub const FILE_READ_ATTRIBUTES: u32 = fs::FILE_READ_ATTRIBUTES.0;
+pub const LOCKFILE_EXCLUSIVE_LOCK: u32 = fs::LOCKFILE_EXCLUSIVE_LOCK.0;
+pub const FILE_SHARE_DELETE: u32 = fs::FILE_SHARE_DELETE.0;
+pub const FILE_SHARE_READ: u32 = fs::FILE_SHARE_READ.0;
+pub const FILE_SHARE_WRITE: u32 = fs::FILE_SHARE_WRITE.0;
+pub const FIND_FIRST_EX_LARGE_FETCH: u32 = fs::FIND_FIRST_EX_LARGE_FETCH.0;
+#[allow(non_upper_case_globals)]
+pub const FindExInfoBasic: i32 = fs::FindExInfoBasic.0;
+#[allow(non_upper_case_globals)]
+pub const FindExSearchNameMatch: i32 = fs::FindExSearchNameMatch.0;
+
+fn to_bool<T>(result: windows::core::Result<T>) -> BOOL {
+    result.is_ok() as BOOL
+}
+
+fn non_null<T>(ptr: *const T) -> Option<*const T> {
+    (!ptr.is_null()).then_some(ptr)
+}
+
+fn non_null_mut<T>(ptr: *mut T) -> Option<*mut T> {
+    (!ptr.is_null()).then_some(ptr)
+}
+
+pub unsafe fn CloseHandle(handle: HANDLE) -> BOOL {
+    to_bool(foundation::CloseHandle(foundation::HANDLE(handle)))
+}
+
+pub unsafe fn GetLastError() -> u32 {
+    foundation::GetLastError().0
+}
+
+pub unsafe fn SetLastError(code: u32) {
+    foundation::SetLastError(foundation::WIN32_ERROR(code))
+}
+
+pub unsafe fn GetCurrentProcess() -> HANDLE {
+    threading::GetCurrentProcess().0
+}
+
+pub unsafe fn OpenProcessToken(process: HANDLE, access: u32, token: *mut HANDLE) -> BOOL {
+    // `HANDLE` is a transparent wrapper around the raw pointer.
+    let token = token.cast::<foundation::HANDLE>();
+    to_bool(threading::OpenProcessToken(
+        foundation::HANDLE(process),
+        security::TOKEN_ACCESS_MASK(access),
+        token,
+    ))
+}
+
+pub unsafe fn LookupPrivilegeValueW(system: *const u16, name: *const u16, luid: *mut foundation::LUID) -> BOOL {
+    to_bool(security::LookupPrivilegeValueW(PCWSTR(system), PCWSTR(name), luid))
+}
+
+pub unsafe fn AdjustTokenPrivileges(
+    token: HANDLE,
+    disable_all: BOOL,
+    new_state: *const TOKEN_PRIVILEGES,
+    len: u32,
+    previous_state: *mut TOKEN_PRIVILEGES,
+    return_len: *mut u32,
+) -> BOOL {
+    to_bool(security::AdjustTokenPrivileges(
+        foundation::HANDLE(token),
+        disable_all != FALSE,
+        non_null(new_state),
+        len,
+        non_null_mut(previous_state),
+        non_null_mut(return_len),
+    ))
+}
+
+pub unsafe fn ImpersonateLoggedOnUser(token: HANDLE) -> BOOL {
+    to_bool(security::ImpersonateLoggedOnUser(foundation::HANDLE(token)))
+}
+
+pub unsafe fn RevertToSelf() -> BOOL {
+    to_bool(security::RevertToSelf())
+}
+
+pub unsafe fn SetFileSecurityW(file_name: *const u16, information: u32, descriptor: *mut c_void) -> BOOL {
+    security::SetFileSecurityW(
+        PCWSTR(file_name),
+        security::OBJECT_SECURITY_INFORMATION(information),
+        security::PSECURITY_DESCRIPTOR(descriptor),
+    )
+    .0
+}
+
+pub unsafe fn GetFinalPathNameByHandleW(file: HANDLE, buf: *mut u16, len: u32, flags: u32) -> u32 {
+    // SAFETY: the caller hands us `len` writable units, which the API only writes to.
+    let buf = slice::from_raw_parts_mut(buf, len as usize);
+    fs::GetFinalPathNameByHandleW(foundation::HANDLE(file), buf, fs::GETFINALPATHNAMEBYHANDLE_FLAGS(flags))
+}
+
+pub unsafe fn GetFullPathNameW(file_name: *const u16, len: u32, buf: *mut u16, file_part: *mut PWSTR) -> u32 {
+    // SAFETY: the caller hands us `len` writable units, which the API only writes to.
+    let buf = non_null_mut(buf).map(|buf| slice::from_raw_parts_mut(buf, len as usize));
+    fs::GetFullPathNameW(PCWSTR(file_name), buf, non_null_mut(file_part))
+}
+
+pub unsafe fn GetLongPathNameW(short_path: *const u16, buf: *mut u16, len: u32) -> u32 {
+    // SAFETY: the caller hands us `len` writable units, which the API only writes to.
+    let buf = non_null_mut(buf).map(|buf| slice::from_raw_parts_mut(buf, len as usize));
+    fs::GetLongPathNameW(PCWSTR(short_path), buf)
+}
+
+pub unsafe fn GetVolumePathNameW(file_name: *const u16, buf: *mut u16, len: u32) -> BOOL {
+    // SAFETY: the caller hands us `len` writable units, which the API only writes to.
+    let buf = slice::from_raw_parts_mut(buf, len as usize);
+    to_bool(fs::GetVolumePathNameW(PCWSTR(file_name), buf))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub unsafe fn GetVolumeInformationW(
+    root_path_name: *const u16,
+    volume_name: *mut u16,
+    volume_name_len: u32,
+    serial_number: *mut u32,
+    max_component_len: *mut u32,
+    file_system_flags: *mut u32,
+    file_system_name: *mut u16,
+    file_system_name_len: u32,
+) -> BOOL {
+    // SAFETY: the caller hands us writable units of the given lengths.
+    let volume_name = non_null_mut(volume_name).map(|buf| slice::from_raw_parts_mut(buf, volume_name_len as usize));
+    let file_system_name =
+        non_null_mut(file_system_name).map(|buf| slice::from_raw_parts_mut(buf, file_system_name_len as usize));
+    to_bool(fs::GetVolumeInformationW(
+        PCWSTR(root_path_name),
+        volume_name,
+        non_null_mut(serial_number),
+        non_null_mut(max_component_len),
+        non_null_mut(file_system_flags),
+        file_system_name,
+    ))
+}
+
+pub unsafe fn GetVolumePathNamesForVolumeNameW(
+    volume_name: *const u16,
+    buf: *mut u16,
+    len: u32,
+    return_len: *mut u32,
+) -> BOOL {
+    // SAFETY: the caller hands us `len` writable units, which the API only writes to.
+    let buf = non_null_mut(buf).map(|buf| slice::from_raw_parts_mut(buf, len as usize));
+    to_bool(fs::GetVolumePathNamesForVolumeNameW(
+        PCWSTR(volume_name),
+        buf,
+        return_len,
+    ))
+}
+
+pub unsafe fn FindFirstFileExW(
+    file_name: *const u16,
+    info_level: i32,
+    find_data: *mut c_void,
+    search_op: i32,
+    search_filter: *const c_void,
+    flags: u32,
+) -> HANDLE {
+    fs::FindFirstFileExW(
+        PCWSTR(file_name),
+        fs::FINDEX_INFO_LEVELS(info_level),
+        find_data,
+        fs::FINDEX_SEARCH_OPS(search_op),
+        non_null(search_filter),
+        fs::FIND_FIRST_EX_FLAGS(flags),
+    )
+    .map_or(INVALID_HANDLE_VALUE, |handle| handle.0)
+}
+
+pub unsafe fn FindNextFileW(find_file: HANDLE, find_data: *mut WIN32_FIND_DATAW) -> BOOL {
+    to_bool(fs::FindNextFileW(foundation::HANDLE(find_file), find_data))
+}
+
+pub unsafe fn FindClose(find_file: HANDLE) -> BOOL {
+    to_bool(fs::FindClose(foundation::HANDLE(find_file)))
+}
+
+pub unsafe fn GetFileInformationByHandle(file: HANDLE, info: *mut BY_HANDLE_FILE_INFORMATION) -> BOOL {
+    to_bool(fs::GetFileInformationByHandle(foundation::HANDLE(file), info))
+}
+
+pub unsafe fn SetFileTime(
+    file: HANDLE,
+    creation_time: *const FILETIME,
+    last_access_time: *const FILETIME,
+    last_write_time: *const FILETIME,
+) -> BOOL {
+    to_bool(fs::SetFileTime(
+        foundation::HANDLE(file),
+        non_null(creation_time),
+        non_null(last_access_time),
+        non_null(last_write_time),
+    ))
+}
+
+pub unsafe fn SetFileAttributesW(file_name: *const u16, attributes: u32) -> BOOL {
+    to_bool(fs::SetFileAttributesW(
+        PCWSTR(file_name),
+        fs::FILE_FLAGS_AND_ATTRIBUTES(attributes),
+    ))
+}
+
+#[cfg(feature = "watch")]
+#[allow(clippy::too_many_arguments)]
+pub unsafe fn ReadDirectoryChangesW(
+    directory: HANDLE,
+    buf: *mut c_void,
+    len: u32,
+    watch_subtree: BOOL,
+    notify_filter: u32,
+    bytes_returned: *mut u32,
+    overlapped: *mut OVERLAPPED,
+    completion_routine: io::LPOVERLAPPED_COMPLETION_ROUTINE,
+) -> BOOL {
+    to_bool(fs::ReadDirectoryChangesW(
+        foundation::HANDLE(directory),
+        buf,
+        len,
+        watch_subtree != FALSE,
+        fs::FILE_NOTIFY_CHANGE(notify_filter),
+        non_null_mut(bytes_returned),
+        non_null_mut(overlapped),
+        completion_routine,
+    ))
+}
+
+#[cfg(feature = "watch")]
+pub unsafe fn CancelIoEx(file: HANDLE, overlapped: *const OVERLAPPED) -> BOOL {
+    to_bool(io::CancelIoEx(foundation::HANDLE(file), non_null(overlapped)))
+}
+
+pub unsafe fn GetVersionExW(info: *mut OSVERSIONINFOW) -> BOOL {
+    to_bool(sysinfo::GetVersionExW(info))
+}
+
+pub unsafe fn LockFileEx(
+    file: HANDLE,
+    flags: u32,
+    reserved: u32,
+    len_low: u32,
+    len_high: u32,
+    overlapped: *mut OVERLAPPED,
+) -> BOOL {
+    to_bool(fs::LockFileEx(
+        foundation::HANDLE(file),
+        fs::LOCK_FILE_FLAGS(flags),
+        Some(reserved),
+        len_low,
+        len_high,
+        overlapped,
+    ))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub unsafe fn DeviceIoControl(
+    device: HANDLE,
+    code: u32,
+    in_buf: *const c_void,
+    in_len: u32,
+    out_buf: *mut c_void,
+    out_len: u32,
+    bytes_returned: *mut u32,
+    overlapped: *mut OVERLAPPED,
+) -> BOOL {
+    to_bool(io::DeviceIoControl(
+        foundation::HANDLE(device),
+        code,
+        non_null(in_buf),
+        in_len,
+        non_null_mut(out_buf),
+        out_len,
+        non_null_mut(bytes_returned),
+        non_null_mut(overlapped),
+    ))
+}
+
+// Used by the `nt-api` feature.
+#[cfg(feature = "nt-api")]
+use windows::Wdk::Storage::FileSystem as nt;
+#[cfg(feature = "nt-api")]
+pub use windows::Win32::System::IO::IO_STATUS_BLOCK;
+
+#[cfg(feature = "nt-api")]
+pub const FILE_CREATE: u32 = nt::FILE_CREATE.0;
+#[cfg(feature = "nt-api")]
+pub const FILE_DIRECTORY_FILE: u32 = nt::FILE_DIRECTORY_FILE.0;
+#[cfg(feature = "nt-api")]
+pub const FILE_OPEN: u32 = nt::FILE_OPEN.0;
+#[cfg(feature = "nt-api")]
+pub const FILE_OPEN_FOR_BACKUP_INTENT: u32 = nt::FILE_OPEN_FOR_BACKUP_INTENT.0;
+#[cfg(feature = "nt-api")]
+pub const FILE_OPEN_NO_RECALL: u32 = nt::FILE_OPEN_NO_RECALL.0;
+#[cfg(feature = "nt-api")]
+pub const FILE_OPEN_REPARSE_POINT: u32 = nt::FILE_OPEN_REPARSE_POINT.0;
+#[cfg(feature = "nt-api")]
+pub const FILE_SYNCHRONOUS_IO_NONALERT: u32 = nt::FILE_SYNCHRONOUS_IO_NONALERT.0;
+#[cfg(feature = "nt-api")]
+pub const OBJ_CASE_INSENSITIVE: u32 = foundation::OBJ_CASE_INSENSITIVE.0;
+#[cfg(feature = "nt-api")]
+pub const SYNCHRONIZE: u32 = fs::SYNCHRONIZE.0;
+
+/// Laid out like `UNICODE_STRING`, with a raw pointer in place of `PWSTR`.
+#[cfg(feature = "nt-api")]
+#[repr(C)]
+pub struct UNICODE_STRING {
+    pub Length: u16,
+    pub MaximumLength: u16,
+    pub Buffer: *mut u16,
+}
+
+/// Laid out like `OBJECT_ATTRIBUTES`, with raw handles, pointers and flags in
+/// place of their newtypes.
+#[cfg(feature = "nt-api")]
+#[repr(C)]
+pub struct OBJECT_ATTRIBUTES {
+    pub Length: u32,
+    pub RootDirectory: HANDLE,
+    pub ObjectName: *const UNICODE_STRING,
+    pub Attributes: u32,
+    pub SecurityDescriptor: *const c_void,
+    pub SecurityQualityOfService: *const c_void,
+}
+
+#[cfg(feature = "nt-api")]
+#[allow(clippy::too_many_arguments)]
+pub unsafe fn NtCreateFile(
+    file: *mut HANDLE,
+    access: u32,
+    attributes: *const OBJECT_ATTRIBUTES,
+    io_status: *mut IO_STATUS_BLOCK,
+    allocation_size: *const i64,
+    file_attributes: u32,
+    share_mode: u32,
+    disposition: u32,
+    options: u32,
+    ea_buf: *const c_void,
+    ea_len: u32,
+) -> i32 {
+    // Both structs have the same layout as the ones they stand in for.
+    nt::NtCreateFile(
+        file.cast::<foundation::HANDLE>(),
+        fs::FILE_ACCESS_RIGHTS(access),
+        attributes.cast(),
+        io_status,
+        non_null(allocation_size),
+        fs::FILE_FLAGS_AND_ATTRIBUTES(file_attributes),
+        fs::FILE_SHARE_MODE(share_mode),
+        nt::NTCREATEFILE_CREATE_DISPOSITION(disposition),
+        nt::NTCREATEFILE_CREATE_OPTIONS(options),
+        non_null(ea_buf),
+        ea_len,
+    )
+    .0
+}
+
+#[cfg(feature = "nt-api")]
+#[allow(clippy::too_many_arguments)]
+pub unsafe fn NtFsControlFile(
+    file: HANDLE,
+    event: HANDLE,
+    apc_routine: io::PIO_APC_ROUTINE,
+    apc_context: *const c_void,
+    io_status: *mut IO_STATUS_BLOCK,
+    code: u32,
+    in_buf: *const c_void,
+    in_len: u32,
+    out_buf: *mut c_void,
+    out_len: u32,
+) -> i32 {
+    nt::NtFsControlFile(
+        foundation::HANDLE(file),
+        (!event.is_null()).then_some(foundation::HANDLE(event)),
+        apc_routine,
+        non_null(apc_context),
+        io_status,
+        code,
+        non_null(in_buf),
+        in_len,
+        non_null_mut(out_buf),
+        out_len,
+    )
+    .0
+}
+
+#[cfg(feature = "nt-api")]
+pub unsafe fn RtlNtStatusToDosError(status: i32) -> u32 {
+    foundation::RtlNtStatusToDosError(foundation::NTSTATUS(status))
+}
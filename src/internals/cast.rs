@@ -1,13 +1,23 @@
 use std::alloc::{alloc, handle_alloc_error, Layout};
-use std::mem::align_of;
+use std::cell::Cell;
+use std::mem::{align_of, ManuallyDrop};
 
 use super::c::{MAXIMUM_REPARSE_DATA_BUFFER_SIZE, REPARSE_DATA_BUFFER};
+use crate::options::BufferStrategy;
 
 type MaybeU8 = std::mem::MaybeUninit<u8>;
+type Raw = [MaybeU8; MAXIMUM_REPARSE_DATA_BUFFER_SIZE as usize];
+
+thread_local! {
+    /// The buffer `BufferStrategy::ThreadLocal` reuses, when not in use.
+    static POOLED: Cell<Option<Box<Raw>>> = const { Cell::new(None) };
+}
 
 #[repr(align(4))]
 pub struct BytesAsReparseDataBuffer {
-    value: Box<[MaybeU8; MAXIMUM_REPARSE_DATA_BUFFER_SIZE as usize]>,
+    value: ManuallyDrop<Box<Raw>>,
+    /// Whether `value` goes back to `POOLED` when dropped.
+    pooled: bool,
 }
 
 // Asserts that pointers of `BytesAsReparseDataBuffer` can be casted to
@@ -21,23 +31,55 @@ const _: () = {
 impl BytesAsReparseDataBuffer {
     // MSRV(1.82): Use `Box::new_uninit_slice` instead.
     pub fn new() -> Self {
-        type Raw = [MaybeU8; MAXIMUM_REPARSE_DATA_BUFFER_SIZE as usize];
-        const LAYOUT: Layout = Layout::new::<Raw>();
-        let boxed = unsafe {
-            let ptr = alloc(LAYOUT).cast::<Raw>();
-            if ptr.is_null() {
-                handle_alloc_error(LAYOUT);
+        Self {
+            value: ManuallyDrop::new(allocate()),
+            pooled: false,
+        }
+    }
+
+    /// Allocates a buffer following `strategy`.
+    pub fn with_strategy(strategy: BufferStrategy) -> Self {
+        match strategy {
+            BufferStrategy::PerCall => Self::new(),
+            BufferStrategy::ThreadLocal => {
+                // Taken out while in use, so a nested use allocates its own.
+                let value = POOLED.with(Cell::take).unwrap_or_else(allocate);
+                Self {
+                    value: ManuallyDrop::new(value),
+                    pooled: true,
+                }
             }
-            Box::from_raw(ptr)
-        };
-        Self { value: boxed }
+        }
     }
 
     pub fn as_mut_ptr(&mut self) -> *mut REPARSE_DATA_BUFFER {
         self.value.as_mut_ptr().cast::<REPARSE_DATA_BUFFER>()
     }
 
-    pub unsafe fn assume_init(&mut self) -> &REPARSE_DATA_BUFFER {
-        &*self.as_mut_ptr()
+    /// The first `len` bytes, which must have been initialized.
+    pub unsafe fn assume_init_bytes(&self, len: usize) -> &[u8] {
+        std::slice::from_raw_parts(self.value.as_ptr().cast::<u8>(), len)
+    }
+}
+
+impl Drop for BytesAsReparseDataBuffer {
+    fn drop(&mut self) {
+        // SAFETY: `value` is not used again.
+        let value = unsafe { ManuallyDrop::take(&mut self.value) };
+        if self.pooled {
+            // Fails once the thread is exiting, when the buffer is just freed.
+            let _ = POOLED.try_with(|pooled| pooled.set(Some(value)));
+        }
+    }
+}
+
+fn allocate() -> Box<Raw> {
+    const LAYOUT: Layout = Layout::new::<Raw>();
+    unsafe {
+        let ptr = alloc(LAYOUT).cast::<Raw>();
+        if ptr.is_null() {
+            handle_alloc_error(LAYOUT);
+        }
+        Box::from_raw(ptr)
     }
 }
@@ -1,40 +1,440 @@
+#[cfg(feature = "nt-api")]
+mod nt;
 mod utf16;
 
-use std::ffi::OsStr;
+use std::ffi::{c_void, OsStr, OsString};
 use std::fs::{File, OpenOptions};
-use std::io;
-use std::mem::{size_of, zeroed, MaybeUninit};
-use std::os::windows::ffi::OsStrExt;
-use std::os::windows::fs::OpenOptionsExt;
+use std::mem::{size_of, size_of_val, zeroed, MaybeUninit};
+use std::os::windows::ffi::{OsStrExt, OsStringExt};
+use std::os::windows::fs::{MetadataExt, OpenOptionsExt};
+use std::os::windows::io::{AsRawHandle, OwnedHandle};
 use std::path::Path;
-use std::ptr::{addr_of_mut, null, null_mut};
+use std::ptr::{addr_of_mut, copy_nonoverlapping, null, null_mut};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::{fs, io, slice, thread};
 
 pub(crate) use utf16::utf16s;
+pub use utf16::{eq_ignore_case, upcase};
 
 use super::c;
+use crate::options::Retry;
+use crate::parse;
 
-pub fn open_reparse_point(reparse_point: &Path, write: bool) -> io::Result<File> {
-    let access = c::GENERIC_READ | if write { c::GENERIC_WRITE } else { 0 };
-    // Set this flag to obtain a handle to a directory. Appropriate security checks
-    // still apply when this flag is used without SE_BACKUP_NAME and SE_RESTORE_NAME
-    // privileges.
-    // Ref <https://learn.microsoft.com/en-us/windows/win32/api/fileapi/nf-fileapi-createfilea#directories>
-    let dir_attrs = c::FILE_FLAG_OPEN_REPARSE_POINT | c::FILE_FLAG_BACKUP_SEMANTICS;
-    let mut opts = OpenOptions::new();
-    opts.access_mode(access).share_mode(0).custom_flags(dir_attrs);
-    // Opens existing directory path
-    match opts.open(reparse_point) {
-        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {
-            set_privilege(write)?;
-            opts.open(reparse_point)
+#[cfg(test)]
+thread_local! {
+    /// Number of times `open_reparse_point` was called on this thread.
+    pub static REPARSE_POINT_OPENS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+    /// Makes `set_reparse_point` skip `FSCTL_SET_REPARSE_POINT_EX` on this thread.
+    pub static SKIP_SET_REPARSE_POINT_EX: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+    /// Number of handles from `open_reparse_point` still open on this thread.
+    pub static OPEN_REPARSE_HANDLES: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+    /// Makes `set_privilege` fail as for a user not holding the privilege on this thread.
+    pub static SKIP_SET_PRIVILEGE: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+    /// Whether an `Impersonation` is active on this thread.
+    pub static IMPERSONATING: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+}
+
+/// A handle to a reparse point, as opened by [`open_reparse_point`].
+#[cfg(not(test))]
+pub type ReparseHandle = File;
+
+/// A handle to a reparse point, as opened by [`open_reparse_point`], counted
+/// in `OPEN_REPARSE_HANDLES` until it is closed.
+#[cfg(test)]
+#[derive(Debug)]
+pub struct ReparseHandle(File);
+
+#[cfg(test)]
+impl ReparseHandle {
+    fn new(file: File) -> Self {
+        OPEN_REPARSE_HANDLES.with(|open| open.set(open.get() + 1));
+        Self(file)
+    }
+}
+
+#[cfg(test)]
+impl std::ops::Deref for ReparseHandle {
+    type Target = File;
+
+    fn deref(&self) -> &File {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+impl Drop for ReparseHandle {
+    fn drop(&mut self) {
+        OPEN_REPARSE_HANDLES.with(|open| open.set(open.get() - 1));
+    }
+}
+
+/// Runs the current thread as the user of a token until dropped, when the
+/// thread reverts to the process's own token, whether or not the work done in
+/// between succeeded.
+pub struct Impersonation(());
+
+impl Impersonation {
+    pub fn new(token: &OwnedHandle) -> io::Result<Self> {
+        if unsafe { c::ImpersonateLoggedOnUser(token.as_raw_handle()) } == 0 {
+            return Err(io::Error::last_os_error());
         }
+        #[cfg(test)]
+        IMPERSONATING.with(|impersonating| impersonating.set(true));
+        Ok(Self(()))
+    }
+}
+
+impl Drop for Impersonation {
+    fn drop(&mut self) {
+        // There is nothing left to do if even this fails.
+        unsafe { c::RevertToSelf() };
+        #[cfg(test)]
+        IMPERSONATING.with(|impersonating| impersonating.set(false));
+    }
+}
+
+/// Flags every handle to a reparse point is opened with.
+///
+/// Set this flag to obtain a handle to a directory. Appropriate security checks
+/// still apply when this flag is used without SE_BACKUP_NAME and SE_RESTORE_NAME
+/// privileges.
+/// Ref <https://learn.microsoft.com/en-us/windows/win32/api/fileapi/nf-fileapi-createfilea#directories>
+///
+/// `FILE_FLAG_OPEN_NO_RECALL` keeps cloud files placeholders, such as those of
+/// OneDrive, from being hydrated just to read their reparse data.
+const REPARSE_POINT_FLAGS: u32 =
+    c::FILE_FLAG_OPEN_REPARSE_POINT | c::FILE_FLAG_BACKUP_SEMANTICS | c::FILE_FLAG_OPEN_NO_RECALL;
+
+// Closing a handle opened with `FILE_FLAG_DELETE_ON_CLOSE` would delete the
+// junction itself.
+const _: () = assert!(REPARSE_POINT_FLAGS & c::FILE_FLAG_DELETE_ON_CLOSE == 0);
+
+/// Cleared once `FSCTL_SET_REPARSE_POINT_EX` turns out not to be supported.
+static SET_REPARSE_POINT_EX: AtomicBool = AtomicBool::new(true);
+
+/// The access rights [`open_reparse_point`] requests.
+const fn reparse_point_access(write: bool) -> u32 {
+    c::GENERIC_READ | if write { c::GENERIC_WRITE } else { 0 }
+}
+
+// Reading must not ask for write access, which read-only volumes such as
+// mounted disk images refuse even to reparse points that are only read.
+const _: () = assert!(reparse_point_access(false) & c::GENERIC_WRITE == 0);
+
+pub fn open_reparse_point(reparse_point: &Path, write: bool) -> io::Result<ReparseHandle> {
+    open_reparse_point_with_access(reparse_point, reparse_point_access(write), write)
+}
+
+/// Like [`open_reparse_point`], but requests exactly `access`. `write` selects
+/// which privilege to enable if opening is denied.
+pub fn open_reparse_point_with_access(reparse_point: &Path, access: u32, write: bool) -> io::Result<ReparseHandle> {
+    #[cfg(test)]
+    REPARSE_POINT_OPENS.with(|opens| opens.set(opens.get() + 1));
+    // Opens existing directory path
+    let file = match open_exclusive(reparse_point, access) {
+        // Without the privilege, the denial is what the caller needs to see.
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => match set_privilege(write) {
+            Ok(()) => open_exclusive(reparse_point, access),
+            Err(_) => Err(e),
+        },
         other => other,
+    }?;
+    #[cfg(test)]
+    let file = ReparseHandle::new(file);
+    Ok(file)
+}
+
+/// Opens the reparse point at `path` itself with `access` and without sharing.
+#[cfg(any(test, not(feature = "nt-api")))]
+pub fn open_exclusive_win32(path: &Path, access: u32) -> io::Result<File> {
+    OpenOptions::new()
+        .access_mode(access)
+        .share_mode(0)
+        .custom_flags(REPARSE_POINT_FLAGS)
+        .open(path)
+}
+
+#[cfg(feature = "nt-api")]
+pub use nt::create_dir_at;
+#[cfg(feature = "nt-api")]
+use nt::{fs_control, open_exclusive};
+#[cfg(not(feature = "nt-api"))]
+use {fs_control_win32 as fs_control, open_exclusive_win32 as open_exclusive};
+
+/// Opens the symbolic link at `path` itself for reading its reparse data,
+/// whether it links to a file or a directory.
+///
+/// Only directories need `FILE_FLAG_BACKUP_SEMANTICS`, and other handles are
+/// not locked out.
+pub fn open_symlink(path: &Path) -> io::Result<File> {
+    let attributes = fs::symlink_metadata(path)?.file_attributes();
+    let flags = if attributes & c::FILE_ATTRIBUTE_DIRECTORY != 0 {
+        REPARSE_POINT_FLAGS
+    } else {
+        c::FILE_FLAG_OPEN_REPARSE_POINT
+    };
+    OpenOptions::new()
+        .access_mode(c::GENERIC_READ)
+        .share_mode(c::FILE_SHARE_READ | c::FILE_SHARE_WRITE | c::FILE_SHARE_DELETE)
+        .custom_flags(flags)
+        .open(path)
+}
+
+/// Opens the directory at `path`, following reparse points, and returns its
+/// final path as the file system sees it, with the verbatim prefix (`\\?\`).
+///
+/// Fails if `path` doesn't exist, or isn't a directory.
+pub fn final_directory_path(path: &Path) -> io::Result<Vec<u16>> {
+    let dir = OpenOptions::new()
+        .access_mode(c::FILE_READ_ATTRIBUTES)
+        .share_mode(c::FILE_SHARE_READ | c::FILE_SHARE_WRITE | c::FILE_SHARE_DELETE)
+        .custom_flags(c::FILE_FLAG_BACKUP_SEMANTICS)
+        .open(path)?;
+    if !dir.metadata()?.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "`target` is not a directory",
+        ));
+    }
+    let handle = dir.as_raw_handle() as c::HANDLE;
+    let mut buf: Vec<u16> = vec![0; 512];
+    loop {
+        // FILE_NAME_NORMALIZED | VOLUME_NAME_DOS
+        let len = unsafe { c::GetFinalPathNameByHandleW(handle, buf.as_mut_ptr(), buf.len() as u32, 0) } as usize;
+        if len == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // Too small, and `len` is the size needed, null terminator included.
+        if len >= buf.len() {
+            buf.resize(len, 0);
+            continue;
+        }
+        buf.truncate(len);
+        return Ok(buf);
+    }
+}
+
+/// Opens the file at `path`, creating it if needed, and waits until this
+/// handle holds an exclusive lock on it. The lock is released once the
+/// returned file is closed, even if the process dies.
+pub fn lock_file(path: &Path) -> io::Result<File> {
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .share_mode(c::FILE_SHARE_READ | c::FILE_SHARE_WRITE | c::FILE_SHARE_DELETE)
+        .open(path)?;
+    // Locks the whole file, whatever its size. The handle is synchronous, so
+    // the call only returns once the lock is held.
+    let mut overlapped: c::OVERLAPPED = unsafe { zeroed() };
+    if unsafe {
+        c::LockFileEx(
+            file.as_raw_handle() as c::HANDLE,
+            c::LOCKFILE_EXCLUSIVE_LOCK,
+            0,
+            u32::MAX,
+            u32::MAX,
+            &mut overlapped,
+        )
+    } == 0
+    {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(file)
+}
+
+/// Calls `open` until it succeeds or fails with anything but a sharing
+/// violation, at most `retry.attempts` more times, backing off exponentially.
+pub fn retry_sharing_violation<T>(retry: Retry, open: impl FnMut() -> io::Result<T>) -> io::Result<T> {
+    retry_while(retry, is_sharing_violation, open)
+}
+
+/// Like [`retry_sharing_violation`], but also retries when access is denied,
+/// as it transiently is while antivirus software scans a new junction.
+pub fn retry_transient<T>(retry: Retry, op: impl FnMut() -> io::Result<T>) -> io::Result<T> {
+    retry_while(
+        retry,
+        |e| is_sharing_violation(e) || e.kind() == io::ErrorKind::PermissionDenied,
+        op,
+    )
+}
+
+fn is_sharing_violation(e: &io::Error) -> bool {
+    e.raw_os_error() == Some(c::ERROR_SHARING_VIOLATION as i32)
+}
+
+fn retry_while<T>(
+    retry: Retry,
+    transient: impl Fn(&io::Error) -> bool,
+    mut open: impl FnMut() -> io::Result<T>,
+) -> io::Result<T> {
+    let mut backoff = retry.backoff;
+    for _ in 0..retry.attempts {
+        match open() {
+            Err(e) if transient(&e) => {
+                thread::sleep(backoff);
+                backoff = backoff.saturating_mul(2);
+            }
+            other => return other,
+        }
+    }
+    open()
+}
+
+/// Opens each ancestor of the absolute `path`, from its root down, without
+/// following reparse points and without allowing them to be deleted or
+/// renamed while the handles are open.
+///
+/// Errors with `InvalidInput` if one of them is a reparse point that
+/// [`redirects`] elsewhere.
+pub fn pin_parents(path: &Path) -> io::Result<Vec<File>> {
+    let mut parents: Vec<&Path> = path.ancestors().skip(1).collect();
+    parents.reverse();
+    let mut pinned = Vec::with_capacity(parents.len());
+    for parent in parents {
+        let file = OpenOptions::new()
+            .access_mode(c::FILE_READ_ATTRIBUTES)
+            .share_mode(c::FILE_SHARE_READ | c::FILE_SHARE_WRITE)
+            .custom_flags(REPARSE_POINT_FLAGS)
+            .open(parent)?;
+        if file.metadata()?.file_attributes() & c::FILE_ATTRIBUTE_REPARSE_POINT != 0 && redirects(&file)? {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("parent {} of `junction` is a link", parent.display()),
+            ));
+        }
+        pinned.push(file);
+    }
+    Ok(pinned)
+}
+
+/// Whether the reparse point opened as `file` links to another path, as
+/// junctions and symbolic links do.
+///
+/// Volume mount points, whose target is a volume root `\??\Volume{GUID}\`,
+/// and reparse points that are no name surrogates, such as cloud files
+/// placeholders, are part of the path they appear in.
+fn redirects(file: &File) -> io::Result<bool> {
+    let buf = get_reparse_data_point(file.as_raw_handle())?;
+    if !crate::tags::is_surrogate(parse::reparse_tag(&buf)?) {
+        return Ok(false);
+    }
+    let mount_point = match parse::parse_reparse_buffer(&buf) {
+        Ok(mount_point) => mount_point,
+        Err(_) => return Ok(true),
+    };
+    let name: Vec<u16> = mount_point.substitute_name.units().collect();
+    Ok(match name.strip_prefix(&utf16s::<11>(br"\??\Volume{")[..]) {
+        Some(rest) => {
+            !rest.ends_with(&utf16s::<2>(br"}\")[..]) || rest.iter().filter(|&&u| u == u16::from(b'\\')).count() != 1
+        }
+        None => true,
+    })
+}
+
+/// Queries the file information of `path` itself, not following reparse points.
+///
+/// Only attribute access is requested, and other handles are not locked out.
+pub fn file_information(path: &Path) -> io::Result<c::BY_HANDLE_FILE_INFORMATION> {
+    let file = OpenOptions::new()
+        .access_mode(c::FILE_READ_ATTRIBUTES)
+        .share_mode(c::FILE_SHARE_READ | c::FILE_SHARE_WRITE | c::FILE_SHARE_DELETE)
+        .custom_flags(REPARSE_POINT_FLAGS)
+        .open(path)?;
+    let mut info: c::BY_HANDLE_FILE_INFORMATION = unsafe { zeroed() };
+    if unsafe { c::GetFileInformationByHandle(file.as_raw_handle(), &mut info) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(info)
+}
+
+/// An entry of a directory listing.
+pub struct FindEntry {
+    pub name: OsString,
+    pub attributes: u32,
+    /// The tag of the reparse point, if the entry is one.
+    pub reparse_tag: Option<u32>,
+}
+
+/// Lists the entries of `dir`, besides `.` and `..`, along with the tags of
+/// those that are reparse points.
+///
+/// The tags come with the listing itself, so no entry is opened.
+pub fn find_entries(dir: &Path) -> io::Result<Vec<FindEntry>> {
+    let pattern = os_str_to_utf16(dir.join("*").as_os_str());
+    let mut data: c::WIN32_FIND_DATAW = unsafe { zeroed() };
+    let handle = find_first(&pattern, &mut data)?;
+    let handle = scopeguard::guard(handle, |h| unsafe {
+        c::FindClose(h);
+    });
+    let mut entries = Vec::new();
+    loop {
+        let entry = FindEntry::from_data(&data);
+        if entry.name != "." && entry.name != ".." {
+            entries.push(entry);
+        }
+        if unsafe { c::FindNextFileW(*handle, &mut data) } == 0 {
+            let err = io::Error::last_os_error();
+            if err.raw_os_error() == Some(c::ERROR_NO_MORE_FILES as i32) {
+                return Ok(entries);
+            }
+            return Err(err);
+        }
+    }
+}
+
+/// Looks `path` itself up in its parent's listing, which needs no handle to
+/// it, so works where opening it is denied.
+pub fn find_entry(path: &Path) -> io::Result<FindEntry> {
+    let mut data: c::WIN32_FIND_DATAW = unsafe { zeroed() };
+    let handle = find_first(&os_str_to_utf16(path.as_os_str()), &mut data)?;
+    unsafe { c::FindClose(handle) };
+    Ok(FindEntry::from_data(&data))
+}
+
+impl FindEntry {
+    fn from_data(data: &c::WIN32_FIND_DATAW) -> Self {
+        let len = data
+            .cFileName
+            .iter()
+            .position(|&unit| unit == 0)
+            .unwrap_or(data.cFileName.len());
+        let reparse = data.dwFileAttributes & c::FILE_ATTRIBUTE_REPARSE_POINT != 0;
+        FindEntry {
+            name: OsString::from_wide(&data.cFileName[..len]),
+            attributes: data.dwFileAttributes,
+            // `dwReserved0` holds the tag of reparse points.
+            reparse_tag: reparse.then_some(data.dwReserved0),
+        }
     }
 }
 
+/// Starts a listing of the entries matching the null-terminated `pattern`.
+fn find_first(pattern: &[u16], data: &mut c::WIN32_FIND_DATAW) -> io::Result<c::HANDLE> {
+    let handle = unsafe {
+        c::FindFirstFileExW(
+            pattern.as_ptr(),
+            c::FindExInfoBasic,
+            (data as *mut c::WIN32_FIND_DATAW).cast(),
+            c::FindExSearchNameMatch,
+            null(),
+            c::FIND_FIRST_EX_LARGE_FETCH,
+        )
+    };
+    if handle == c::INVALID_HANDLE_VALUE {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(handle)
+}
+
 fn set_privilege(write: bool) -> io::Result<()> {
     const ERROR_NOT_ALL_ASSIGNED: u32 = 1300;
     const TOKEN_PRIVILEGES_SIZE: u32 = size_of::<c::TOKEN_PRIVILEGES>() as _;
+    #[cfg(test)]
+    if SKIP_SET_PRIVILEGE.with(|skip| skip.get()) {
+        return Err(io::Error::from_raw_os_error(ERROR_NOT_ALL_ASSIGNED as i32));
+    }
     unsafe {
         let mut handle: c::HANDLE = c::INVALID_HANDLE_VALUE;
         if c::OpenProcessToken(c::GetCurrentProcess(), c::TOKEN_ADJUST_PRIVILEGES, &mut handle) == 0 {
@@ -71,17 +471,134 @@ fn set_privilege(write: bool) -> io::Result<()> {
     Ok(())
 }
 
-pub fn get_reparse_data_point(handle: c::HANDLE, rdb: *mut c::REPARSE_DATA_BUFFER) -> io::Result<()> {
-    // Call DeviceIoControl to get the reparse point data
+/// Size of the first buffer [`get_reparse_data_point`] reads into, which fits
+/// all but the longest junction targets.
+const INITIAL_REPARSE_DATA_SIZE: usize = 1024;
+
+/// Reads the reparse data of `handle`.
+pub fn get_reparse_data_point(handle: c::HANDLE) -> io::Result<Vec<u8>> {
+    read_growing(|buf| {
+        let mut bytes_returned: u32 = 0;
+        // Call DeviceIoControl to get the reparse point data
+        if unsafe {
+            c::DeviceIoControl(
+                handle,
+                c::FSCTL_GET_REPARSE_POINT,
+                null_mut(),
+                0,
+                buf.as_mut_ptr().cast(),
+                buf.len() as u32,
+                &mut bytes_returned,
+                null_mut(),
+            )
+        } == 0
+        {
+            return Err((io::Error::last_os_error(), bytes_returned as usize));
+        }
+        Ok(bytes_returned as usize)
+    })
+}
+
+/// Calls `ioctl` with larger and larger buffers, up to
+/// `MAXIMUM_REPARSE_DATA_BUFFER_SIZE`, until the reparse data fits.
+///
+/// `ioctl` returns how many bytes it wrote, along with the error on failure.
+/// Errors with `InvalidData` if the data doesn't fit the largest buffer.
+pub fn read_growing<F>(mut ioctl: F) -> io::Result<Vec<u8>>
+where
+    F: FnMut(&mut [u8]) -> Result<usize, (io::Error, usize)>,
+{
+    let mut buf = vec![0u8; INITIAL_REPARSE_DATA_SIZE];
+    loop {
+        let (err, written) = match ioctl(&mut buf) {
+            Ok(written) => {
+                buf.truncate(parse::buffer_len(&buf[..written])?);
+                return Ok(buf);
+            }
+            Err(failed) => failed,
+        };
+        let too_small = [c::ERROR_MORE_DATA, c::ERROR_INSUFFICIENT_BUFFER]
+            .iter()
+            .any(|&code| err.raw_os_error() == Some(code as i32));
+        if !too_small {
+            return Err(err);
+        }
+        // When the data didn't fit, the header is usually still filled in and
+        // reports how much room is needed.
+        let needed = match parse::buffer_len(&buf[..written]) {
+            Err(parse::Error::Truncated { required, .. }) if written >= parse::HEADER_SIZE => required,
+            Err(e @ parse::Error::TooLarge { .. }) => return Err(e.into()),
+            _ => buf.len() * 2,
+        };
+        if buf.len() >= parse::MAXIMUM_BUFFER_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "reparse data doesn't fit the largest reparse data buffer",
+            ));
+        }
+        buf = vec![0u8; needed.clamp(buf.len() + 1, parse::MAXIMUM_BUFFER_SIZE)];
+    }
+}
+
+/// Sets the reparse point of `handle` to the first `len` bytes of `rdb`.
+///
+/// Uses `FSCTL_SET_REPARSE_POINT_EX` where supported, which atomically checks
+/// that the file has no reparse point yet, or one with the same tag that is
+/// then replaced. Older systems fall back to `FSCTL_SET_REPARSE_POINT`.
+pub fn set_reparse_point(handle: c::HANDLE, rdb: *mut c::REPARSE_DATA_BUFFER, len: u32) -> io::Result<()> {
+    #[cfg(test)]
+    let skip = SKIP_SET_REPARSE_POINT_EX.with(|skip| skip.get());
+    #[cfg(not(test))]
+    let skip = false;
+    if !skip && SET_REPARSE_POINT_EX.load(Ordering::Relaxed) {
+        match set_reparse_point_ex(handle, rdb, len) {
+            Err(e)
+                if [c::ERROR_INVALID_FUNCTION, c::ERROR_NOT_SUPPORTED]
+                    .iter()
+                    .any(|&code| e.raw_os_error() == Some(code as i32)) =>
+            {
+                SET_REPARSE_POINT_EX.store(false, Ordering::Relaxed);
+            }
+            other => return other,
+        }
+    }
+    set_reparse_point_control(handle, c::FSCTL_SET_REPARSE_POINT, rdb.cast(), len)
+}
+
+fn set_reparse_point_ex(handle: c::HANDLE, rdb: *mut c::REPARSE_DATA_BUFFER, len: u32) -> io::Result<()> {
+    // SAFETY: the caller initialized the first `len` bytes.
+    let data = unsafe { slice::from_raw_parts(rdb.cast::<u8>(), len as usize) };
+    // The GUID and Reserved fields stay zeroed for Microsoft tags.
+    let mut buf = vec![0u8; c::REPARSE_DATA_BUFFER_EX_HEADER_SIZE + data.len()];
+    buf[..4].copy_from_slice(&c::REPARSE_DATA_EX_FLAG_GIVEN_TAG_OR_NONE.to_le_bytes());
+    // ExistingReparseTag, the tag of the new reparse data.
+    buf[4..8].copy_from_slice(&data[..4]);
+    buf[c::REPARSE_DATA_BUFFER_EX_HEADER_SIZE..].copy_from_slice(data);
+    set_reparse_point_control(
+        handle,
+        c::FSCTL_SET_REPARSE_POINT_EX,
+        buf.as_mut_ptr().cast(),
+        buf.len() as u32,
+    )
+}
+
+fn set_reparse_point_control(handle: c::HANDLE, control: u32, buf: *mut c_void, len: u32) -> io::Result<()> {
+    fs_control(handle, control, buf, len)
+}
+
+/// Sends the file system control `code` with the input `buf` of `len` bytes,
+/// and no output, to `handle`.
+#[cfg(any(test, not(feature = "nt-api")))]
+pub fn fs_control_win32(handle: c::HANDLE, control: u32, buf: *mut c_void, len: u32) -> io::Result<()> {
     let mut bytes_returned: u32 = 0;
     if unsafe {
         c::DeviceIoControl(
             handle,
-            c::FSCTL_GET_REPARSE_POINT,
+            control,
+            buf,
+            len,
             null_mut(),
             0,
-            rdb.cast(),
-            c::MAXIMUM_REPARSE_DATA_BUFFER_SIZE,
             &mut bytes_returned,
             null_mut(),
         )
@@ -92,14 +609,33 @@ pub fn get_reparse_data_point(handle: c::HANDLE, rdb: *mut c::REPARSE_DATA_BUFFE
     Ok(())
 }
 
-pub fn set_reparse_point(handle: c::HANDLE, rdb: *mut c::REPARSE_DATA_BUFFER, len: u32) -> io::Result<()> {
+// See https://msdn.microsoft.com/en-us/library/windows/desktop/aa364560(v=vs.85).aspx
+//
+// The tag in the buffer must match that of the existing reparse point, so it
+// is read from it first. Only junctions and symbolic links are deleted: the
+// reparse points of deduplicated files or placeholders hold their data.
+pub fn delete_reparse_point(handle: c::HANDLE) -> io::Result<()> {
+    let existing = get_reparse_data_point(handle)?;
+    let tag = parse::reparse_tag(&existing)?;
+    if tag != c::IO_REPARSE_TAG_MOUNT_POINT && tag != parse::IO_REPARSE_TAG_SYMLINK {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!(
+                "not a junction or symbolic link, but a reparse point with tag {:#x}",
+                tag
+            ),
+        ));
+    }
+    let mut rgdb: c::REPARSE_GUID_DATA_BUFFER = unsafe { zeroed() };
+    rgdb.ReparseTag = tag;
     let mut bytes_returned: u32 = 0;
+
     if unsafe {
         c::DeviceIoControl(
             handle,
-            c::FSCTL_SET_REPARSE_POINT,
-            rdb.cast(),
-            len,
+            c::FSCTL_DELETE_REPARSE_POINT,
+            addr_of_mut!(rgdb).cast(),
+            u32::from(c::REPARSE_GUID_DATA_BUFFER_HEADER_SIZE),
             null_mut(),
             0,
             &mut bytes_returned,
@@ -112,26 +648,297 @@ pub fn set_reparse_point(handle: c::HANDLE, rdb: *mut c::REPARSE_DATA_BUFFER, le
     Ok(())
 }
 
-// See https://msdn.microsoft.com/en-us/library/windows/desktop/aa364560(v=vs.85).aspx
-pub fn delete_reparse_point(handle: c::HANDLE) -> io::Result<()> {
-    // TODO: Should we use REPARSE_DATA_BUFFER instead?
-    let mut rgdb: c::REPARSE_GUID_DATA_BUFFER = unsafe { zeroed() };
-    rgdb.ReparseTag = c::IO_REPARSE_TAG_MOUNT_POINT;
+/// Returns the first path, such as `C:\` or a mounted folder, that the volume
+/// named `volume` (`Volume{GUID}`) is mounted at, or `None` if there is none.
+///
+/// The path ends with a separator.
+pub fn volume_path_name(volume: &[u16]) -> io::Result<Option<Vec<u16>>> {
+    // The API wants the verbatim form, with a trailing separator.
+    let name: Vec<u16> = super::VERBATIM_PREFIX
+        .iter()
+        .chain(volume)
+        .chain(&[u16::from(b'\\'), 0])
+        .copied()
+        .collect();
+    // Room for a `MAX_PATH` long path, grown when the API asks for more.
+    let mut buf: Vec<u16> = vec![0; 261];
+    loop {
+        let mut len: u32 = 0;
+        let ok =
+            unsafe { c::GetVolumePathNamesForVolumeNameW(name.as_ptr(), buf.as_mut_ptr(), buf.len() as u32, &mut len) };
+        if ok != 0 {
+            break;
+        }
+        let err = io::Error::last_os_error();
+        if err.raw_os_error() != Some(c::ERROR_MORE_DATA as i32) || len as usize <= buf.len() {
+            return Err(err);
+        }
+        buf.resize(len as usize, 0);
+    }
+    // The buffer holds a list of null-terminated paths, ending with an empty one.
+    let first = buf.split(|&unit| unit == 0).next().unwrap_or(&[]);
+    Ok((!first.is_empty()).then(|| first.to_vec()))
+}
+
+/// Opens the directory `dir` for [`read_directory_changes`], without locking
+/// out other handles.
+#[cfg(feature = "watch")]
+pub fn open_dir_for_changes(dir: &Path) -> io::Result<File> {
+    OpenOptions::new()
+        .access_mode(c::FILE_LIST_DIRECTORY)
+        .share_mode(c::FILE_SHARE_READ | c::FILE_SHARE_WRITE | c::FILE_SHARE_DELETE)
+        .custom_flags(c::FILE_FLAG_BACKUP_SEMANTICS)
+        .open(dir)
+}
+
+/// Blocks until entries of the directory opened as `dir` are renamed, added,
+/// removed or modified, and returns their names, or `None` if more changed
+/// than `buf` could hold.
+///
+/// Fails with `ERROR_OPERATION_ABORTED` once [`cancel_io`] is called.
+#[cfg(feature = "watch")]
+pub fn read_directory_changes(dir: &File, buf: &mut [u32]) -> io::Result<Option<Vec<OsString>>> {
+    const FILTER: u32 =
+        c::FILE_NOTIFY_CHANGE_DIR_NAME | c::FILE_NOTIFY_CHANGE_ATTRIBUTES | c::FILE_NOTIFY_CHANGE_LAST_WRITE;
     let mut bytes_returned: u32 = 0;
+    if unsafe {
+        c::ReadDirectoryChangesW(
+            dir.as_raw_handle(),
+            buf.as_mut_ptr().cast(),
+            std::mem::size_of_val(buf) as u32,
+            c::FALSE,
+            FILTER,
+            &mut bytes_returned,
+            null_mut(),
+            None,
+        )
+    } == 0
+    {
+        return Err(io::Error::last_os_error());
+    }
+    if bytes_returned == 0 {
+        return Ok(None);
+    }
+    // SAFETY: the API wrote `bytes_returned` bytes of FILE_NOTIFY_INFORMATION entries.
+    let bytes = unsafe { slice::from_raw_parts(buf.as_ptr().cast::<u8>(), bytes_returned as usize) };
+    let field = |at: usize| u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]]) as usize;
+    let mut names = Vec::new();
+    let mut offset = 0;
+    loop {
+        // NextEntryOffset, Action and FileNameLength, followed by FileName.
+        let (next, name_len) = (field(offset), field(offset + 8));
+        let name: Vec<u16> = bytes[offset + 12..offset + 12 + name_len]
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .collect();
+        names.push(OsString::from_wide(&name));
+        if next == 0 {
+            return Ok(Some(names));
+        }
+        offset += next;
+    }
+}
 
+/// Cancels the I/O pending on `file` from any thread, such as a blocked
+/// [`read_directory_changes`].
+#[cfg(feature = "watch")]
+pub fn cancel_io(file: &File) -> io::Result<()> {
+    if unsafe { c::CancelIoEx(file.as_raw_handle(), null()) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Expands the 8.3 short names, such as `PROGRA~1`, in the existing `path`
+/// to their long forms, without a terminating null.
+pub fn get_long_path(path: &Path) -> io::Result<Vec<u16>> {
+    let path = os_str_to_utf16(path.as_os_str());
+    // The long form is usually about as long as the short one.
+    let mut buf: Vec<u16> = vec![0; path.len().max(261)];
+    loop {
+        let len = unsafe { c::GetLongPathNameW(path.as_ptr(), buf.as_mut_ptr(), buf.len() as u32) } as usize;
+        if len == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // Too small, and `len` is the size needed, null terminator included.
+        if len >= buf.len() {
+            buf.resize(len, 0);
+            continue;
+        }
+        buf.truncate(len);
+        return Ok(buf);
+    }
+}
+
+/// Returns the platform ID and the major and minor version of Windows,
+/// queried once per process.
+///
+/// Without a manifest declaring support for it, Windows 8.1 and later report
+/// themselves as Windows 8, which is recent enough for what this is used for.
+pub fn os_version() -> io::Result<(u32, u32, u32)> {
+    static VERSION: OnceLock<Option<(u32, u32, u32)>> = OnceLock::new();
+    let version = VERSION.get_or_init(|| {
+        let mut info: c::OSVERSIONINFOW = unsafe { zeroed() };
+        info.dwOSVersionInfoSize = size_of::<c::OSVERSIONINFOW>() as u32;
+        if unsafe { c::GetVersionExW(&mut info) } == 0 {
+            return None;
+        }
+        Some((info.dwPlatformId, info.dwMajorVersion, info.dwMinorVersion))
+    });
+    version.ok_or_else(|| io::Error::new(io::ErrorKind::Other, "failed to query the Windows version"))
+}
+
+/// Returns the root of the volume holding `path`, such as `C:\` or a mounted
+/// folder, with a trailing separator and without a terminating null.
+pub fn volume_root(path: &Path) -> io::Result<Vec<u16>> {
+    let path = os_str_to_utf16(path.as_os_str());
+    // The volume path is never longer than `path`.
+    let mut root: Vec<u16> = vec![0; path.len().max(261)];
+    if unsafe { c::GetVolumePathNameW(path.as_ptr(), root.as_mut_ptr(), root.len() as u32) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let len = root.iter().position(|&unit| unit == 0).unwrap_or(root.len());
+    root.truncate(len);
+    Ok(root)
+}
+
+/// Opens the volume whose root is `root`, such as `C:\` or
+/// `\\?\Volume{GUID}\`, for reading its MFT and change journal. Needs
+/// administrator rights.
+pub fn open_volume(root: &[u16]) -> io::Result<File> {
+    let device = match root {
+        // `C:\` is opened as `\\.\C:`.
+        [drive, colon, ..] if *colon == u16::from(b':') => {
+            let mut device = utf16s::<4>(br"\\.\").to_vec();
+            device.extend_from_slice(&[*drive, *colon]);
+            device
+        }
+        // `\\?\Volume{GUID}\` is opened without its trailing separator.
+        _ if root.starts_with(&utf16s::<11>(br"\\?\Volume{")) => root[..root.len() - 1].to_vec(),
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "volumes mounted at a folder can't be opened by their root",
+            ))
+        }
+    };
+    OpenOptions::new()
+        .access_mode(c::GENERIC_READ)
+        .share_mode(c::FILE_SHARE_READ | c::FILE_SHARE_WRITE)
+        .open(OsString::from_wide(&device))
+}
+
+/// Returns the next USN the change journal of `volume` will write, or fails
+/// if the volume has no active journal.
+pub fn query_usn_journal(volume: &File) -> io::Result<u64> {
+    // USN_JOURNAL_DATA_V0: UsnJournalID, FirstUsn, NextUsn, LowestValidUsn,
+    // MaxUsn, MaximumSize and AllocationDelta.
+    let mut data = [0u64; 7];
+    let mut bytes_returned: u32 = 0;
     if unsafe {
         c::DeviceIoControl(
-            handle,
-            c::FSCTL_DELETE_REPARSE_POINT,
-            addr_of_mut!(rgdb).cast(),
-            u32::from(c::REPARSE_GUID_DATA_BUFFER_HEADER_SIZE),
+            volume.as_raw_handle(),
+            c::FSCTL_QUERY_USN_JOURNAL,
             null_mut(),
             0,
+            data.as_mut_ptr().cast(),
+            size_of_val(&data) as u32,
+            &mut bytes_returned,
+            null_mut(),
+        )
+    } == 0
+    {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(data[2])
+}
+
+/// Reads the MFT records of `volume` from the file reference number `start`
+/// on, with USNs below `high_usn`, into `buf`. Returns how many bytes were
+/// read, or `None` once there are no more records.
+pub fn enum_usn_data(volume: &File, start: u64, high_usn: u64, buf: &mut [u8]) -> io::Result<Option<usize>> {
+    // MFT_ENUM_DATA_V0: StartFileReferenceNumber, LowUsn and HighUsn.
+    let input = [start, 0, high_usn];
+    let mut bytes_returned: u32 = 0;
+    if unsafe {
+        c::DeviceIoControl(
+            volume.as_raw_handle(),
+            c::FSCTL_ENUM_USN_DATA,
+            input.as_ptr().cast(),
+            size_of_val(&input) as u32,
+            buf.as_mut_ptr().cast(),
+            buf.len() as u32,
             &mut bytes_returned,
             null_mut(),
         )
     } == 0
     {
+        let err = io::Error::last_os_error();
+        if err.raw_os_error() == Some(c::ERROR_HANDLE_EOF as i32) {
+            return Ok(None);
+        }
+        return Err(err);
+    }
+    Ok(Some(bytes_returned as usize))
+}
+
+/// Returns the file system flags and the file system name, such as `NTFS` or
+/// `FAT32`, of the volume holding `path`.
+pub fn volume_information(path: &Path) -> io::Result<(u32, OsString)> {
+    let mut root = volume_root(path)?;
+    root.push(0);
+    // At most `MAX_PATH + 1` units is enough for the file system name.
+    let mut flags: u32 = 0;
+    let mut name: Vec<u16> = vec![0; 261];
+    if unsafe {
+        c::GetVolumeInformationW(
+            root.as_ptr(),
+            null_mut(),
+            0,
+            null_mut(),
+            null_mut(),
+            &mut flags,
+            name.as_mut_ptr(),
+            name.len() as u32,
+        )
+    } == 0
+    {
+        return Err(io::Error::last_os_error());
+    }
+    let len = name.iter().position(|&unit| unit == 0).unwrap_or(name.len());
+    Ok((flags, OsString::from_wide(&name[..len])))
+}
+
+pub fn set_attributes(path: &Path, attributes: u32) -> io::Result<()> {
+    let path = os_str_to_utf16(path.as_os_str());
+    if unsafe { c::SetFileAttributesW(path.as_ptr(), attributes) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Sets the creation and last write times of `file`, as `FILETIME`s, leaving
+/// its last access time as is.
+pub fn set_file_times(file: &File, created: u64, written: u64) -> io::Result<()> {
+    let filetime = |time: u64| c::FILETIME {
+        dwLowDateTime: time as u32,
+        dwHighDateTime: (time >> 32) as u32,
+    };
+    let (created, written) = (filetime(created), filetime(written));
+    if unsafe { c::SetFileTime(file.as_raw_handle(), &created, null(), &written) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Applies the parts of the self-relative security `descriptor` selected by
+/// `information` to `path`.
+pub fn set_security(path: &Path, information: u32, descriptor: &[u8]) -> io::Result<()> {
+    let path = os_str_to_utf16(path.as_os_str());
+    // Copy into a buffer aligned for the descriptor's 32-bit fields.
+    let mut aligned = vec![0u32; (descriptor.len() + 3) / 4];
+    unsafe { copy_nonoverlapping(descriptor.as_ptr(), aligned.as_mut_ptr().cast::<u8>(), descriptor.len()) };
+    if unsafe { c::SetFileSecurityW(path.as_ptr(), information, aligned.as_mut_ptr().cast()) } == 0 {
         return Err(io::Error::last_os_error());
     }
     Ok(())
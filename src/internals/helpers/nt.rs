@@ -0,0 +1,149 @@
+//! Opening reparse points and setting their data through ntdll, for when
+//! `CreateFileW` and `DeviceIoControl` are hooked or blocked.
+
+use std::ffi::c_void;
+use std::fs::File;
+use std::io;
+use std::mem::{size_of, zeroed};
+use std::os::windows::io::FromRawHandle;
+use std::path::Path;
+use std::ptr::{null, null_mut};
+
+use super::{get_full_path, utf16s};
+use crate::internals::c;
+
+const NT_PREFIX: [u16; 4] = utf16s(br"\??\");
+const VERBATIM_PREFIX: [u16; 4] = utf16s(br"\\?\");
+const DEVICE_PREFIX: [u16; 4] = utf16s(br"\\.\");
+const UNC_PREFIX: [u16; 4] = utf16s(br"UNC\");
+
+/// Converts `path` to the NT path `CreateFileW` would open, in the object
+/// manager's `\??\` directory.
+pub fn nt_path(path: &Path) -> io::Result<Vec<u16>> {
+    let full = get_full_path(path)?;
+    let rest = if let Some(rest) = full.strip_prefix(&VERBATIM_PREFIX[..]) {
+        rest.to_vec()
+    } else if let Some(rest) = full.strip_prefix(&DEVICE_PREFIX[..]) {
+        rest.to_vec()
+    } else if let Some(rest) = full.strip_prefix(&utf16s(br"\\")[..]) {
+        UNC_PREFIX.iter().chain(rest).copied().collect()
+    } else {
+        full
+    };
+    Ok(NT_PREFIX.iter().chain(&rest).copied().collect())
+}
+
+fn check(status: i32) -> io::Result<()> {
+    // Warnings and errors have the high bit set.
+    if status < 0 {
+        let code = unsafe { c::RtlNtStatusToDosError(status) };
+        return Err(io::Error::from_raw_os_error(code as i32));
+    }
+    Ok(())
+}
+
+/// Opens the reparse point at `path` itself with `access` and without sharing,
+/// like `CreateFileW` with `FILE_FLAG_OPEN_REPARSE_POINT` and
+/// `FILE_FLAG_BACKUP_SEMANTICS` and `FILE_FLAG_OPEN_NO_RECALL` does.
+pub fn open_exclusive(path: &Path, access: u32) -> io::Result<File> {
+    let mut name = nt_path(path)?;
+    create_file(
+        null_mut(),
+        &mut name,
+        access,
+        c::FILE_OPEN,
+        c::FILE_OPEN_REPARSE_POINT | c::FILE_OPEN_FOR_BACKUP_INTENT | c::FILE_OPEN_NO_RECALL,
+    )
+}
+
+/// Creates the directory `leaf` in the directory opened as `parent`, without
+/// resolving any path, and opens it for reading and writing, without sharing.
+pub fn create_dir_at(parent: c::HANDLE, leaf: &[u16]) -> io::Result<File> {
+    let mut name = leaf.to_vec();
+    create_file(
+        parent,
+        &mut name,
+        c::GENERIC_READ | c::GENERIC_WRITE,
+        c::FILE_CREATE,
+        c::FILE_DIRECTORY_FILE | c::FILE_OPEN_REPARSE_POINT | c::FILE_OPEN_FOR_BACKUP_INTENT,
+    )
+}
+
+/// Calls `NtCreateFile` for `name`, relative to `root` unless it is null,
+/// with a synchronous handle and without sharing.
+fn create_file(root: c::HANDLE, name: &mut [u16], access: u32, disposition: u32, options: u32) -> io::Result<File> {
+    let len = u16::try_from(name.len() * 2)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "path is too long for an NT path"))?;
+    let name = c::UNICODE_STRING {
+        Length: len,
+        MaximumLength: len,
+        Buffer: name.as_mut_ptr(),
+    };
+    let attributes = c::OBJECT_ATTRIBUTES {
+        Length: size_of::<c::OBJECT_ATTRIBUTES>() as u32,
+        RootDirectory: root,
+        ObjectName: &name,
+        Attributes: c::OBJ_CASE_INSENSITIVE,
+        SecurityDescriptor: null(),
+        SecurityQualityOfService: null(),
+    };
+    let mut handle: c::HANDLE = c::INVALID_HANDLE_VALUE;
+    let mut io_status: c::IO_STATUS_BLOCK = unsafe { zeroed() };
+    check(unsafe {
+        c::NtCreateFile(
+            &mut handle,
+            // Synchronous handles need `SYNCHRONIZE`, which `CreateFileW` adds.
+            access | c::SYNCHRONIZE,
+            &attributes,
+            &mut io_status,
+            null(),
+            0,
+            0,
+            disposition,
+            options | c::FILE_SYNCHRONOUS_IO_NONALERT,
+            null(),
+            0,
+        )
+    })?;
+    Ok(unsafe { File::from_raw_handle(handle) })
+}
+
+/// Sends the file system control `code` with the input `buf` of `len` bytes,
+/// and no output, to `handle`.
+pub fn fs_control(handle: c::HANDLE, code: u32, buf: *mut c_void, len: u32) -> io::Result<()> {
+    let mut io_status: c::IO_STATUS_BLOCK = unsafe { zeroed() };
+    check(unsafe {
+        c::NtFsControlFile(
+            handle,
+            null_mut(),
+            None,
+            null(),
+            &mut io_status,
+            code,
+            buf,
+            len,
+            null_mut(),
+            0,
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::nt_path;
+
+    #[test]
+    fn nt_path_prefixes() {
+        let wide = |path: &str| path.encode_utf16().collect::<Vec<u16>>();
+        for (path, expected) in [
+            (r"C:\dir", r"\??\C:\dir"),
+            (r"\\?\C:\dir", r"\??\C:\dir"),
+            (r"\\?\UNC\server\share\dir", r"\??\UNC\server\share\dir"),
+            (r"\\server\share\dir", r"\??\UNC\server\share\dir"),
+        ] {
+            assert_eq!(nt_path(Path::new(path)).unwrap(), wide(expected), "{}", path);
+        }
+    }
+}
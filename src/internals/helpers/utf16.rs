@@ -1,3 +1,6 @@
+use std::ffi::{OsStr, OsString};
+use std::os::windows::ffi::{OsStrExt, OsStringExt};
+
 /// Convert ASCII bytes to UTF-16 sequences.
 pub const fn utf16s<const N: usize>(src: &'static [u8; N]) -> [u16; N] {
     let mut dst = [0u16; N];
@@ -8,3 +11,27 @@ pub const fn utf16s<const N: usize>(src: &'static [u8; N]) -> [u16; N] {
     }
     dst
 }
+
+/// Upcases `name` one UTF-16 unit at a time, as NTFS does by default when
+/// comparing names, so that names differing only in case map to the same key.
+pub fn upcase(name: &OsStr) -> OsString {
+    OsString::from_wide(&name.encode_wide().map(upcase_unit).collect::<Vec<u16>>())
+}
+
+/// Whether `a` and `b` are the same name ignoring case, as NTFS compares them
+/// by default.
+pub fn eq_ignore_case(a: &OsStr, b: &OsStr) -> bool {
+    a.encode_wide().map(upcase_unit).eq(b.encode_wide().map(upcase_unit))
+}
+
+// Units whose uppercase isn't a single unit, such as `ß`, are kept as is.
+fn upcase_unit(unit: u16) -> u16 {
+    let mut upper = match char::from_u32(u32::from(unit)) {
+        Some(c) => c.to_uppercase(),
+        None => return unit,
+    };
+    match (upper.next(), upper.next()) {
+        (Some(c), None) => u16::try_from(u32::from(c)).unwrap_or(unit),
+        _ => unit,
+    }
+}
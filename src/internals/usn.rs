@@ -0,0 +1,145 @@
+//! Reading the MFT of a volume through `FSCTL_ENUM_USN_DATA`.
+
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::io;
+use std::os::windows::ffi::OsStringExt;
+use std::path::PathBuf;
+
+/// An entry of the MFT, as described by a `USN_RECORD_V2`.
+pub struct UsnRecord {
+    /// The file reference number of the entry.
+    pub id: u64,
+    /// The file reference number of the directory holding the entry.
+    pub parent: u64,
+    pub attributes: u32,
+    pub name: OsString,
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn read_u16(buf: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(buf.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn read_u32(buf: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(buf.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn read_u64(buf: &[u8], at: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(buf.get(at..at + 8)?.try_into().ok()?))
+}
+
+/// Parses the output of one `FSCTL_ENUM_USN_DATA` call: the file reference
+/// number to continue from, followed by `USN_RECORD_V2` records.
+pub fn parse_records(buf: &[u8]) -> io::Result<(u64, Vec<UsnRecord>)> {
+    let next = read_u64(buf, 0).ok_or_else(|| invalid("USN data is too short"))?;
+    let mut records = Vec::new();
+    let mut rest = &buf[8..];
+    while !rest.is_empty() {
+        let len = read_u32(rest, 0).ok_or_else(|| invalid("USN record is truncated"))? as usize;
+        // A record is at least as long as its fixed part, which ends at 60.
+        if len < 60 || len > rest.len() {
+            return Err(invalid("USN record length is out of bounds"));
+        }
+        let record = &rest[..len];
+        if read_u16(record, 4) != Some(2) {
+            return Err(invalid("USN record is not version 2"));
+        }
+        let name_len = read_u16(record, 56).unwrap() as usize;
+        let name_offset = read_u16(record, 58).unwrap() as usize;
+        let name = record
+            .get(name_offset..name_offset + name_len)
+            .ok_or_else(|| invalid("USN record name is out of bounds"))?;
+        let name: Vec<u16> = name
+            .chunks_exact(2)
+            .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+            .collect();
+        records.push(UsnRecord {
+            id: read_u64(record, 8).unwrap(),
+            parent: read_u64(record, 16).unwrap(),
+            attributes: read_u32(record, 52).unwrap(),
+            name: OsString::from_wide(&name),
+        });
+        rest = &rest[len..];
+    }
+    Ok((next, records))
+}
+
+/// Builds the path of `id` relative to the directory `root` by following the
+/// parents in `dirs`. Returns `None` if `id` isn't under `root`.
+pub fn path_of(id: u64, root: u64, dirs: &HashMap<u64, (u64, OsString)>) -> Option<PathBuf> {
+    let mut names = Vec::new();
+    let mut current = id;
+    while current != root {
+        let (parent, name) = dirs.get(&current)?;
+        names.push(name);
+        // A well-formed MFT has no cycles, but don't loop on one that does.
+        if names.len() > dirs.len() {
+            return None;
+        }
+        current = *parent;
+    }
+    Some(names.into_iter().rev().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::ffi::OsString;
+    use std::path::PathBuf;
+
+    use super::{parse_records, path_of};
+
+    fn record(id: u64, parent: u64, attributes: u32, name: &str) -> Vec<u8> {
+        let name: Vec<u8> = name.encode_utf16().flat_map(u16::to_le_bytes).collect();
+        // Records are padded to a multiple of 8 bytes.
+        let len = (60 + name.len() + 7) & !7;
+        let mut buf = vec![0u8; len];
+        buf[0..4].copy_from_slice(&(len as u32).to_le_bytes());
+        buf[4..6].copy_from_slice(&2u16.to_le_bytes());
+        buf[8..16].copy_from_slice(&id.to_le_bytes());
+        buf[16..24].copy_from_slice(&parent.to_le_bytes());
+        buf[52..56].copy_from_slice(&attributes.to_le_bytes());
+        buf[56..58].copy_from_slice(&(name.len() as u16).to_le_bytes());
+        buf[58..60].copy_from_slice(&60u16.to_le_bytes());
+        buf[60..60 + name.len()].copy_from_slice(&name);
+        buf
+    }
+
+    #[test]
+    fn parse_records_reads_v2() {
+        let mut buf = 42u64.to_le_bytes().to_vec();
+        buf.extend(record(7, 5, 0x10, "dir"));
+        buf.extend(record(9, 7, 0x410, "junction"));
+        let (next, records) = parse_records(&buf).unwrap();
+        assert_eq!(next, 42);
+        assert_eq!(records.len(), 2);
+        assert_eq!((records[1].id, records[1].parent, records[1].attributes), (9, 7, 0x410));
+        assert_eq!(records[1].name, "junction");
+    }
+
+    #[test]
+    fn parse_records_rejects_truncated() {
+        let mut buf = 42u64.to_le_bytes().to_vec();
+        let mut one = record(7, 5, 0x10, "dir");
+        one.truncate(40);
+        buf.extend(one);
+        assert!(parse_records(&buf).is_err());
+        assert!(parse_records(&[0; 4]).is_err());
+    }
+
+    #[test]
+    fn path_of_follows_parents() {
+        let mut dirs = HashMap::new();
+        dirs.insert(7, (5, OsString::from("a")));
+        dirs.insert(9, (7, OsString::from("b")));
+        dirs.insert(11, (3, OsString::from("elsewhere")));
+        assert_eq!(path_of(9, 5, &dirs), Some(PathBuf::from(r"a\b")));
+        assert_eq!(path_of(11, 5, &dirs), None);
+        dirs.insert(13, (13, OsString::from("loop")));
+        assert_eq!(path_of(13, 5, &dirs), None);
+    }
+}
@@ -9,26 +9,113 @@ serves as a symbolic link to another directory on the computer. For example,
 if the directory `D:\SYMLINK` specified `C:\WINNT\SYSTEM32` as its target, then
 an application accessing `D:\SYMLINK\DRIVERS` would in reality be accessing
 `C:\WINNT\SYSTEM32\DRIVERS`.
+
+# Thread safety
+
+The free functions are stateless: apart from the filesystem itself, all state
+lives on the caller's stack and in handles that are closed before returning.
+Calling them concurrently from several threads is safe, including on paths in
+the same directory. The public types are all `Send` and `Sync`.
+
+Relative paths are resolved against the process-wide current directory.
+[`create`] resolves both its paths once up front, but changing the current
+directory while other threads pass relative paths is still racy; prefer
+absolute paths in multi-threaded programs.
+
+When opening a directory is denied, the process token is adjusted to enable
+the privilege that opening it requires, which affects the whole process.
+
+# Platforms
+
+Everything touching the filesystem is only available on Windows. The
+[`parse`] and [`tags`] modules don't depend on it and are available
+everywhere, for decoding reparse data captured on another machine.
 */
 #![doc(html_root_url = "https://docs.rs/junction/~1")]
-#![cfg(windows)]
 #![deny(rust_2021_compatibility)]
 
+#[cfg(windows)]
+mod cache;
+#[cfg(windows)]
+mod convert;
+#[cfg(windows)]
+mod diagnose;
+#[cfg(windows)]
+mod ext;
+#[cfg(windows)]
 mod internals;
+#[cfg(windows)]
+mod options;
+pub mod parse;
+#[cfg(windows)]
+mod security;
+pub mod tags;
+#[cfg(all(windows, feature = "testutil"))]
+mod testutil;
+#[cfg(all(windows, feature = "watch"))]
+mod watch;
 
-#[cfg(test)]
+#[cfg(all(test, windows))]
 mod tests;
 
+#[cfg(windows)]
+use std::collections::HashMap;
+#[cfg(windows)]
+use std::ffi::OsStr;
+#[cfg(windows)]
+use std::fs::File;
+#[cfg(windows)]
 use std::io;
+#[cfg(all(windows, feature = "nt-api"))]
+use std::os::windows::io::BorrowedHandle;
+#[cfg(windows)]
 use std::path::{Path, PathBuf};
+#[cfg(windows)]
+use std::time::Duration;
+
+#[cfg(windows)]
+pub use cache::TargetCache;
+#[cfg(windows)]
+pub use diagnose::{Check, Diagnostic, Severity};
+#[cfg(windows)]
+pub use ext::JunctionExt;
+#[cfg(windows)]
+pub use options::{
+    BufferStrategy, ConvertOptions, CreateOptions, CreateReport, DeleteOptions, JunctionSpec, ReadOptions, ReparseData,
+    ReparsePoint, Status,
+};
+#[cfg(windows)]
+pub use security::{IntegrityLevel, ParseSidError, Sid};
+pub use tags::ReparseTag;
+#[cfg(all(windows, feature = "watch"))]
+pub use watch::WatchHandle;
 
 /// Creates a junction point from the specified directory to the specified target directory.
 ///
 /// N.B. Only works on NTFS.
 ///
+/// The target may be an absolute path such as `C:\dir`, a path relative to
+/// the current directory, a UNC path such as `\\server\share\dir`, or any of
+/// these in verbatim (`\\?\`) form. Device paths such as `\\.\C:` name
+/// devices rather than directories, so they are rejected.
+///
+/// The target is stored as an absolute path with any `.` and `..`
+/// components collapsed, including for verbatim paths, which Windows
+/// otherwise leaves untouched.
+///
 /// # Error
 ///
-/// This function may error if the `junction` path already exists.
+/// This function may error if the `junction` path already exists, see
+/// [`CreateOptions::overwrite`] to replace it, and errors with `InvalidInput`
+/// for a device path `target` or when `junction` is the root of a volume,
+/// such as `C:\`. It also errors with `InvalidInput` if the target is too
+/// long once resolved, which [`fits`] checks ahead of time.
+/// Passing the same path for both, even with different casing, errors with
+/// `InvalidInput` before anything is created.
+///
+/// If the parent directory's inheritable ACL lets the junction directory be
+/// created but not written to, this function removes the directory again and
+/// errors with `PermissionDenied`, saying so.
 ///
 /// # Example
 ///
@@ -45,12 +132,352 @@ use std::path::{Path, PathBuf};
 ///     create(&target, &junction)
 /// }
 /// ```
+#[cfg(windows)]
 pub fn create<P, Q>(target: P, junction: Q) -> io::Result<()>
 where
     P: AsRef<Path>,
     Q: AsRef<Path>,
 {
-    internals::create(target.as_ref(), junction.as_ref())
+    CreateOptions::new().create(target, junction)
+}
+
+/// Checks whether a junction to `target` is short enough to be created.
+///
+/// The target is resolved the way [`create`] resolves it, and it is the
+/// resolved length that is checked, as that is what gets stored: a relative
+/// target grows by the current directory, and `.` and `..` components are
+/// collapsed. Checking the length of `target` as given is not enough, so any
+/// pre-check should use this. Both this and [`create`] then agree on which
+/// targets are too long. The target need not exist.
+///
+/// # Error
+///
+/// This function errors like [`create`] for a target that can't be resolved,
+/// such as a device path.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// # use junction::fits;
+/// fn main() -> io::Result<()> {
+///     assert!(fits(r"C:\dir")?);
+///     assert!(!fits(format!(r"C:\{}", "a\\".repeat(8192)))?);
+///     Ok(())
+/// }
+/// ```
+#[cfg(windows)]
+pub fn fits<P: AsRef<Path>>(target: P) -> io::Result<bool> {
+    internals::fits(target.as_ref())
+}
+
+/// Creates a junction point like [`create`], to `relative_target` on the root
+/// of `drive`.
+///
+/// The target is built as `<drive>:\<relative_target>`, which avoids
+/// malformed targets such as `C:relative`, relative to the current directory
+/// of the drive, that concatenating strings can produce.
+///
+/// # Error
+///
+/// This function errors with `InvalidInput` if `drive` is not an ASCII letter,
+/// or if `relative_target` has a root or a prefix of its own, such as `\dir`
+/// or `C:dir`. Otherwise it errors like [`create`].
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// # use junction::{create_on_drive, get_target};
+/// fn main() -> io::Result<()> {
+///     let tmpdir = tempfile::tempdir()?;
+///     let junction = tmpdir.path().join("junction");
+///     create_on_drive('C', r"Windows\System32", &junction)?;
+///     assert_eq!(get_target(&junction)?.to_str(), Some(r"C:\Windows\System32"));
+///     Ok(())
+/// }
+/// ```
+#[cfg(windows)]
+pub fn create_on_drive<P, Q>(drive: char, relative_target: P, junction: Q) -> io::Result<()>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let target = internals::drive_target(drive, relative_target.as_ref())?;
+    create(target, junction)
+}
+
+/// Creates a junction point like [`create`], and reports the sizes of the
+/// reparse data it wrote.
+///
+/// This helps debugging targets close to the size limit of reparse points:
+/// both names, plus their null terminators, have to fit in
+/// [`CreateReport::MAX_PATH_BUFFER`] bytes.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// # use std::fs;
+/// # use junction::{create_report, CreateReport};
+/// fn main() -> io::Result<()> {
+///     let tmpdir = tempfile::tempdir()?;
+///     let target = tmpdir.path().join("target");
+///     let junction = tmpdir.path().join("junction");
+///     # fs::create_dir_all(&target)?;
+///     let report = create_report(&target, &junction)?;
+///     let used = report.substitute_len_in_bytes + report.print_name_len_in_bytes + 4;
+///     println!("used {} of {} bytes", used, CreateReport::MAX_PATH_BUFFER);
+///     Ok(())
+/// }
+/// ```
+#[cfg(windows)]
+pub fn create_report<P, Q>(target: P, junction: Q) -> io::Result<CreateReport>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    CreateOptions::new().create_report(target, junction)
+}
+
+/// Creates a junction point like [`create`], but **with an empty PrintName**.
+///
+/// **Do not use this outside of tests.** Junctions without a PrintName work
+/// on the host, but break in Windows Container layer snapshots, where they
+/// are restored pointing nowhere. Some legacy tools still write them, and
+/// this function exists only to reproduce such junctions, e.g. to test that
+/// [`diagnose`] reports them.
+///
+/// The SubstituteName is written as [`create`] writes it.
+///
+/// Only available with the `testutil` feature.
+#[cfg(all(windows, feature = "testutil"))]
+pub fn create_with_empty_print_name<P, Q>(target: P, junction: Q) -> io::Result<()>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let options = CreateOptions {
+        empty_print_name: true,
+        ..CreateOptions::new()
+    };
+    internals::create(target.as_ref(), junction.as_ref(), &options).map(drop)
+}
+
+/// Creates a junction point like [`create`], but **without the `\??\` prefix**
+/// on its SubstituteName, which then holds the target as is.
+///
+/// **Do not use this outside of tests.** Windows itself expects the NT prefix,
+/// so such junctions may not resolve on NTFS. Some tools, such as Wine or the
+/// layer tooling of some virtual machines, write them, and this function
+/// exists only to reproduce such junctions, e.g. to test how they are read.
+///
+/// The PrintName is written as [`create`] writes it.
+///
+/// Only available with the `testutil` feature.
+#[cfg(all(windows, feature = "testutil"))]
+pub fn create_no_nt_prefix<P, Q>(target: P, junction: Q) -> io::Result<()>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let options = CreateOptions {
+        no_nt_prefix: true,
+        ..CreateOptions::new()
+    };
+    internals::create(target.as_ref(), junction.as_ref(), &options).map(drop)
+}
+
+/// Creates a junction point like [`create`], but storing an absolute `target`
+/// exactly as given, without the path normalization [`create`] applies.
+///
+/// [`create`] runs the target through `GetFullPathNameW`, which, among
+/// others, collapses `.` and `..` components, turns forward slashes into
+/// backslashes and strips trailing spaces and dots. Use this function when
+/// the SubstituteName has to hold a precise string, such as one reproducing
+/// a junction written by another tool; prefer [`create`] otherwise, as
+/// Windows may not resolve targets it would have normalized. Only a leading
+/// verbatim prefix (`\\?\`) is replaced by the NT prefix (`\??\`). Relative
+/// targets are resolved like [`create`] does.
+///
+/// # Error
+///
+/// This function fails like [`create`], including when the target is too
+/// long for the reparse data buffer, or is a device path (`\\.\`).
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// # use std::fs;
+/// # use junction::{create_osstr, get_target};
+/// fn main() -> io::Result<()> {
+///     let tmpdir = tempfile::tempdir()?;
+///     let target = tmpdir.path().join("other").join("..").join("target");
+///     let junction = tmpdir.path().join("junction");
+///     # fs::create_dir_all(tmpdir.path().join("target"))?;
+///     create_osstr(&target, &junction)?;
+///     assert_eq!(get_target(&junction)?, target);
+///     Ok(())
+/// }
+/// ```
+#[cfg(windows)]
+pub fn create_osstr<P, Q>(target: P, junction: Q) -> io::Result<()>
+where
+    P: AsRef<OsStr>,
+    Q: AsRef<Path>,
+{
+    let options = CreateOptions {
+        raw_target: true,
+        ..CreateOptions::new()
+    };
+    internals::create(Path::new(target.as_ref()), junction.as_ref(), &options).map(drop)
+}
+
+/// Creates a junction named `leaf` in the directory opened as `parent`,
+/// pointing to `target`, without resolving the junction's path at all.
+///
+/// The junction directory is created by `NtCreateFile` relative to the
+/// `parent` handle, and its reparse point written through the handle that
+/// creates it, so neither a change of the current directory nor a concurrent
+/// rename or swap of a parent directory by path can redirect it. This makes
+/// it the most robust way to create a junction in sandboxed or concurrent
+/// contexts. Pass an absolute `target` to keep the current directory out of
+/// it entirely, as relative targets are resolved like [`create`] does.
+///
+/// `parent` must be a handle to a directory, such as one opened with
+/// `FILE_FLAG_BACKUP_SEMANTICS`, which [`std::fs::File`] needs for
+/// directories. Creating the junction is checked against the parent's ACL,
+/// which must grant `FILE_ADD_SUBDIRECTORY` to the caller.
+///
+/// Only available with the `nt-api` feature.
+///
+/// # Error
+///
+/// This function errors with `InvalidInput` if `leaf` isn't a single file
+/// name, or is a reserved device name such as `CON`, with `AlreadyExists` if
+/// `leaf` already exists, and like [`create`] for an invalid target. If
+/// writing the reparse point fails, the empty directory is left behind.
+///
+/// # Example
+///
+/// ```rust
+/// use std::fs::OpenOptions;
+/// use std::io;
+/// use std::os::windows::fs::OpenOptionsExt;
+/// use std::os::windows::io::AsHandle;
+/// # use std::fs;
+/// # use junction::{create_relative_to_handle, get_target};
+/// const FILE_FLAG_BACKUP_SEMANTICS: u32 = 0x0200_0000;
+///
+/// fn main() -> io::Result<()> {
+///     let tmpdir = tempfile::tempdir()?;
+///     let target = tmpdir.path().join("target");
+///     # fs::create_dir_all(&target)?;
+///     let parent = OpenOptions::new()
+///         .read(true)
+///         .custom_flags(FILE_FLAG_BACKUP_SEMANTICS)
+///         .open(tmpdir.path())?;
+///     create_relative_to_handle(parent.as_handle(), "junction", &target)?;
+///     assert_eq!(get_target(tmpdir.path().join("junction"))?, target);
+///     Ok(())
+/// }
+/// ```
+#[cfg(all(windows, feature = "nt-api"))]
+pub fn create_relative_to_handle<L, P>(parent: BorrowedHandle<'_>, leaf: L, target: P) -> io::Result<()>
+where
+    L: AsRef<OsStr>,
+    P: AsRef<Path>,
+{
+    internals::create_relative_to_handle(parent, leaf.as_ref(), target.as_ref())
+}
+
+/// Encodes the reparse data of a junction to the specified target directory
+/// once, for [`apply`] to create many junctions to it.
+///
+/// The target is resolved and its reparse data laid out here, so that each
+/// [`apply`] only creates a directory and writes the buffer.
+///
+/// # Error
+///
+/// This function errors with `InvalidInput` if the target is too long for a
+/// junction, or can't be resolved like [`create`] resolves it.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// # use std::fs;
+/// # use junction::{apply, build_reparse_data, get_target};
+/// fn main() -> io::Result<()> {
+///     let tmpdir = tempfile::tempdir()?;
+///     let target = tmpdir.path().join("target");
+///     # fs::create_dir_all(&target)?;
+///     let data = build_reparse_data(&target)?;
+///     for i in 0..10 {
+///         let junction = tmpdir.path().join(format!("junction{}", i));
+///         apply(&data, &junction)?;
+///         assert_eq!(get_target(&junction)?, get_target(tmpdir.path().join("junction0"))?);
+///     }
+///     Ok(())
+/// }
+/// ```
+#[cfg(windows)]
+pub fn build_reparse_data<P: AsRef<Path>>(target: P) -> io::Result<ReparseData> {
+    internals::build_reparse_data(target.as_ref())
+}
+
+/// Creates a junction point at the specified directory from reparse data
+/// encoded by [`build_reparse_data`].
+///
+/// The buffer is checked to be a well-formed junction before it is written.
+/// Unlike [`create`], the volume isn't checked for reparse point support.
+///
+/// # Error
+///
+/// This function errors with `InvalidData` if `data` is malformed, and
+/// otherwise like [`create`].
+#[cfg(windows)]
+pub fn apply<P: AsRef<Path>>(data: &ReparseData, junction: P) -> io::Result<()> {
+    internals::apply(data, junction.as_ref())
+}
+
+/// Creates a junction point from the specified directory to the specified
+/// target directory, unless it already is one. Returns whether it was created.
+///
+/// Succeeds without changing anything if `junction` is already a junction to
+/// `target`, comparing them ignoring case. Use [`CreateOptions::lock`] when
+/// several processes may ensure the same junction at once.
+///
+/// # Error
+///
+/// This function errors with `AlreadyExists` if `junction` is a junction to
+/// another target, and fails if it is anything else. Otherwise it errors like
+/// [`create`].
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// # use std::fs;
+/// # use junction::ensure;
+/// fn main() -> io::Result<()> {
+///     let tmpdir = tempfile::tempdir()?;
+///     let target = tmpdir.path().join("target");
+///     let junction = tmpdir.path().join("junction");
+///     # fs::create_dir_all(&target)?;
+///     assert!(ensure(&target, &junction)?);
+///     assert!(!ensure(&target, &junction)?);
+///     Ok(())
+/// }
+/// ```
+#[cfg(windows)]
+pub fn ensure<P, Q>(target: P, junction: Q) -> io::Result<bool>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    CreateOptions::new().ensure(target, junction)
 }
 
 /// Deletes a `junction` reparse point from the specified file or directory.
@@ -60,6 +487,10 @@ where
 /// This function delete the junction point only, leaving the target directory
 /// and its content as is. It does nothing if the `junction` point does not exist.
 ///
+/// Directory symbolic links are deleted the same way. Other reparse points,
+/// such as deduplicated files or cloud files placeholders, whose reparse
+/// data holds their contents, are refused with an error and left intact.
+///
 /// # Example
 ///
 /// ```rust
@@ -76,12 +507,136 @@ where
 ///     delete(&junction)
 /// }
 /// ```
+#[cfg(windows)]
 pub fn delete<P: AsRef<Path>>(junction: P) -> io::Result<()> {
-    internals::delete(junction.as_ref())
+    DeleteOptions::new().delete(junction)
+}
+
+/// Deletes a `junction` reparse point like [`delete`], retrying up to
+/// `attempts` more times when access is denied or on a sharing violation,
+/// waiting `backoff` before the first retry and twice as long before each
+/// following one.
+///
+/// Antivirus scanners can hold a just created junction open, making [`delete`]
+/// fail transiently, for instance when tearing down in CI.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// use std::time::Duration;
+/// # use std::fs;
+/// # use junction::{create, delete_with_retry};
+/// fn main() -> io::Result<()> {
+///     let tmpdir = tempfile::tempdir()?;
+///     let target = tmpdir.path().join("target");
+///     let junction = tmpdir.path().join("junction");
+///     # fs::create_dir_all(&target)?;
+///     create(&target, &junction)?;
+///     delete_with_retry(&junction, 3, Duration::from_millis(10))
+/// }
+/// ```
+#[cfg(windows)]
+pub fn delete_with_retry<P: AsRef<Path>>(junction: P, attempts: u32, backoff: Duration) -> io::Result<()> {
+    DeleteOptions::new().with_retry(attempts, backoff).delete(junction)
+}
+
+/// Deletes the reparse points of all `junctions`, carrying on past failures,
+/// and returns the result for each path in order.
+///
+/// Each junction is deleted the same way [`delete`] does, leaving the
+/// directories behind; use [`DeleteOptions::remove_dir`] with
+/// [`DeleteOptions::delete_many`] to remove them as well.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// # use std::fs;
+/// # use junction::{create, delete_many};
+/// fn main() -> io::Result<()> {
+///     let tmpdir = tempfile::tempdir()?;
+///     let target = tmpdir.path().join("target");
+///     let junctions = [tmpdir.path().join("a"), tmpdir.path().join("b")];
+///     # fs::create_dir_all(&target)?;
+///     for junction in &junctions {
+///         create(&target, junction)?;
+///     }
+///     for (junction, result) in delete_many(junctions) {
+///         result?;
+///         assert!(junction.is_dir());
+///     }
+///     Ok(())
+/// }
+/// ```
+#[cfg(windows)]
+pub fn delete_many<I>(junctions: I) -> Vec<(PathBuf, io::Result<()>)>
+where
+    I: IntoIterator<Item = PathBuf>,
+{
+    DeleteOptions::new().delete_many(junctions)
+}
+
+/// Disables a junction point by deleting its reparse point, leaving an empty
+/// directory, and returns the target it had.
+///
+/// The path then resolves to nothing until [`enable`] restores the junction
+/// with the returned target.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// # use std::fs;
+/// # use junction::{create, disable, enable, exists};
+/// fn main() -> io::Result<()> {
+///     let tmpdir = tempfile::tempdir()?;
+///     let target = tmpdir.path().join("target");
+///     let junction = tmpdir.path().join("junction");
+///     # fs::create_dir_all(&target)?;
+///     create(&target, &junction)?;
+///     let target = disable(&junction)?;
+///     assert!(!exists(&junction)?);
+///     enable(&junction, &target)?;
+///     assert!(exists(&junction)?);
+///     Ok(())
+/// }
+/// ```
+#[cfg(windows)]
+pub fn disable<P: AsRef<Path>>(junction: P) -> io::Result<PathBuf> {
+    internals::disable(junction.as_ref())
+}
+
+/// Turns the existing empty directory `junction` back into a junction point
+/// to `target`, such as after [`disable`].
+///
+/// The target is stored the same way [`create`] stores it. Unlike [`create`],
+/// the directory is kept, along with its attributes and permissions.
+///
+/// # Error
+///
+/// This function errors if `junction` doesn't exist or is a non-empty
+/// directory.
+#[cfg(windows)]
+pub fn enable<P, Q>(junction: P, target: Q) -> io::Result<()>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    internals::enable(junction.as_ref(), target.as_ref())
 }
 
 /// Determines whether the specified path exists and refers to a junction point.
 ///
+/// Other reparse points, such as cloud files placeholders from OneDrive or
+/// Projected File System placeholders from VFS for Git, are reported as
+/// `Ok(false)`. They are opened with `FILE_FLAG_OPEN_NO_RECALL` and never
+/// followed, so checking them doesn't download or hydrate their contents.
+///
+/// Junctions aren't followed either: one whose target is missing, or on a
+/// disconnected network share, is reported as `Ok(true)` without waiting
+/// for the share to time out.
+///
 /// # Example
 ///
 /// ```rust
@@ -93,14 +648,18 @@ pub fn delete<P: AsRef<Path>>(junction: P) -> io::Result<()> {
 ///     Ok(())
 /// }
 /// ```
+#[cfg(windows)]
 pub fn exists<P: AsRef<Path>>(junction: P) -> io::Result<bool> {
-    internals::exists(junction.as_ref())
+    ReadOptions::new().exists(junction)
 }
 
 /// Gets the target of the specified junction point.
 ///
 /// N.B. Only works on NTFS.
 ///
+/// Only the junction itself is read, so this returns promptly even when the
+/// target is missing or on an unreachable network share.
+///
 /// # Example
 ///
 /// ```rust
@@ -112,6 +671,1278 @@ pub fn exists<P: AsRef<Path>>(junction: P) -> io::Result<bool> {
 ///     Ok(())
 /// }
 /// ```
+#[cfg(windows)]
 pub fn get_target<P: AsRef<Path>>(junction: P) -> io::Result<PathBuf> {
-    internals::get_target(junction.as_ref())
+    ReadOptions::new().get_target(junction)
+}
+
+/// Gets where the specified junction point leads, as resolved by the file
+/// system, rather than the target it stores.
+///
+/// The junction is opened following it, and `GetFinalPathNameByHandleW` then
+/// reports the final path of the directory opened, without the verbatim
+/// (`\\?\`) prefix. Chains of junctions and symbolic links are followed to
+/// the end, and the path comes back in the case the file system uses, with
+/// any 8.3 short names expanded. Use [`get_target`] for the stored target.
+///
+/// # Error
+///
+/// This function errors with `Other` if `junction` is not a reparse point, and
+/// with `NotFound` if its target doesn't exist.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// # use std::fs;
+/// # use junction::{create, resolved_target};
+/// fn main() -> io::Result<()> {
+///     let tmpdir = tempfile::tempdir()?;
+///     let target = tmpdir.path().join("target");
+///     let junction = tmpdir.path().join("junction");
+///     # fs::create_dir_all(&target)?;
+///     create(&target, &junction)?;
+///     println!("junction -> {}", resolved_target(&junction)?.display());
+///     Ok(())
+/// }
+/// ```
+#[cfg(windows)]
+pub fn resolved_target<P: AsRef<Path>>(junction: P) -> io::Result<PathBuf> {
+    internals::resolved_target(junction.as_ref())
+}
+
+/// Checks whether the target of the specified junction point lies within the
+/// directory `allowed_root`, or is `allowed_root` itself.
+///
+/// Both the target and `allowed_root` are canonicalized first, which resolves
+/// `..` components, 8.3 short names and any links along the way, including a
+/// target that is itself a junction leading elsewhere. They are then compared
+/// component by component, ignoring ASCII case. Tools accepting junctions
+/// from users can reject those that escape a sandbox with this.
+///
+/// # Error
+///
+/// This function errors if `junction` is not a junction, or if its target or
+/// `allowed_root` doesn't exist and so can't be canonicalized.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// # use std::fs;
+/// # use junction::{create, target_within};
+/// fn main() -> io::Result<()> {
+///     let tmpdir = tempfile::tempdir()?;
+///     let sandbox = tmpdir.path().join("sandbox");
+///     let target = sandbox.join("data");
+///     let junction = tmpdir.path().join("junction");
+///     # fs::create_dir_all(&target)?;
+///     create(&target, &junction)?;
+///     assert!(target_within(&junction, &sandbox)?);
+///     Ok(())
+/// }
+/// ```
+#[cfg(windows)]
+pub fn target_within<P, Q>(junction: P, allowed_root: Q) -> io::Result<bool>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    internals::target_within(junction.as_ref(), allowed_root.as_ref())
+}
+
+/// Gets the target of the specified junction point like [`get_target`], but
+/// expressed relative to the directory `base`, such as an install root.
+///
+/// The stored target is absolute, and is compared component by component to
+/// `base`, ignoring ASCII case. Targets beside or outside of `base` start with
+/// `..` components, and a target equal to `base` is returned as `.`. Neither
+/// path is resolved further, so links along either are not followed.
+///
+/// # Error
+///
+/// This function errors with `InvalidInput` if the target and `base` share
+/// no common prefix, such as when they are on different drives.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// use std::path::Path;
+/// # use std::fs;
+/// # use junction::{create, get_target_relative_to};
+/// fn main() -> io::Result<()> {
+///     let tmpdir = tempfile::tempdir()?;
+///     let target = tmpdir.path().join("lib").join("v1");
+///     let junction = tmpdir.path().join("current");
+///     # fs::create_dir_all(&target)?;
+///     create(&target, &junction)?;
+///     let relative = get_target_relative_to(&junction, tmpdir.path())?;
+///     assert_eq!(relative, Path::new(r"lib\v1"));
+///     Ok(())
+/// }
+/// ```
+#[cfg(windows)]
+pub fn get_target_relative_to<P, Q>(junction: P, base: Q) -> io::Result<PathBuf>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    internals::get_target_relative_to(junction.as_ref(), base.as_ref())
+}
+
+/// Gets the target of the specified junction point like [`get_target`], but
+/// maps a target on a volume GUID to the path the volume is mounted at.
+///
+/// Volume mount points store targets such as `\??\Volume{GUID}\`, which
+/// [`get_target`] returns as `Volume{GUID}\`. This function looks up where
+/// that volume is mounted, such as `D:\` or a mounted folder, and returns the
+/// target under it. If the volume is mounted at several paths, the first one
+/// reported is used. Other targets are returned unchanged.
+///
+/// # Error
+///
+/// This function errors with `NotFound` if the target's volume is not mounted
+/// at any path.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// # use std::fs;
+/// # use junction::{create, get_target, get_target_resolve_volume};
+/// fn main() -> io::Result<()> {
+///     let tmpdir = tempfile::tempdir()?;
+///     let target = tmpdir.path().join("target");
+///     let junction = tmpdir.path().join("junction");
+///     # fs::create_dir_all(&target)?;
+///     create(&target, &junction)?;
+///     assert_eq!(get_target_resolve_volume(&junction)?, get_target(&junction)?);
+///     Ok(())
+/// }
+/// ```
+#[cfg(windows)]
+pub fn get_target_resolve_volume<P: AsRef<Path>>(junction: P) -> io::Result<PathBuf> {
+    ReadOptions::new().get_target_resolve_volume(junction)
+}
+
+/// Gets the target of the specified junction point as it is best shown to
+/// users.
+///
+/// This is the PrintName, the Win32 path stored for display, unless it is
+/// empty, as in junctions made by some older tools. Those fall back to the
+/// SubstituteName without its `\??\` prefix, which [`get_target`] returns.
+///
+/// # Error
+///
+/// This function errors with `Other` if `junction` is a reparse point but not
+/// a junction.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// # use std::fs;
+/// # use junction::{create, display_target};
+/// fn main() -> io::Result<()> {
+///     let tmpdir = tempfile::tempdir()?;
+///     let target = tmpdir.path().join("target");
+///     let junction = tmpdir.path().join("junction");
+///     # fs::create_dir_all(&target)?;
+///     create(&target, &junction)?;
+///     println!("junction -> {}", display_target(&junction)?.display());
+///     Ok(())
+/// }
+/// ```
+#[cfg(windows)]
+pub fn display_target<P: AsRef<Path>>(junction: P) -> io::Result<PathBuf> {
+    internals::display_target(junction.as_ref())
+}
+
+/// Checks whether the junction at `junction` is as described by `spec`.
+///
+/// The stored target and the target of `spec`, resolved like [`create`] does,
+/// are compared ignoring case and trailing separators, as NTFS does by default.
+/// The PrintName is compared the same way, unless the spec leaves it out. Plain
+/// files, directories and other kinds of reparse points never match, so this
+/// is the check an idempotent tool makes before (re)creating a junction.
+///
+/// # Error
+///
+/// This function errors if `junction` doesn't exist or can't be read.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// # use std::fs;
+/// # use junction::{create, matches_spec, JunctionSpec};
+/// fn main() -> io::Result<()> {
+///     let tmpdir = tempfile::tempdir()?;
+///     let target = tmpdir.path().join("target");
+///     let junction = tmpdir.path().join("junction");
+///     # fs::create_dir_all(&target)?;
+///     create(&target, &junction)?;
+///     let spec = JunctionSpec { target, print_name: None };
+///     assert!(matches_spec(&junction, &spec)?);
+///     Ok(())
+/// }
+/// ```
+#[cfg(windows)]
+pub fn matches_spec<P: AsRef<Path>>(junction: P, spec: &JunctionSpec) -> io::Result<bool> {
+    internals::matches_spec(junction.as_ref(), spec)
+}
+
+/// Makes `junction` a junction as described by `spec`, returning whether
+/// anything had to change.
+///
+/// A missing junction is created, one with another target is retargeted in
+/// place, and one with another PrintName has its PrintName repaired. When the
+/// spec leaves the PrintName out, the target itself is stored as usual. A
+/// junction that already matches, as checked by [`matches_spec`], is left
+/// alone, so calling this again returns `Ok(false)`.
+///
+/// # Error
+///
+/// This function errors with `AlreadyExists` if `junction` exists but is not
+/// a junction, such as a plain directory or a symbolic link, as those are
+/// never replaced. It also fails like [`create`] does for an invalid target.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// # use std::fs;
+/// # use junction::{apply_spec, JunctionSpec};
+/// fn main() -> io::Result<()> {
+///     let tmpdir = tempfile::tempdir()?;
+///     let target = tmpdir.path().join("target");
+///     let junction = tmpdir.path().join("junction");
+///     # fs::create_dir_all(&target)?;
+///     let spec = JunctionSpec { target, print_name: None };
+///     assert!(apply_spec(&junction, &spec)?);
+///     assert!(!apply_spec(&junction, &spec)?);
+///     Ok(())
+/// }
+/// ```
+#[cfg(windows)]
+pub fn apply_spec<P: AsRef<Path>>(junction: P, spec: &JunctionSpec) -> io::Result<bool> {
+    internals::apply_spec(junction.as_ref(), spec)
+}
+
+/// Makes each junction in `specs` as described by its [`JunctionSpec`], like
+/// [`apply_spec`] does, carrying on past failures, and returns whether each
+/// one changed, in order.
+///
+/// Each directory holding the junctions is listed once up front, rather than
+/// reading the metadata of every junction separately, so converging dozens of
+/// junctions under a root stays cheap. Junctions that already match are only
+/// opened to confirm they do.
+///
+/// With `prune`, junctions in those same directories that aren't in `specs`
+/// are then deleted along with their directories, and reported after the
+/// specs as changed. Other entries are never removed.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// # use std::fs;
+/// # use junction::{create, ensure_many, JunctionSpec};
+/// fn main() -> io::Result<()> {
+///     let tmpdir = tempfile::tempdir()?;
+///     let target = tmpdir.path().join("target");
+///     # fs::create_dir_all(&target)?;
+///     create(&target, tmpdir.path().join("stale"))?;
+///     let spec = JunctionSpec { target, print_name: None };
+///     let specs = [(tmpdir.path().join("a"), spec.clone()), (tmpdir.path().join("b"), spec)];
+///     for (junction, changed) in ensure_many(&specs, true) {
+///         println!("{}: {}", junction.display(), changed?);
+///     }
+///     assert!(!tmpdir.path().join("stale").exists());
+///     Ok(())
+/// }
+/// ```
+#[cfg(windows)]
+pub fn ensure_many(specs: &[(PathBuf, JunctionSpec)], prune: bool) -> Vec<(PathBuf, io::Result<bool>)> {
+    internals::ensure_many(specs, prune)
+}
+
+/// Gets the target of the specified junction point in the drive-letter Win32
+/// form, such as `C:\dir`, whenever there is one.
+///
+/// Targets stored with a verbatim (`\\?\`) or device (`\\.\`) prefix
+/// before a drive letter have it removed, and targets on a volume GUID are
+/// mapped to the volume's drive letter like [`get_target_resolve_volume`]
+/// does. Other targets, such as UNC paths or volumes without a drive letter,
+/// are returned as [`get_target`] returns them.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// # use std::fs;
+/// # use junction::{create, get_target_win32};
+/// fn main() -> io::Result<()> {
+///     let tmpdir = tempfile::tempdir()?;
+///     let target = tmpdir.path().join("target");
+///     let junction = tmpdir.path().join("junction");
+///     # fs::create_dir_all(&target)?;
+///     create(&target, &junction)?;
+///     let target = get_target_win32(&junction)?;
+///     assert_eq!(target.to_str().unwrap().as_bytes()[1], b':');
+///     Ok(())
+/// }
+/// ```
+#[cfg(windows)]
+pub fn get_target_win32<P: AsRef<Path>>(junction: P) -> io::Result<PathBuf> {
+    ReadOptions::new().get_target_win32(junction)
+}
+
+/// Returns the canonical path of the junction point itself, as opposed to
+/// its target.
+///
+/// The parent directory is fully resolved, following any junctions or
+/// symbolic links along the way, but the final component is kept as is. This
+/// gives a consistent location for junctions nested beneath other junctions.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// # use std::fs;
+/// # use junction::{create, real_path};
+/// fn main() -> io::Result<()> {
+///     let tmpdir = tempfile::tempdir()?;
+///     let target = tmpdir.path().join("target");
+///     let junction = tmpdir.path().join("junction");
+///     # fs::create_dir_all(&target)?;
+///     create(&target, &junction)?;
+///     assert_eq!(real_path(&junction)?.file_name(), junction.file_name());
+///     Ok(())
+/// }
+/// ```
+#[cfg(windows)]
+pub fn real_path<P: AsRef<Path>>(junction: P) -> io::Result<PathBuf> {
+    internals::real_path(junction.as_ref())
+}
+
+/// Returns the volume serial number and file index of the junction point
+/// itself, which together identify it on the system regardless of the path
+/// it is reached through.
+///
+/// The junction is not followed, so this differs from the identity of its
+/// target. The file index may change if the junction is moved across volumes
+/// or recreated.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// # use std::fs;
+/// # use junction::{create, file_id};
+/// fn main() -> io::Result<()> {
+///     let tmpdir = tempfile::tempdir()?;
+///     let target = tmpdir.path().join("target");
+///     let junction = tmpdir.path().join("junction");
+///     # fs::create_dir_all(&target)?;
+///     create(&target, &junction)?;
+///     assert_ne!(file_id(&junction)?, file_id(&target)?);
+///     Ok(())
+/// }
+/// ```
+#[cfg(windows)]
+pub fn file_id<P: AsRef<Path>>(junction: P) -> io::Result<(u32, u64)> {
+    let id = internals::file_id(junction.as_ref())?;
+    Ok((id.volume_serial_number, id.file_index))
+}
+
+/// Reads the target of the junction point or symbolic link at `path`, along
+/// with its kind and whether the stored target is relative.
+///
+/// Mount points always store absolute targets. Symbolic links may store a
+/// target relative to the directory holding them, which their flags record.
+/// Unlike [`get_target`], this doesn't check that `path` exists first.
+///
+/// # Error
+///
+/// This function errors if `path` is not a reparse point, and with `Other` if
+/// it is neither a junction point nor a symbolic link.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// # use std::fs;
+/// # use junction::{create, read_reparse_point, ReparseTag};
+/// fn main() -> io::Result<()> {
+///     let tmpdir = tempfile::tempdir()?;
+///     let target = tmpdir.path().join("target");
+///     let junction = tmpdir.path().join("junction");
+///     # fs::create_dir_all(&target)?;
+///     create(&target, &junction)?;
+///     let reparse_point = read_reparse_point(&junction)?;
+///     assert_eq!(reparse_point.tag, ReparseTag::MountPoint);
+///     assert!(!reparse_point.relative);
+///     Ok(())
+/// }
+/// ```
+#[cfg(windows)]
+pub fn read_reparse_point<P: AsRef<Path>>(path: P) -> io::Result<ReparsePoint> {
+    ReadOptions::new().read_reparse_point(path)
+}
+
+/// Reports what is at `path` as far as junctions go, for a status display
+/// that needs no further queries.
+///
+/// The reparse point is read once, without following it: a junction is
+/// reported with its target, PrintName and how much of the reparse data
+/// buffer it takes up, and only then followed to check whether its target
+/// exists. Symbolic links, to files or directories, are reported with their
+/// target, and other reparse points with their tag.
+///
+/// # Error
+///
+/// This function errors if `path` exists but can't be opened or its reparse
+/// data can't be read, or with `InvalidData` if a junction's or symbolic
+/// link's reparse data is malformed.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// # use std::fs;
+/// # use junction::{create, status, Status};
+/// fn main() -> io::Result<()> {
+///     let tmpdir = tempfile::tempdir()?;
+///     let target = tmpdir.path().join("target");
+///     let junction = tmpdir.path().join("junction");
+///     assert_eq!(status(&junction)?, Status::Missing);
+///     # fs::create_dir_all(&target)?;
+///     create(&target, &junction)?;
+///     match status(&junction)? {
+///         Status::Junction { target_exists, .. } => assert!(target_exists),
+///         other => panic!("not a junction: {:?}", other),
+///     }
+///     Ok(())
+/// }
+/// ```
+#[cfg(windows)]
+pub fn status<P: AsRef<Path>>(path: P) -> io::Result<Status> {
+    internals::status(path.as_ref())
+}
+
+/// Follows the reparse points from `start`, returning each one along with its
+/// tag, such as junction `A` leading to junction `B`.
+///
+/// Each hop reads the target stored in the reparse point, so the chain shows
+/// how a path resolves step by step even when it doesn't resolve at all. It
+/// ends at the first path that is not a reparse point or doesn't exist, which
+/// is the stored target of the last hop, at a reparse point other than a
+/// junction or symbolic link, or after `max_hops` hops. A `start` that isn't
+/// a reparse point gives an empty chain.
+///
+/// # Error
+///
+/// This function errors with `InvalidData` if the reparse points lead back to
+/// one already visited.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// # use std::fs;
+/// # use junction::{create, resolution_chain, ReparseTag};
+/// fn main() -> io::Result<()> {
+///     let tmpdir = tempfile::tempdir()?;
+///     let target = tmpdir.path().join("target");
+///     let first = tmpdir.path().join("first");
+///     let second = tmpdir.path().join("second");
+///     # fs::create_dir_all(&target)?;
+///     create(&target, &second)?;
+///     create(&second, &first)?;
+///     let chain = resolution_chain(&first, 10)?;
+///     assert_eq!(chain.len(), 2);
+///     assert!(chain.iter().all(|(_, tag)| *tag == ReparseTag::MountPoint));
+///     Ok(())
+/// }
+/// ```
+#[cfg(windows)]
+pub fn resolution_chain<P: AsRef<Path>>(start: P, max_hops: usize) -> io::Result<Vec<(PathBuf, ReparseTag)>> {
+    internals::resolution_chain(start.as_ref(), max_hops)
+}
+
+/// Gets the target of the symbolic link at `path`, whether it links to a file
+/// or a directory.
+///
+/// Absolute targets are returned without their `\??\` prefix, with those on
+/// a share as `\\server\share`, and relative targets as stored, relative to
+/// the directory holding the link.
+///
+/// # Error
+///
+/// This function errors with `Other` if `path` is a reparse point but not a
+/// symbolic link, such as a junction.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use std::io;
+/// use std::os::windows::fs::symlink_file;
+/// # use std::fs;
+/// # use junction::get_symlink_target;
+/// fn main() -> io::Result<()> {
+///     let tmpdir = tempfile::tempdir()?;
+///     let target = tmpdir.path().join("target");
+///     let link = tmpdir.path().join("link");
+///     # fs::write(&target, "")?;
+///     // Needs either privileges or developer mode.
+///     symlink_file(&target, &link)?;
+///     assert_eq!(get_symlink_target(&link)?, target);
+///     Ok(())
+/// }
+/// ```
+#[cfg(windows)]
+pub fn get_symlink_target<P: AsRef<Path>>(path: P) -> io::Result<PathBuf> {
+    internals::get_symlink_target(path.as_ref())
+}
+
+/// Gets the target of the WSL symbolic link at `path`.
+///
+/// Symbolic links created from inside WSL are reparse points of their own
+/// kind, [`ReparseTag::LxSymlink`], that store a Linux path as UTF-8. The
+/// target is returned as stored, usually with forward slashes and often
+/// relative, so it is not a Windows path. [`get_target`] does not read these.
+///
+/// # Error
+///
+/// This function errors with `Other` if `path` is a reparse point but not a
+/// WSL symbolic link, and with `InvalidData` if its target isn't UTF-8.
+#[cfg(windows)]
+pub fn lx_symlink_target<P: AsRef<Path>>(path: P) -> io::Result<String> {
+    internals::lx_symlink_target(path.as_ref())
+}
+
+/// Reads the raw reparse data buffer of the reparse point at `path`, as
+/// returned by `FSCTL_GET_REPARSE_POINT`.
+///
+/// The buffer is exactly as long as its header declares, and can be decoded
+/// with the [`parse`] module, on any platform.
+#[cfg(windows)]
+pub fn raw_reparse_buffer<P: AsRef<Path>>(path: P) -> io::Result<Vec<u8>> {
+    internals::raw_reparse_buffer(path.as_ref())
+}
+
+/// Reads the `ReparseDataLength` of the reparse point at `path`: the size of
+/// its reparse data, without the 8-byte header.
+///
+/// Pass it to [`parse::utilization`] to see how close a junction's target is
+/// to the limit that [`create`] enforces.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// # use std::fs;
+/// # use junction::{create, parse, reparse_data_len};
+/// fn main() -> io::Result<()> {
+///     let tmpdir = tempfile::tempdir()?;
+///     let target = tmpdir.path().join("target");
+///     let junction = tmpdir.path().join("junction");
+///     # fs::create_dir_all(&target)?;
+///     create(&target, &junction)?;
+///     let len = reparse_data_len(&junction)?;
+///     assert!(parse::utilization(len) < 5.0);
+///     Ok(())
+/// }
+/// ```
+#[cfg(windows)]
+pub fn reparse_data_len<P: AsRef<Path>>(path: P) -> io::Result<u16> {
+    internals::reparse_data_len(path.as_ref())
+}
+
+/// Compares the raw reparse data buffers of the reparse points `a` and `b`,
+/// returning where they first differ, or `None` if they are identical.
+///
+/// This helps tracking down junctions that break when copied, such as those
+/// with an empty PrintName in Windows Container layers. See
+/// [`parse::diff_reparse_buffers`] for how the difference is reported.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// # use std::fs;
+/// # use junction::{create, diff_reparse};
+/// fn main() -> io::Result<()> {
+///     let tmpdir = tempfile::tempdir()?;
+///     let target = tmpdir.path().join("target");
+///     let a = tmpdir.path().join("a");
+///     let b = tmpdir.path().join("b");
+///     # fs::create_dir_all(&target)?;
+///     create(&target, &a)?;
+///     create(&target, &b)?;
+///     assert_eq!(diff_reparse(&a, &b)?, None);
+///     Ok(())
+/// }
+/// ```
+#[cfg(windows)]
+pub fn diff_reparse<P, Q>(a: P, b: Q) -> io::Result<Option<parse::ReparseDiff>>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    internals::diff_reparse(a.as_ref(), b.as_ref())
+}
+
+/// Gets the target of the junction point opened as `file`.
+///
+/// This is [`get_target`] for callers that already hold a handle, such as one
+/// opened with custom options. The file must have been opened with the
+/// `FILE_FLAG_OPEN_REPARSE_POINT` flag, so that the handle refers to the
+/// junction itself rather than its target, and with `FILE_FLAG_BACKUP_SEMANTICS`,
+/// which opening any directory requires.
+///
+/// # Example
+///
+/// ```rust
+/// use std::fs::OpenOptions;
+/// use std::io;
+/// use std::os::windows::fs::OpenOptionsExt;
+/// # use std::fs;
+/// # use junction::{create, get_target, get_target_from_file};
+/// const FILE_FLAG_OPEN_REPARSE_POINT: u32 = 0x0020_0000;
+/// const FILE_FLAG_BACKUP_SEMANTICS: u32 = 0x0200_0000;
+/// fn main() -> io::Result<()> {
+///     let tmpdir = tempfile::tempdir()?;
+///     let target = tmpdir.path().join("target");
+///     let junction = tmpdir.path().join("junction");
+///     # fs::create_dir_all(&target)?;
+///     create(&target, &junction)?;
+///     let file = OpenOptions::new()
+///         .read(true)
+///         .custom_flags(FILE_FLAG_OPEN_REPARSE_POINT | FILE_FLAG_BACKUP_SEMANTICS)
+///         .open(&junction)?;
+///     assert_eq!(get_target_from_file(&file)?, get_target(&junction)?);
+///     Ok(())
+/// }
+/// ```
+#[cfg(windows)]
+pub fn get_target_from_file(file: &File) -> io::Result<PathBuf> {
+    internals::get_target_from_file(file)
+}
+
+/// Gets the tag of the reparse point opened as `file`.
+///
+/// The file must have been opened with the `FILE_FLAG_OPEN_REPARSE_POINT`
+/// flag, as for [`get_target_from_file`]. Errors if it is not a reparse point.
+#[cfg(windows)]
+pub fn tag_from_file(file: &File) -> io::Result<ReparseTag> {
+    internals::tag_from_file(file)
+}
+
+/// Gets the tag of the reparse point at `path`, such as
+/// [`ReparseTag::MountPoint`] for a junction.
+///
+/// If opening the reparse point or reading its data is denied, the tag is
+/// taken from the listing of its parent directory instead, which needs no
+/// access to the entry itself.
+///
+/// # Error
+///
+/// This function errors if `path` is not a reparse point.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// # use std::fs;
+/// # use junction::{create, reparse_tag, ReparseTag};
+/// fn main() -> io::Result<()> {
+///     let tmpdir = tempfile::tempdir()?;
+///     let target = tmpdir.path().join("target");
+///     let junction = tmpdir.path().join("junction");
+///     # fs::create_dir_all(&target)?;
+///     create(&target, &junction)?;
+///     assert_eq!(reparse_tag(&junction)?, ReparseTag::MountPoint);
+///     Ok(())
+/// }
+/// ```
+#[cfg(windows)]
+pub fn reparse_tag<P: AsRef<Path>>(path: P) -> io::Result<ReparseTag> {
+    internals::reparse_tag(path.as_ref())
+}
+
+/// Lists the junction points directly inside `dir`, each with its target.
+///
+/// Entries are filtered on their reparse point attribute from the directory
+/// listing, and each candidate is opened once to both confirm it is a junction
+/// and read its target. Other kinds of reparse points, such as symbolic links,
+/// are skipped, as are junctions deleted while listing or whose reparse data
+/// may not be read. Junctions are not followed.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// # use std::fs;
+/// # use junction::{create, list_junctions_with_targets};
+/// fn main() -> io::Result<()> {
+///     let tmpdir = tempfile::tempdir()?;
+///     let target = tmpdir.path().join("target");
+///     let junction = tmpdir.path().join("junction");
+///     # fs::create_dir_all(&target)?;
+///     create(&target, &junction)?;
+///     for (junction, target) in list_junctions_with_targets(tmpdir.path())? {
+///         println!("{} -> {}", junction.display(), target.display());
+///     }
+///     Ok(())
+/// }
+/// ```
+#[cfg(windows)]
+pub fn list_junctions_with_targets<P: AsRef<Path>>(dir: P) -> io::Result<Vec<(PathBuf, PathBuf)>> {
+    internals::list_junctions_with_targets(dir.as_ref())
+}
+
+/// Finds every junction on the volume holding `volume`, each with its target,
+/// sorted by path.
+///
+/// On NTFS volumes with a change journal, the entries come from the MFT
+/// through `FSCTL_ENUM_USN_DATA`, which is much faster than walking a large
+/// volume. Only directories with the reparse point attribute are then opened,
+/// to confirm they are junctions and read their targets. Opening the volume
+/// needs administrator rights; without them, or without an active journal,
+/// this falls back to walking every directory from the root of the volume.
+/// Either way, entries that can't be read are skipped and junctions are not
+/// followed.
+///
+/// Volumes that are only mounted at a folder can't be opened by their root and
+/// are always walked.
+///
+/// # Error
+///
+/// This function fails if the root of the volume can't be found or listed.
+#[cfg(windows)]
+pub fn find_junctions_usn<P: AsRef<Path>>(volume: P) -> io::Result<Vec<(PathBuf, PathBuf)>> {
+    internals::find_junctions_usn(volume.as_ref())
+}
+
+/// Converts the directory symbolic link at `path` into a junction to the same
+/// target, in place.
+///
+/// The link is opened once, without sharing, and its reparse data is swapped
+/// on that handle, so no other process sees it half converted. If writing
+/// the junction fails, the symbolic link is put back.
+///
+/// # Error
+///
+/// This function errors with `Other` if `path` is a reparse point but not a
+/// symbolic link, and with `InvalidInput` if it is a symbolic link to a file
+/// or with a relative target, since junctions need absolute targets.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use std::io;
+/// use std::os::windows::fs::symlink_dir;
+/// # use std::fs;
+/// # use junction::{convert_symlink_to_junction, exists};
+/// fn main() -> io::Result<()> {
+///     let tmpdir = tempfile::tempdir()?;
+///     let target = tmpdir.path().join("target");
+///     let link = tmpdir.path().join("link");
+///     # fs::create_dir_all(&target)?;
+///     // Needs Developer Mode or the privilege to create symbolic links.
+///     symlink_dir(&target, &link)?;
+///     convert_symlink_to_junction(&link)?;
+///     assert!(exists(&link)?);
+///     Ok(())
+/// }
+/// ```
+#[cfg(windows)]
+pub fn convert_symlink_to_junction<P: AsRef<Path>>(path: P) -> io::Result<()> {
+    internals::convert_symlink_to_junction(path.as_ref())
+}
+
+/// Moves the contents of the directory `dir` into `store_root/<name>`, where
+/// `<name>` is the final component of `dir`, and replaces `dir` with a
+/// junction pointing there.
+///
+/// The contents stay reachable under `dir` through the junction. Entries are
+/// moved one by one, so `store_root` must be on the same volume as `dir`. If
+/// moving fails halfway, the entries moved so far are left in the store.
+///
+/// # Error
+///
+/// This function errors if `dir` is not a real directory, and with
+/// `AlreadyExists` if its store entry already exists. Use
+/// [`ConvertOptions::merge`] to merge into an existing store entry instead.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// use std::fs;
+/// # use junction::convert_to_junction;
+/// fn main() -> io::Result<()> {
+///     let tmpdir = tempfile::tempdir()?;
+///     let dir = tmpdir.path().join("node_modules");
+///     let store = tmpdir.path().join("store");
+///     fs::create_dir_all(&dir)?;
+///     fs::write(dir.join("file"), "contents")?;
+///     convert_to_junction(&dir, &store)?;
+///     assert_eq!(fs::read_to_string(store.join("node_modules").join("file"))?, "contents");
+///     assert_eq!(fs::read_to_string(dir.join("file"))?, "contents");
+///     Ok(())
+/// }
+/// ```
+#[cfg(windows)]
+pub fn convert_to_junction<P, Q>(dir: P, store_root: Q) -> io::Result<()>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    ConvertOptions::new().convert_to_junction(dir, store_root)
+}
+
+/// Replaces the populated directory `dir` with a junction to `target`,
+/// moving the directory aside rather than deleting anything, and returns the
+/// path it was moved to.
+///
+/// The junction is created next to `dir` first, then `dir` is renamed to
+/// `<name>.old` and the junction renamed into its place, so `dir` is only
+/// missing between the two renames, without anything copied in between. A
+/// numbered suffix such as `<name>.old.1` is used if the name is taken.
+/// Cleaning up the old contents is left to the caller.
+///
+/// # Error
+///
+/// This function errors with `InvalidInput` if `dir` is not a real directory.
+/// If the junction can't be moved into place, the directory is moved back.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// use std::fs;
+/// # use junction::{get_target, replace_dir_with_junction};
+/// fn main() -> io::Result<()> {
+///     let tmpdir = tempfile::tempdir()?;
+///     let target = tmpdir.path().join("target");
+///     let dir = tmpdir.path().join("dir");
+///     fs::create_dir_all(&target)?;
+///     fs::create_dir_all(&dir)?;
+///     fs::write(dir.join("file"), "contents")?;
+///     let stash = replace_dir_with_junction(&target, &dir)?;
+///     assert!(get_target(&dir)?.ends_with("target"));
+///     assert_eq!(fs::read_to_string(stash.join("file"))?, "contents");
+///     Ok(())
+/// }
+/// ```
+#[cfg(windows)]
+pub fn replace_dir_with_junction<P, Q>(target: P, dir: Q) -> io::Result<PathBuf>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    convert::replace_dir_with_junction(target.as_ref(), dir.as_ref())
+}
+
+/// Checks the junction at `junction` for every known way a junction can
+/// break, returning the problems found.
+///
+/// The checks are that the path exists, is a reparse point tagged as a mount
+/// point, has well-formed reparse data with an NT path as its SubstituteName
+/// and a non-empty PrintName, and that its target currently resolves. An empty
+/// list means the junction is healthy. Checks that depend on an earlier one
+/// are skipped when it fails.
+///
+/// # Error
+///
+/// This function errors if `junction` can't be inspected at all, such as when
+/// access to it is denied.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// # use std::fs;
+/// # use junction::{create, diagnose};
+/// fn main() -> io::Result<()> {
+///     let tmpdir = tempfile::tempdir()?;
+///     let target = tmpdir.path().join("target");
+///     let junction = tmpdir.path().join("junction");
+///     # fs::create_dir_all(&target)?;
+///     create(&target, &junction)?;
+///     for diagnostic in diagnose(&junction)? {
+///         println!("{:?}: {}", diagnostic.severity, diagnostic.message);
+///     }
+///     Ok(())
+/// }
+/// ```
+#[cfg(windows)]
+pub fn diagnose<P: AsRef<Path>>(junction: P) -> io::Result<Vec<Diagnostic>> {
+    diagnose::diagnose(junction.as_ref())
+}
+
+/// Determines whether the specified junction is broken, its target no longer
+/// existing.
+///
+/// Only a target that is gone, as told by `ERROR_FILE_NOT_FOUND` or
+/// `ERROR_PATH_NOT_FOUND` when following the junction, counts as broken.
+///
+/// # Error
+///
+/// This function errors if `junction` isn't a junction, or if its target
+/// can't be reached to tell, such as on a disconnected network share or a
+/// drive that isn't mounted, rather than guessing either way.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// # use std::fs;
+/// # use junction::{create, is_broken};
+/// fn main() -> io::Result<()> {
+///     let tmpdir = tempfile::tempdir()?;
+///     let target = tmpdir.path().join("target");
+///     let junction = tmpdir.path().join("junction");
+///     # fs::create_dir_all(&target)?;
+///     create(&target, &junction)?;
+///     assert!(!is_broken(&junction)?);
+///     fs::remove_dir(&target)?;
+///     assert!(is_broken(&junction)?);
+///     Ok(())
+/// }
+/// ```
+#[cfg(windows)]
+pub fn is_broken<P: AsRef<Path>>(junction: P) -> io::Result<bool> {
+    internals::is_broken(junction.as_ref())
+}
+
+/// Removes the broken junctions under `root`, as told by [`is_broken`],
+/// along with their directories, and returns the paths removed.
+///
+/// Subdirectories are searched too, but junctions are not followed, so only
+/// junctions that lie under `root` itself are removed. Junctions whose
+/// target can't be reached to tell, such as those to a disconnected network
+/// share, are kept, as are valid ones. `root` itself is not included.
+///
+/// # Error
+///
+/// This function stops at the first directory under `root` that can't be
+/// listed, or broken junction that can't be removed, leaving the ones
+/// removed so far removed.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// # use std::fs;
+/// # use junction::{create, prune_broken};
+/// fn main() -> io::Result<()> {
+///     let tmpdir = tempfile::tempdir()?;
+///     let target = tmpdir.path().join("target");
+///     let junction = tmpdir.path().join("junction");
+///     # fs::create_dir_all(&target)?;
+///     create(&target, &junction)?;
+///     fs::remove_dir(&target)?;
+///     assert_eq!(prune_broken(tmpdir.path())?, vec![junction.clone()]);
+///     assert!(!junction.exists());
+///     Ok(())
+/// }
+/// ```
+#[cfg(windows)]
+pub fn prune_broken<P: AsRef<Path>>(root: P) -> io::Result<Vec<PathBuf>> {
+    internals::prune_broken(root.as_ref())
+}
+
+/// Sums the sizes of the files under the target of the specified junction
+/// point.
+///
+/// Junctions and symbolic links to directories within the target are
+/// followed, but every directory is only counted once, however many links
+/// lead to it. This keeps links pointing back up the tree from recursing
+/// forever. Links to files and broken links are not counted. Files whose
+/// reparse point isn't a link, such as deduplicated files or cloud files
+/// placeholders, count with their logical size, without being opened.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// use std::fs;
+/// # use junction::{create, target_size};
+/// fn main() -> io::Result<()> {
+///     let tmpdir = tempfile::tempdir()?;
+///     let target = tmpdir.path().join("target");
+///     let junction = tmpdir.path().join("junction");
+///     fs::create_dir_all(&target)?;
+///     fs::write(target.join("file"), "contents")?;
+///     create(&target, &junction)?;
+///     assert_eq!(target_size(&junction)?, 8);
+///     Ok(())
+/// }
+/// ```
+#[cfg(windows)]
+pub fn target_size<P: AsRef<Path>>(junction: P) -> io::Result<u64> {
+    internals::target_size(junction.as_ref())
+}
+
+/// Copies the tree at `src` to `dst`, replacing each junction with a copy of
+/// the contents of its target.
+///
+/// The copy holds no junctions, so it can be moved or archived without
+/// depending on anything outside of it. Symbolic links to directories are
+/// followed the same way. A directory reached through several junctions is
+/// copied once for each of them.
+///
+/// # Error
+///
+/// This function errors with `InvalidInput` if a junction leads back to a
+/// directory it is itself in, which would be copied forever. Parts of the
+/// tree copied so far are left in `dst`.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// use std::fs;
+/// # use junction::{copy_tree_flatten, create, exists};
+/// fn main() -> io::Result<()> {
+///     let tmpdir = tempfile::tempdir()?;
+///     let target = tmpdir.path().join("target");
+///     let src = tmpdir.path().join("src");
+///     let dst = tmpdir.path().join("dst");
+///     fs::create_dir_all(&target)?;
+///     fs::create_dir_all(&src)?;
+///     fs::write(target.join("file"), "contents")?;
+///     create(&target, src.join("junction"))?;
+///     copy_tree_flatten(&src, &dst)?;
+///     assert!(!exists(dst.join("junction"))?);
+///     assert_eq!(fs::read_to_string(dst.join("junction").join("file"))?, "contents");
+///     Ok(())
+/// }
+/// ```
+#[cfg(windows)]
+pub fn copy_tree_flatten<P, Q>(src: P, dst: Q) -> io::Result<()>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    internals::copy_tree_flatten(src.as_ref(), dst.as_ref())
+}
+
+/// Counts the reparse points under `root` by their raw tag.
+///
+/// The tree is walked without following reparse points: each one is counted
+/// and its contents, if any, are skipped. `root` itself is not counted. The
+/// constants in the [`tags`] module, or [`ReparseTag`], can map the tags to
+/// names.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// # use std::fs;
+/// # use junction::{create, reparse_tag_histogram, tags};
+/// fn main() -> io::Result<()> {
+///     let tmpdir = tempfile::tempdir()?;
+///     let target = tmpdir.path().join("target");
+///     let junction = tmpdir.path().join("junction");
+///     # fs::create_dir_all(&target)?;
+///     create(&target, &junction)?;
+///     let histogram = reparse_tag_histogram(tmpdir.path())?;
+///     assert_eq!(histogram.get(&tags::MOUNT_POINT), Some(&1));
+///     Ok(())
+/// }
+/// ```
+#[cfg(windows)]
+pub fn reparse_tag_histogram<P: AsRef<Path>>(root: P) -> io::Result<HashMap<u32, usize>> {
+    internals::reparse_tag_histogram(root.as_ref())
+}
+
+/// Groups the junctions under `root` by their target, to find several
+/// junctions pointing at the same place, such as redundant links in a build
+/// cache.
+///
+/// Targets are compared by their canonical path, ignoring case, so that
+/// different spellings of one directory share a group. Targets that are gone
+/// are compared as stored. Each group is keyed by the target as the first
+/// junction found with it stores it. Subdirectories are searched too, but
+/// junctions are not followed. `root` itself is not included.
+///
+/// # Error
+///
+/// This function fails if a directory under `root` can't be listed, or a
+/// junction found can't be read.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// # use std::fs;
+/// # use junction::{create, group_by_target};
+/// fn main() -> io::Result<()> {
+///     let tmpdir = tempfile::tempdir()?;
+///     let target = tmpdir.path().join("target");
+///     # fs::create_dir_all(&target)?;
+///     create(&target, tmpdir.path().join("a"))?;
+///     create(&target, tmpdir.path().join("b"))?;
+///     let groups = group_by_target(tmpdir.path())?;
+///     assert_eq!(groups.len(), 1);
+///     assert_eq!(groups.values().next().unwrap().len(), 2);
+///     Ok(())
+/// }
+/// ```
+#[cfg(windows)]
+pub fn group_by_target<P: AsRef<Path>>(root: P) -> io::Result<HashMap<PathBuf, Vec<PathBuf>>> {
+    internals::group_by_target(root.as_ref())
+}
+
+/// Re-points every junction under `root` whose target is `old_prefix` or
+/// lies beneath it, so that it leads to the same place under `new_prefix`
+/// instead. Returns how many junctions were changed.
+///
+/// Useful once a shared store moves, say from `D:\store` to `E:\store`.
+/// Prefixes match whole components, ignoring case. Subdirectories are
+/// searched too, but junctions are not followed. Each junction is rewritten
+/// in place, so it keeps its attributes and security.
+///
+/// # Error
+///
+/// This function stops at the first junction that can't be read or
+/// rewritten, leaving the ones changed so far changed.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// # use std::fs;
+/// # use junction::{create, get_target, retarget_prefix};
+/// fn main() -> io::Result<()> {
+///     let tmpdir = tempfile::tempdir()?;
+///     let old = tmpdir.path().join("old");
+///     let new = tmpdir.path().join("new");
+///     let junction = tmpdir.path().join("junction");
+///     # fs::create_dir_all(old.join("pkg"))?;
+///     create(old.join("pkg"), &junction)?;
+///     fs::rename(&old, &new)?;
+///     assert_eq!(retarget_prefix(tmpdir.path(), &old, &new)?, 1);
+///     assert_eq!(get_target(&junction)?, new.join("pkg"));
+///     Ok(())
+/// }
+/// ```
+#[cfg(windows)]
+pub fn retarget_prefix<P, Q, R>(root: P, old_prefix: Q, new_prefix: R) -> io::Result<usize>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+    R: AsRef<Path>,
+{
+    internals::retarget_prefix(root.as_ref(), old_prefix.as_ref(), new_prefix.as_ref())
+}
+
+/// Watches the specified junction point, calling `on_change` with its new
+/// target whenever it is retargeted, until the returned [`WatchHandle`] is
+/// stopped or dropped.
+///
+/// Changes to the junction's parent directory are watched from a background
+/// thread, which `on_change` runs on. After each change to the junction, its
+/// target is read again, and `on_change` is only called if it differs from
+/// the last one seen. While the junction is missing, nothing is reported.
+///
+/// Only available with the `watch` feature.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// use std::sync::mpsc;
+/// use std::time::Duration;
+/// # use std::fs;
+/// # use junction::{create, disable, enable, watch_target};
+/// fn main() -> io::Result<()> {
+///     let tmpdir = tempfile::tempdir()?;
+///     let old = tmpdir.path().join("old");
+///     let new = tmpdir.path().join("new");
+///     let junction = tmpdir.path().join("junction");
+///     # fs::create_dir_all(&old)?;
+///     # fs::create_dir_all(&new)?;
+///     create(&old, &junction)?;
+///     let (sender, receiver) = mpsc::channel();
+///     let watch = watch_target(&junction, move |target| sender.send(target).unwrap())?;
+///     disable(&junction)?;
+///     enable(&junction, &new)?;
+///     assert_eq!(receiver.recv_timeout(Duration::from_secs(10)).unwrap(), new);
+///     watch.stop();
+///     Ok(())
+/// }
+/// ```
+#[cfg(all(windows, feature = "watch"))]
+pub fn watch_target<P, F>(junction: P, on_change: F) -> io::Result<WatchHandle>
+where
+    P: AsRef<Path>,
+    F: FnMut(PathBuf) + Send + 'static,
+{
+    watch::watch_target(junction.as_ref(), on_change)
+}
+
+/// Creates `target`, writes `marker_contents` to a `marker.txt` file in it,
+/// then creates a junction to it at `junction`.
+///
+/// Pair with [`verify_marker`] to check, for instance after snapshotting a
+/// container layer, that the junction still resolves to the same directory.
+///
+/// Only available with the `testutil` feature.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// # use junction::{create_with_marker, verify_marker};
+/// fn main() -> io::Result<()> {
+///     let tmpdir = tempfile::tempdir()?;
+///     let target = tmpdir.path().join("target");
+///     let junction = tmpdir.path().join("junction");
+///     create_with_marker(&target, &junction, "junction-test-ok")?;
+///     assert!(verify_marker(&junction, "junction-test-ok")?);
+///     Ok(())
+/// }
+/// ```
+#[cfg(all(windows, feature = "testutil"))]
+pub fn create_with_marker<P, Q>(target: P, junction: Q, marker_contents: &str) -> io::Result<()>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    testutil::create_with_marker(target.as_ref(), junction.as_ref(), marker_contents)
+}
+
+/// Checks that the `marker.txt` file written by [`create_with_marker`] reads
+/// `expected` through `junction`.
+///
+/// Returns `Ok(false)` if the marker can't be found through the junction,
+/// such as when the junction is broken or leads elsewhere.
+///
+/// Only available with the `testutil` feature.
+#[cfg(all(windows, feature = "testutil"))]
+pub fn verify_marker<P: AsRef<Path>>(junction: P, expected: &str) -> io::Result<bool> {
+    testutil::verify_marker(junction.as_ref(), expected)
+}
+
+/// Returns the reparse data buffer that [`create`] would write for `target`,
+/// byte for byte, without touching the filesystem.
+///
+/// Tests can compare it against a checked-in golden buffer, to catch changes
+/// to the offsets, lengths or PrintName apart from any filesystem effects.
+///
+/// Only available with the `testutil` feature.
+///
+/// # Error
+///
+/// This function errors like [`build_reparse_data`].
+#[cfg(all(windows, feature = "testutil"))]
+pub fn encode_reparse_buffer<P: AsRef<Path>>(target: P) -> io::Result<Vec<u8>> {
+    testutil::encode_reparse_buffer(target.as_ref())
 }
@@ -0,0 +1,689 @@
+use std::io;
+use std::os::windows::io::OwnedHandle;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::{convert, internals, IntegrityLevel, ReparseTag, Sid};
+
+/// Options and flags which can be used to configure how a junction is created.
+///
+/// This builder exposes the ability to configure how a junction point is
+/// created, much like [`std::fs::OpenOptions`] does for files. [`create`]
+/// is the same as calling [`CreateOptions::create`] with the defaults.
+///
+/// [`create`]: crate::create
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// # use std::fs;
+/// use junction::CreateOptions;
+/// fn main() -> io::Result<()> {
+///     let tmpdir = tempfile::tempdir()?;
+///     let target = tmpdir.path().join("target");
+///     let junction = tmpdir.path().join("junction");
+///     # fs::create_dir_all(&target)?;
+///     // FILE_ATTRIBUTE_HIDDEN | FILE_ATTRIBUTE_SYSTEM
+///     CreateOptions::new().attributes(0x2 | 0x4).create(&target, &junction)
+/// }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct CreateOptions {
+    pub(crate) attributes: u32,
+    pub(crate) retry: Retry,
+    pub(crate) owner: Option<Sid>,
+    pub(crate) integrity_level: Option<IntegrityLevel>,
+    pub(crate) no_follow_parents: bool,
+    pub(crate) strict_target: bool,
+    pub(crate) skip_filesystem_check: bool,
+    pub(crate) validate_target_open: bool,
+    pub(crate) expand_short_names: bool,
+    pub(crate) print_name_from_input: bool,
+    pub(crate) inherit_target_times: bool,
+    pub(crate) overwrite: bool,
+    pub(crate) force_replace_directory: bool,
+    pub(crate) lock: bool,
+    pub(crate) impersonate: Option<Arc<OwnedHandle>>,
+    // Only set by `create_with_empty_print_name`.
+    pub(crate) empty_print_name: bool,
+    // Only set by `create_no_nt_prefix`.
+    pub(crate) no_nt_prefix: bool,
+    // Only set by `create_osstr`.
+    pub(crate) raw_target: bool,
+    pub(crate) buffer_strategy: BufferStrategy,
+}
+
+impl CreateOptions {
+    /// Creates a blank new set of options, which creates a junction the same
+    /// way [`create`](crate::create) does.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets extra file attributes, such as `FILE_ATTRIBUTE_HIDDEN` or
+    /// `FILE_ATTRIBUTE_SYSTEM`, to apply to the junction directory once it is
+    /// created.
+    ///
+    /// Explorer-created and many system junctions carry hidden and system
+    /// attributes. Defaults to no extra attributes.
+    pub fn attributes(&mut self, attributes: u32) -> &mut Self {
+        self.attributes = attributes;
+        self
+    }
+
+    /// Sets the owner of the junction directory once it is created.
+    ///
+    /// Only the owner and administrators can then change the junction's
+    /// permissions, and so retarget it. Setting an owner other than the
+    /// current user needs the `SE_RESTORE_NAME` privilege. Defaults to the
+    /// owner Windows assigns, usually the current user.
+    pub fn owner(&mut self, owner: Sid) -> &mut Self {
+        self.owner = Some(owner);
+        self
+    }
+
+    /// Sets the mandatory integrity level of the junction directory once it
+    /// is created.
+    ///
+    /// Processes running at a lower integrity level can't write to the
+    /// junction, and so can't retarget it. Setting a level above the current
+    /// process's needs the `SeRelabelPrivilege` privilege. Defaults to no
+    /// label, which Windows treats as [`IntegrityLevel::Medium`].
+    pub fn integrity_level(&mut self, level: IntegrityLevel) -> &mut Self {
+        self.integrity_level = Some(level);
+        self
+    }
+
+    /// Sets whether to refuse creating the junction beneath a reparse point,
+    /// such as a symbolic link or another junction, rather than following it.
+    ///
+    /// Each parent directory is opened without following reparse points and
+    /// held open until the junction is created, so none of them can be swapped
+    /// for a link in the meantime. Creation fails with `InvalidInput` if one
+    /// of them is a junction or a symbolic link. Volume mount points and other
+    /// reparse points, such as cloud files placeholders, are let through. This
+    /// keeps installers from being redirected to create junctions elsewhere.
+    /// Defaults to `false`.
+    pub fn no_follow_parents(&mut self, no_follow_parents: bool) -> &mut Self {
+        self.no_follow_parents = no_follow_parents;
+        self
+    }
+
+    /// Sets whether to reject targets that would make a junction many tools
+    /// can't follow.
+    ///
+    /// Creation fails with `InvalidInput` if the final component of the target
+    /// starts or ends with a space, ends with a dot, or if the target contains
+    /// control characters. These usually come from copy-paste errors, and are
+    /// otherwise silently stripped or stored as is. Defaults to `false`.
+    pub fn strict_target(&mut self, strict_target: bool) -> &mut Self {
+        self.strict_target = strict_target;
+        self
+    }
+
+    /// Sets whether to expand 8.3 short names in the target, such as
+    /// `PROGRA~1`, to their long forms before storing it.
+    ///
+    /// Short names depend on the order directories were created in, and are
+    /// not generated at all on some volumes, so junctions storing them are
+    /// confusing and fragile. The target must then exist, or creation fails
+    /// with `NotFound`. Defaults to `false`, storing the target as given.
+    pub fn expand_short_names(&mut self, expand_short_names: bool) -> &mut Self {
+        self.expand_short_names = expand_short_names;
+        self
+    }
+
+    /// Sets whether to open the target before creating the junction, and
+    /// store the path it was opened at.
+    ///
+    /// Creation fails with `NotFound` if the target doesn't exist, or with
+    /// `InvalidInput` if it isn't a directory, before anything is created.
+    /// Links along the target are followed, so the junction stores the
+    /// target's canonical path, in the case the file system uses. This is
+    /// stronger than checking the target's metadata, which a junction to a
+    /// missing directory passes. Defaults to `false`.
+    pub fn validate_target_open(&mut self, validate_target_open: bool) -> &mut Self {
+        self.validate_target_open = validate_target_open;
+        self
+    }
+
+    /// Sets whether to store the PrintName as the caller wrote the target,
+    /// only made absolute with `.` and `..` collapsed, while the
+    /// SubstituteName is resolved as usual.
+    ///
+    /// [`expand_short_names`](Self::expand_short_names) and
+    /// [`validate_target_open`](Self::validate_target_open) replace the target
+    /// with its on-disk form, casing included. With this set, tools showing
+    /// the PrintName, such as `dir`, keep the casing the caller chose.
+    /// Defaults to `false`.
+    pub fn print_name_from_input(&mut self, print_name_from_input: bool) -> &mut Self {
+        self.print_name_from_input = print_name_from_input;
+        self
+    }
+
+    /// Sets whether to give the junction the creation and last write times
+    /// of its target.
+    ///
+    /// The times are read from the target, following links, before anything
+    /// is created, so a missing target fails with `NotFound`. They are set on
+    /// the junction after its reparse point is written, which would otherwise
+    /// update its last write time. This keeps packages built from a tree
+    /// with junctions deterministic. Defaults to `false`.
+    pub fn inherit_target_times(&mut self, inherit_target_times: bool) -> &mut Self {
+        self.inherit_target_times = inherit_target_times;
+        self
+    }
+
+    /// Sets how the buffer the reparse data is encoded in, which is about
+    /// 16 KiB, is allocated.
+    ///
+    /// See [`BufferStrategy`]. Defaults to [`BufferStrategy::PerCall`].
+    pub fn buffer_strategy(&mut self, buffer_strategy: BufferStrategy) -> &mut Self {
+        self.buffer_strategy = buffer_strategy;
+        self
+    }
+
+    /// Sets whether [`CreateOptions::ensure`] holds a lock while it checks
+    /// for and creates the junction.
+    ///
+    /// The lock is taken on a file next to the junction, named after it with
+    /// `.lock` appended, so that processes ensuring the same junction at once,
+    /// such as parallel CI jobs, do so one at a time. The lock file is left in
+    /// place for the next caller. Defaults to `false`.
+    pub fn lock(&mut self, lock: bool) -> &mut Self {
+        self.lock = lock;
+        self
+    }
+
+    /// Sets a user token to create the junction as, via
+    /// `ImpersonateLoggedOnUser`.
+    ///
+    /// The calling thread impersonates the user while the junction is created,
+    /// so access checks and the default owner and ACLs of the junction are
+    /// those of that user, and reverts to the process's own token afterwards,
+    /// whether creation succeeded or not. The token, such as one from
+    /// `LogonUserW`, needs `TOKEN_QUERY` and `TOKEN_DUPLICATE` access.
+    /// Impersonating another user at the same or a higher level also needs
+    /// the `SeImpersonatePrivilege` privilege, which services hold by default;
+    /// without it, Windows silently impersonates at identification level and
+    /// creation fails with `PermissionDenied`. Defaults to the process's own
+    /// token.
+    pub fn impersonate(&mut self, token: OwnedHandle) -> &mut Self {
+        self.impersonate = Some(Arc::new(token));
+        self
+    }
+
+    /// Sets whether to check that the junction's volume supports reparse
+    /// points before creating anything.
+    ///
+    /// Junctions need NTFS or ReFS. On file systems such as FAT32 or exFAT,
+    /// creation then fails early with `Unsupported` and a message naming the
+    /// file system, instead of an opaque error once the directory exists.
+    /// Turning it off saves querying the volume for each junction. Defaults
+    /// to `true`.
+    pub fn check_filesystem(&mut self, check_filesystem: bool) -> &mut Self {
+        self.skip_filesystem_check = !check_filesystem;
+        self
+    }
+
+    /// Sets whether to replace whatever is at the junction's path: a junction
+    /// or directory symbolic link, or an empty directory.
+    ///
+    /// It is removed only once the target has been checked, and a directory
+    /// with contents is still refused, with the OS error
+    /// `ERROR_DIR_NOT_EMPTY`, unless
+    /// [`force_replace_directory`](Self::force_replace_directory) is set too.
+    /// Files are never replaced. Defaults to `false`, failing with
+    /// `AlreadyExists` if the path exists.
+    pub fn overwrite(&mut self, overwrite: bool) -> &mut Self {
+        self.overwrite = overwrite;
+        self
+    }
+
+    /// Sets whether [`overwrite`](Self::overwrite) may replace a plain
+    /// directory with contents, **deleting them**.
+    ///
+    /// Without it, a directory holding user data can't be turned into a
+    /// junction by mistake. Junctions inside the directory are removed, not
+    /// followed, and a target inside it is refused with `InvalidInput`.
+    /// Defaults to `false`.
+    pub fn force_replace_directory(&mut self, force_replace_directory: bool) -> &mut Self {
+        self.force_replace_directory = force_replace_directory;
+        self
+    }
+
+    /// Retries opening the junction directory up to `attempts` more times when
+    /// it fails with a sharing violation, waiting `backoff` before the first
+    /// retry and twice as long before each following one.
+    ///
+    /// Antivirus software and indexers can hold a freshly created directory
+    /// for a short while. Defaults to no retries.
+    pub fn with_retry(&mut self, attempts: u32, backoff: Duration) -> &mut Self {
+        self.retry = Retry { attempts, backoff };
+        self
+    }
+
+    /// Creates a junction point from the specified directory to the specified
+    /// target directory with the options in `self`.
+    ///
+    /// See [`create`](crate::create) for more details.
+    pub fn create<P, Q>(&self, target: P, junction: Q) -> io::Result<()>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        internals::create(target.as_ref(), junction.as_ref(), self).map(drop)
+    }
+
+    /// Like [`CreateOptions::create`], but reports the sizes of the reparse
+    /// data written.
+    ///
+    /// See [`create_report`](crate::create_report) for more details.
+    pub fn create_report<P, Q>(&self, target: P, junction: Q) -> io::Result<CreateReport>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        internals::create(target.as_ref(), junction.as_ref(), self)
+    }
+
+    /// Creates a junction point to the specified target directory with the
+    /// options in `self`, unless it already exists.
+    ///
+    /// See [`ensure`](crate::ensure) for more details.
+    pub fn ensure<P, Q>(&self, target: P, junction: Q) -> io::Result<bool>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        internals::ensure(target.as_ref(), junction.as_ref(), self)
+    }
+}
+
+/// How [`CreateOptions`] allocates the buffer it encodes reparse data in,
+/// which has room for the largest reparse data, about 16 KiB.
+///
+/// Reading a junction, such as with [`get_target`](crate::get_target) or
+/// [`exists`](crate::exists), starts from a small buffer grown only as the
+/// data needs, and [`delete`](crate::delete) needs no more than a header, so
+/// neither takes a strategy.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BufferStrategy {
+    /// Allocates a buffer for each junction created, and frees it right
+    /// after.
+    #[default]
+    PerCall,
+    /// Reuses one buffer per thread, kept until the thread exits. Saves an
+    /// allocation for each junction created, for callers creating many of
+    /// them from a few long-lived threads.
+    ThreadLocal,
+}
+
+/// The sizes of the reparse data written when creating a junction, as
+/// returned by [`create_report`](crate::create_report).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CreateReport {
+    /// Length of the SubstituteName, the target with the `\??\` prefix,
+    /// without its null terminator.
+    pub substitute_len_in_bytes: u16,
+    /// Length of the PrintName, the target itself, without its null
+    /// terminator.
+    pub print_name_len_in_bytes: u16,
+    /// Size of the whole reparse data buffer, headers included.
+    pub in_buffer_size: u16,
+}
+
+impl CreateReport {
+    /// Room in the reparse data buffer for both names and their null
+    /// terminators, which they can never exceed.
+    pub const MAX_PATH_BUFFER: u16 = internals::MAX_PATH_BUFFER;
+}
+
+/// A junction's reparse data, encoded once by
+/// [`build_reparse_data`](crate::build_reparse_data) and written as is by
+/// [`apply`](crate::apply) to create any number of junctions to the same
+/// target.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReparseData {
+    pub(crate) bytes: Vec<u8>,
+    pub(crate) report: CreateReport,
+}
+
+impl ReparseData {
+    /// The raw reparse data buffer, which the [`parse`](crate::parse) module
+    /// can decode.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// The sizes of the encoded reparse data, as [`create_report`]
+    /// reports them.
+    ///
+    /// [`create_report`]: crate::create_report
+    pub fn report(&self) -> CreateReport {
+        self.report
+    }
+}
+
+/// A junction point or symbolic link, as returned by
+/// [`read_reparse_point`](crate::read_reparse_point).
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ReparsePoint {
+    /// Either [`ReparseTag::MountPoint`] or [`ReparseTag::Symlink`].
+    pub tag: ReparseTag,
+    /// The stored target, without the `\??\` prefix.
+    pub target: PathBuf,
+    /// Whether the target is relative to the directory holding the reparse
+    /// point. Only symbolic links can be relative.
+    pub relative: bool,
+}
+
+/// What is at a path, as far as junctions go, as returned by
+/// [`status`](crate::status).
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Status {
+    /// Nothing is at the path.
+    Missing,
+    /// A plain file or directory.
+    NotAReparsePoint,
+    /// A junction.
+    Junction {
+        /// The stored target, without the `\??\` prefix.
+        target: PathBuf,
+        /// The stored PrintName, which may be empty.
+        print_name: PathBuf,
+        /// Whether the target exists, following the junction.
+        target_exists: bool,
+        /// Size of the whole reparse data buffer, headers included, out of
+        /// [`parse::MAXIMUM_BUFFER_SIZE`](crate::parse::MAXIMUM_BUFFER_SIZE).
+        buffer_used: u16,
+    },
+    /// A symbolic link, to a file or a directory.
+    Symlink {
+        /// The stored target, without the `\??\` prefix.
+        target: PathBuf,
+        /// Whether the target is relative to the directory holding the link.
+        relative: bool,
+    },
+    /// A reparse point with any other tag, such as a cloud files
+    /// placeholder.
+    OtherReparse(u32),
+}
+
+/// What a junction should look like, as checked by
+/// [`matches_spec`](crate::matches_spec).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JunctionSpec {
+    /// The target the junction should lead to. Relative targets are resolved
+    /// against the current directory, like [`create`](crate::create) does.
+    pub target: PathBuf,
+    /// The PrintName the junction should store, or `None` to accept any.
+    pub print_name: Option<PathBuf>,
+}
+
+/// Options which can be used to configure how a junction is read.
+///
+/// [`exists`] and [`get_target`] are the same as calling
+/// [`ReadOptions::exists`] and [`ReadOptions::get_target`] with the defaults.
+///
+/// [`exists`]: crate::exists
+/// [`get_target`]: crate::get_target
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// use std::time::Duration;
+/// # use std::fs;
+/// use junction::{create, ReadOptions};
+/// fn main() -> io::Result<()> {
+///     let tmpdir = tempfile::tempdir()?;
+///     let target = tmpdir.path().join("target");
+///     let junction = tmpdir.path().join("junction");
+///     # fs::create_dir_all(&target)?;
+///     create(&target, &junction)?;
+///     let target = ReadOptions::new()
+///         .with_retry(3, Duration::from_millis(10))
+///         .get_target(&junction)?;
+///     # assert!(target.ends_with("target"));
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ReadOptions {
+    pub(crate) retry: Retry,
+    pub(crate) no_follow_parents: bool,
+}
+
+impl ReadOptions {
+    /// Creates a blank new set of options, which reads a junction the same
+    /// way [`get_target`](crate::get_target) does.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Retries opening the junction up to `attempts` more times when it fails
+    /// with a sharing violation, like [`CreateOptions::with_retry`].
+    ///
+    /// Defaults to no retries.
+    pub fn with_retry(&mut self, attempts: u32, backoff: Duration) -> &mut Self {
+        self.retry = Retry { attempts, backoff };
+        self
+    }
+
+    /// Sets whether to refuse reading a junction beneath a symbolic link or
+    /// another junction, rather than following it, like
+    /// [`CreateOptions::no_follow_parents`] does when creating one.
+    ///
+    /// Each parent directory is opened without following reparse points and
+    /// held open until the junction is, so the entry read is the one the path
+    /// literally names. Reading fails with `InvalidInput` if one of them is a
+    /// junction or a symbolic link. Volume mount points and other reparse
+    /// points, such as cloud files placeholders, are let through. Defaults to
+    /// `false`.
+    pub fn no_follow_parents(&mut self, no_follow_parents: bool) -> &mut Self {
+        self.no_follow_parents = no_follow_parents;
+        self
+    }
+
+    /// Determines whether the specified path exists and refers to a junction
+    /// point, with the options in `self`.
+    ///
+    /// See [`exists`](crate::exists) for more details.
+    pub fn exists<P: AsRef<Path>>(&self, junction: P) -> io::Result<bool> {
+        internals::exists(junction.as_ref(), self)
+    }
+
+    /// Gets the target of the specified junction point with the options in
+    /// `self`.
+    ///
+    /// See [`get_target`](crate::get_target) for more details.
+    pub fn get_target<P: AsRef<Path>>(&self, junction: P) -> io::Result<PathBuf> {
+        internals::get_target(junction.as_ref(), self)
+    }
+
+    /// Reads the junction point or symbolic link at `path` with the options in
+    /// `self`.
+    ///
+    /// See [`read_reparse_point`](crate::read_reparse_point) for more details.
+    pub fn read_reparse_point<P: AsRef<Path>>(&self, path: P) -> io::Result<ReparsePoint> {
+        internals::read_reparse_point(path.as_ref(), self)
+    }
+
+    /// Gets the target of the specified junction point with the options in
+    /// `self`, mapping a volume GUID target to the path its volume is mounted
+    /// at.
+    ///
+    /// See [`get_target_resolve_volume`](crate::get_target_resolve_volume)
+    /// for more details.
+    pub fn get_target_resolve_volume<P: AsRef<Path>>(&self, junction: P) -> io::Result<PathBuf> {
+        internals::get_target_resolve_volume(junction.as_ref(), self)
+    }
+
+    /// Gets the target of the specified junction point with the options in
+    /// `self`, in drive-letter form whenever there is one.
+    ///
+    /// See [`get_target_win32`](crate::get_target_win32) for more details.
+    pub fn get_target_win32<P: AsRef<Path>>(&self, junction: P) -> io::Result<PathBuf> {
+        internals::get_target_win32(junction.as_ref(), self)
+    }
+}
+
+/// Options which can be used to configure how a junction is deleted.
+///
+/// [`delete`] is the same as calling [`DeleteOptions::delete`] with the
+/// defaults.
+///
+/// [`delete`]: crate::delete
+///
+/// # Example
+///
+/// ```rust
+/// use std::io;
+/// # use std::fs;
+/// use junction::{create, DeleteOptions};
+/// const DELETE: u32 = 0x0001_0000;
+/// const FILE_WRITE_ATTRIBUTES: u32 = 0x0100;
+/// fn main() -> io::Result<()> {
+///     let tmpdir = tempfile::tempdir()?;
+///     let target = tmpdir.path().join("target");
+///     let junction = tmpdir.path().join("junction");
+///     # fs::create_dir_all(&target)?;
+///     create(&target, &junction)?;
+///     DeleteOptions::new().access(DELETE | FILE_WRITE_ATTRIBUTES).delete(&junction)
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct DeleteOptions {
+    pub(crate) access: u32,
+    pub(crate) remove_dir: bool,
+    pub(crate) retry: Retry,
+}
+
+impl Default for DeleteOptions {
+    fn default() -> Self {
+        Self {
+            access: internals::DELETE_ACCESS,
+            remove_dir: false,
+            retry: Retry::default(),
+        }
+    }
+}
+
+impl DeleteOptions {
+    /// Creates a blank new set of options, which deletes a junction the same
+    /// way [`delete`](crate::delete) does.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the access rights, such as `DELETE | FILE_WRITE_ATTRIBUTES`,
+    /// requested when opening the junction.
+    ///
+    /// Removing the reparse point needs `FILE_WRITE_ATTRIBUTES` (or
+    /// `FILE_WRITE_DATA`) access. Restrictive ACLs may deny the
+    /// `GENERIC_READ | GENERIC_WRITE` requested by default while still
+    /// granting just that, or `DELETE` for removing the directory afterwards.
+    pub fn access(&mut self, access: u32) -> &mut Self {
+        self.access = access;
+        self
+    }
+
+    /// Sets whether the directory left behind by the junction is removed as
+    /// well, once its reparse point is deleted.
+    ///
+    /// The target directory is never touched.
+    pub fn remove_dir(&mut self, remove_dir: bool) -> &mut Self {
+        self.remove_dir = remove_dir;
+        self
+    }
+
+    /// Retries deleting the reparse point, and removing the directory if
+    /// asked to, up to `attempts` more times when access is denied or on a
+    /// sharing violation, backing off like [`CreateOptions::with_retry`].
+    ///
+    /// Antivirus scanners can hold a just created junction open for a short
+    /// while, which makes deleting it fail transiently. Defaults to no retries.
+    pub fn with_retry(&mut self, attempts: u32, backoff: Duration) -> &mut Self {
+        self.retry = Retry { attempts, backoff };
+        self
+    }
+
+    /// Deletes the reparse point of the specified junction with the options in
+    /// `self`.
+    ///
+    /// See [`delete`](crate::delete) for more details.
+    pub fn delete<P: AsRef<Path>>(&self, junction: P) -> io::Result<()> {
+        internals::delete(junction.as_ref(), self)
+    }
+
+    /// Deletes the reparse points of all `junctions` with the options in
+    /// `self`, returning the result for each path.
+    ///
+    /// See [`delete_many`](crate::delete_many) for more details.
+    pub fn delete_many<I>(&self, junctions: I) -> Vec<(PathBuf, io::Result<()>)>
+    where
+        I: IntoIterator<Item = PathBuf>,
+    {
+        internals::delete_many(junctions, self)
+    }
+}
+
+/// Options which can be used to configure how a directory is converted into
+/// a junction.
+///
+/// [`convert_to_junction`] is the same as calling
+/// [`ConvertOptions::convert_to_junction`] with the defaults.
+///
+/// [`convert_to_junction`]: crate::convert_to_junction
+#[derive(Clone, Debug, Default)]
+pub struct ConvertOptions {
+    pub(crate) merge: bool,
+}
+
+impl ConvertOptions {
+    /// Creates a blank new set of options, which converts a directory the
+    /// same way [`convert_to_junction`](crate::convert_to_junction) does.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether to merge the directory's contents into an existing store
+    /// entry, rather than failing with `AlreadyExists`.
+    ///
+    /// Subdirectories present on both sides are merged too, but a file or
+    /// other entry already in the store is never overwritten. Defaults to
+    /// `false`.
+    pub fn merge(&mut self, merge: bool) -> &mut Self {
+        self.merge = merge;
+        self
+    }
+
+    /// Moves the contents of `dir` into the store and replaces it with a
+    /// junction, with the options in `self`.
+    ///
+    /// See [`convert_to_junction`](crate::convert_to_junction) for more details.
+    pub fn convert_to_junction<P, Q>(&self, dir: P, store_root: Q) -> io::Result<()>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        convert::convert_to_junction(dir.as_ref(), store_root.as_ref(), self)
+    }
+}
+
+/// How often to retry opening a reparse point on sharing violations, or on
+/// transient errors for [`DeleteOptions`].
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Retry {
+    /// Number of retries after the first attempt.
+    pub(crate) attempts: u32,
+    /// Delay before the first retry, doubled after each one.
+    pub(crate) backoff: Duration,
+}
@@ -0,0 +1,842 @@
+//! Decoding of reparse data buffers, as returned by `FSCTL_GET_REPARSE_POINT`,
+//! that doesn't touch the filesystem.
+//!
+//! Every offset and length in the buffer is bounds-checked, so arbitrary
+//! bytes decode to an error rather than an out-of-bounds read.
+//!
+//! The decoding only needs `core` and is available on every target, so
+//! reparse data captured on Windows can be decoded elsewhere too. Names are
+//! borrowed from the buffer rather than copied.
+
+use core::fmt;
+
+/// Size of the `ReparseTag`, `ReparseDataLength` and `Reserved` fields that
+/// start every reparse data buffer.
+pub const HEADER_SIZE: usize = 8;
+/// Size of the offset and length fields that start a mount point's data.
+pub const MOUNT_POINT_HEADER_SIZE: usize = 8;
+/// Size of the offset, length and flags fields that start a symbolic link's data.
+pub const SYMLINK_HEADER_SIZE: usize = 12;
+/// Mirrors `SYMLINK_FLAG_RELATIVE`, set when a symbolic link's substitute
+/// name is relative to the directory holding the link.
+pub const SYMLINK_FLAG_RELATIVE: u32 = 0x1;
+/// Size of the version field that starts a WSL symbolic link's data.
+pub const LX_SYMLINK_HEADER_SIZE: usize = 4;
+/// Mirrors `MAXIMUM_REPARSE_DATA_BUFFER_SIZE`, the most data a reparse point can hold.
+pub const MAXIMUM_BUFFER_SIZE: usize = 16 * 1024;
+/// Mirrors `IO_REPARSE_TAG_MOUNT_POINT`, the tag of junctions.
+pub const IO_REPARSE_TAG_MOUNT_POINT: u32 = 0xA000_0003;
+/// Mirrors `IO_REPARSE_TAG_SYMLINK`, the tag of symbolic links.
+pub const IO_REPARSE_TAG_SYMLINK: u32 = 0xA000_000C;
+/// Mirrors `IO_REPARSE_TAG_LX_SYMLINK`, the tag of symbolic links created
+/// from inside WSL.
+pub const IO_REPARSE_TAG_LX_SYMLINK: u32 = 0xA000_001D;
+/// The `\??\` prefix that marks an NT path in a substitute name.
+pub const NT_PREFIX: [u16; 4] = [b'\\' as u16, b'?' as u16, b'?' as u16, b'\\' as u16];
+
+/// A UTF-16 name borrowed from a reparse data buffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Name<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Name<'a> {
+    /// The name as little-endian UTF-16 bytes, without a terminating null.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// Number of UTF-16 code units in the name.
+    pub fn len(&self) -> usize {
+        self.bytes.len() / 2
+    }
+
+    /// Whether the name has no code units.
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// The UTF-16 code units of the name.
+    pub fn units(&self) -> impl Iterator<Item = u16> + 'a {
+        self.bytes.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]]))
+    }
+}
+
+/// The names stored in a mount point (junction) reparse data buffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MountPoint<'a> {
+    /// The NT path the junction resolves to, usually starting with `\??\`.
+    pub substitute_name: Name<'a>,
+    /// The Win32 path shown to users.
+    pub print_name: Name<'a>,
+}
+
+/// The names stored in a symbolic link reparse data buffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Symlink<'a> {
+    /// The path the link resolves to, either an NT path starting with `\??\`
+    /// or a path relative to the directory holding the link.
+    pub substitute_name: Name<'a>,
+    /// The path shown to users.
+    pub print_name: Name<'a>,
+    /// Whether [`SYMLINK_FLAG_RELATIVE`] is set, making the substitute name
+    /// relative.
+    pub relative: bool,
+}
+
+/// Why a reparse data buffer couldn't be decoded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The buffer is shorter than its header, or than the data its header
+    /// declares.
+    Truncated {
+        /// Bytes available.
+        len: usize,
+        /// Bytes needed.
+        required: usize,
+    },
+    /// The header declares more data than any reparse point can hold.
+    TooLarge {
+        /// Bytes needed.
+        required: usize,
+    },
+    /// The reparse point is not a mount point.
+    NotMountPoint {
+        /// The tag it has instead.
+        tag: u32,
+    },
+    /// The mount point data is shorter than its own header.
+    MountPointTruncated {
+        /// Bytes of mount point data.
+        len: usize,
+    },
+    /// The reparse point is not a symbolic link.
+    NotSymlink {
+        /// The tag it has instead.
+        tag: u32,
+    },
+    /// The symbolic link data is shorter than its own header.
+    SymlinkTruncated {
+        /// Bytes of symbolic link data.
+        len: usize,
+    },
+    /// The reparse point is not a WSL symbolic link.
+    NotLxSymlink {
+        /// The tag it has instead.
+        tag: u32,
+    },
+    /// The WSL symbolic link data is shorter than its version field.
+    LxSymlinkTruncated {
+        /// Bytes of WSL symbolic link data.
+        len: usize,
+    },
+    /// The target of a WSL symbolic link is not valid UTF-8.
+    InvalidUtf8 {
+        /// Offset of the first invalid byte in the target.
+        valid_up_to: usize,
+    },
+    /// The offset or length of a name would split a UTF-16 code unit.
+    Misaligned {
+        /// Which name, `SubstituteName` or `PrintName`.
+        name: &'static str,
+    },
+    /// A name lies outside the path buffer.
+    OutOfBounds {
+        /// Which name, `SubstituteName` or `PrintName`.
+        name: &'static str,
+        /// Offset of the name in bytes.
+        offset: usize,
+        /// Length of the name in bytes.
+        len: usize,
+        /// Size of the path buffer in bytes.
+        path_buffer_len: usize,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Truncated { len, required } => {
+                write!(f, "reparse data needs {} bytes but only {} were read", required, len)
+            }
+            Self::TooLarge { required } => write!(
+                f,
+                "reparse data needs {} bytes, more than the maximum of {}",
+                required, MAXIMUM_BUFFER_SIZE
+            ),
+            Self::NotMountPoint { .. } => f.write_str("not a reparse tag mount point"),
+            Self::MountPointTruncated { len } => write!(
+                f,
+                "mount point data is truncated: {} bytes is smaller than its header",
+                len
+            ),
+            Self::NotSymlink { .. } => f.write_str("not a reparse tag symlink"),
+            Self::SymlinkTruncated { len } => {
+                write!(f, "symlink data is truncated: {} bytes is smaller than its header", len)
+            }
+            Self::NotLxSymlink { .. } => f.write_str("not a reparse tag WSL symlink"),
+            Self::LxSymlinkTruncated { len } => write!(
+                f,
+                "WSL symlink data is truncated: {} bytes is smaller than its header",
+                len
+            ),
+            Self::InvalidUtf8 { valid_up_to } => {
+                write!(f, "WSL symlink target is not UTF-8 past byte {}", valid_up_to)
+            }
+            Self::Misaligned { name } => write!(f, "{} is not aligned to UTF-16 code units", name),
+            Self::OutOfBounds {
+                name,
+                offset,
+                len,
+                path_buffer_len,
+            } => write!(
+                f,
+                "{} at offset {} with length {} is outside the {} byte path buffer",
+                name, offset, len, path_buffer_len
+            ),
+        }
+    }
+}
+
+// The only parts of this module that need `std`.
+impl std::error::Error for Error {}
+
+impl From<Error> for std::io::Error {
+    /// Maps [`Error::NotMountPoint`], [`Error::NotSymlink`] and
+    /// [`Error::NotLxSymlink`] to `Other` and everything else to
+    /// `InvalidData`, as the filesystem functions of this crate report them.
+    fn from(err: Error) -> Self {
+        let kind = match err {
+            Error::NotMountPoint { .. } | Error::NotSymlink { .. } | Error::NotLxSymlink { .. } => {
+                std::io::ErrorKind::Other
+            }
+            _ => std::io::ErrorKind::InvalidData,
+        };
+        std::io::Error::new(kind, err)
+    }
+}
+
+/// Decodes the mount point reparse data buffer at the start of `buf`.
+///
+/// Errors with [`Error::NotMountPoint`] for other kinds of reparse points,
+/// and with one of the other variants if the buffer is malformed, for example
+/// when a name's offset or length points outside the buffer.
+pub fn parse_reparse_buffer(buf: &[u8]) -> Result<MountPoint<'_>, Error> {
+    let len = buffer_len(buf)?;
+    let tag = reparse_tag(buf)?;
+    if tag != IO_REPARSE_TAG_MOUNT_POINT {
+        return Err(Error::NotMountPoint { tag });
+    }
+    let data = &buf[HEADER_SIZE..len];
+    if data.len() < MOUNT_POINT_HEADER_SIZE {
+        return Err(Error::MountPointTruncated { len: data.len() });
+    }
+    let (substitute_name, print_name) = names(data, MOUNT_POINT_HEADER_SIZE)?;
+    Ok(MountPoint {
+        substitute_name,
+        print_name,
+    })
+}
+
+/// Decodes the symbolic link reparse data buffer at the start of `buf`.
+///
+/// Errors with [`Error::NotSymlink`] for other kinds of reparse points, and
+/// like [`parse_reparse_buffer`] if the buffer is malformed.
+pub fn parse_symlink_buffer(buf: &[u8]) -> Result<Symlink<'_>, Error> {
+    let len = buffer_len(buf)?;
+    let tag = reparse_tag(buf)?;
+    if tag != IO_REPARSE_TAG_SYMLINK {
+        return Err(Error::NotSymlink { tag });
+    }
+    let data = &buf[HEADER_SIZE..len];
+    if data.len() < SYMLINK_HEADER_SIZE {
+        return Err(Error::SymlinkTruncated { len: data.len() });
+    }
+    let flags = u32::from_le_bytes([data[8], data[9], data[10], data[11]]);
+    let (substitute_name, print_name) = names(data, SYMLINK_HEADER_SIZE)?;
+    Ok(Symlink {
+        substitute_name,
+        print_name,
+        relative: flags & SYMLINK_FLAG_RELATIVE != 0,
+    })
+}
+
+/// Decodes the target of the WSL symbolic link reparse data buffer at the
+/// start of `buf`.
+///
+/// Unlike other reparse points, WSL symbolic links store their target as
+/// UTF-8, after a 4 byte version field, and without a null terminator.
+/// Errors with [`Error::NotLxSymlink`] for other kinds of reparse points, and
+/// with [`Error::InvalidUtf8`] for targets that aren't UTF-8.
+pub fn parse_lx_symlink_buffer(buf: &[u8]) -> Result<&str, Error> {
+    let len = buffer_len(buf)?;
+    let tag = reparse_tag(buf)?;
+    if tag != IO_REPARSE_TAG_LX_SYMLINK {
+        return Err(Error::NotLxSymlink { tag });
+    }
+    let data = &buf[HEADER_SIZE..len];
+    if data.len() < LX_SYMLINK_HEADER_SIZE {
+        return Err(Error::LxSymlinkTruncated { len: data.len() });
+    }
+    core::str::from_utf8(&data[LX_SYMLINK_HEADER_SIZE..]).map_err(|e| Error::InvalidUtf8 {
+        valid_up_to: e.valid_up_to(),
+    })
+}
+
+/// Decodes the substitute and print names of `data`, whose path buffer starts
+/// at `header_size`. Mount points and symbolic links share the layout of the
+/// offset and length fields.
+fn names(data: &[u8], header_size: usize) -> Result<(Name<'_>, Name<'_>), Error> {
+    let field = |i: usize| u16::from_le_bytes([data[i], data[i + 1]]) as usize;
+    let path_buffer = &data[header_size..];
+    Ok((
+        name(path_buffer, field(0), field(2), "SubstituteName")?,
+        name(path_buffer, field(4), field(6), "PrintName")?,
+    ))
+}
+
+fn name<'a>(path_buffer: &'a [u8], offset: usize, len: usize, which: &'static str) -> Result<Name<'a>, Error> {
+    if offset % 2 != 0 || len % 2 != 0 {
+        return Err(Error::Misaligned { name: which });
+    }
+    match path_buffer.get(offset..offset + len) {
+        Some(bytes) => Ok(Name { bytes }),
+        None => Err(Error::OutOfBounds {
+            name: which,
+            offset,
+            len,
+            path_buffer_len: path_buffer.len(),
+        }),
+    }
+}
+
+/// Returns the tag of the reparse data buffer at the start of `buf`.
+///
+/// Errors like [`buffer_len`] if the buffer is malformed.
+pub fn reparse_tag(buf: &[u8]) -> Result<u32, Error> {
+    buffer_len(buf)?;
+    Ok(u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]))
+}
+
+/// Returns the `ReparseDataLength` of the reparse data buffer at the start of
+/// `buf`: the size of the data after its header.
+///
+/// Errors like [`buffer_len`] if the buffer is malformed.
+pub fn data_len(buf: &[u8]) -> Result<u16, Error> {
+    buffer_len(buf)?;
+    Ok(u16::from_le_bytes([buf[4], buf[5]]))
+}
+
+/// Returns how much of [`MAXIMUM_BUFFER_SIZE`] a reparse data buffer with
+/// `data_len` bytes of data takes up, header included, as a percentage.
+///
+/// `create` refuses targets that would take up more than 100%.
+pub fn utilization(data_len: u16) -> f64 {
+    (HEADER_SIZE + data_len as usize) as f64 * 100.0 / MAXIMUM_BUFFER_SIZE as f64
+}
+
+/// Returns the total size, header included, that the reparse data buffer at
+/// the start of `buf` declares for itself.
+///
+/// Errors when `buf` is too short to hold what the header declares, or when
+/// the declared size is beyond what any reparse point can hold. Either way the
+/// error reports the size actually required.
+pub fn buffer_len(buf: &[u8]) -> Result<usize, Error> {
+    if buf.len() < HEADER_SIZE {
+        return Err(Error::Truncated {
+            len: buf.len(),
+            required: HEADER_SIZE,
+        });
+    }
+    let data_len = u16::from_le_bytes([buf[4], buf[5]]) as usize;
+    let required = HEADER_SIZE + data_len;
+    if required > MAXIMUM_BUFFER_SIZE {
+        return Err(Error::TooLarge { required });
+    }
+    if required > buf.len() {
+        return Err(Error::Truncated {
+            len: buf.len(),
+            required,
+        });
+    }
+    Ok(required)
+}
+
+/// A field of a reparse data buffer, as reported by [`diff_reparse_buffers`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Field {
+    /// `ReparseTag`.
+    Tag,
+    /// `ReparseDataLength`.
+    DataLength,
+    /// `Reserved`.
+    Reserved,
+    /// `SubstituteNameOffset` of a mount point.
+    SubstituteNameOffset,
+    /// `SubstituteNameLength` of a mount point.
+    SubstituteNameLength,
+    /// `PrintNameOffset` of a mount point.
+    PrintNameOffset,
+    /// `PrintNameLength` of a mount point.
+    PrintNameLength,
+    /// `PathBuffer` of a mount point, holding both names.
+    PathBuffer,
+    /// The data of any other kind of reparse point.
+    Data,
+    /// Past the end of the shorter buffer.
+    End,
+}
+
+/// Where two reparse data buffers first differ.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReparseDiff {
+    /// The field holding the first differing byte.
+    pub field: Field,
+    /// Offset of the first differing byte from the start of the buffers.
+    pub offset: usize,
+}
+
+/// Compares two reparse data buffers byte by byte, returning where they first
+/// differ, or `None` if they are identical.
+///
+/// Fields past the header are only named for mount points, and only if both
+/// buffers are tagged as one. A buffer that is a prefix of the other differs
+/// at [`Field::End`]. Malformed buffers are compared as they are.
+pub fn diff_reparse_buffers(a: &[u8], b: &[u8]) -> Option<ReparseDiff> {
+    let offset = match a.iter().zip(b).position(|(a, b)| a != b) {
+        Some(offset) => offset,
+        None if a.len() == b.len() => return None,
+        None => {
+            return Some(ReparseDiff {
+                field: Field::End,
+                offset: a.len().min(b.len()),
+            })
+        }
+    };
+    // The tags match if the first difference is past them.
+    let mount_point = offset >= 4 && reparse_tag(a) == Ok(IO_REPARSE_TAG_MOUNT_POINT);
+    let field = match offset {
+        0..=3 => Field::Tag,
+        4..=5 => Field::DataLength,
+        6..=7 => Field::Reserved,
+        _ if !mount_point => Field::Data,
+        8..=9 => Field::SubstituteNameOffset,
+        10..=11 => Field::SubstituteNameLength,
+        12..=13 => Field::PrintNameOffset,
+        14..=15 => Field::PrintNameLength,
+        _ => Field::PathBuffer,
+    };
+    Some(ReparseDiff { field, offset })
+}
+
+/// Strips the [`NT_PREFIX`] from a substitute name, if it has one.
+pub fn strip_nt_prefix(name: &[u16]) -> &[u16] {
+    name.strip_prefix(&NT_PREFIX[..]).unwrap_or(name)
+}
+
+/// Splits a path on a volume GUID, such as `\??\Volume{GUID}\dir`, into
+/// the `Volume{GUID}` name and the rest of the path after it.
+///
+/// The path may start with the [`NT_PREFIX`], the `\\?\` verbatim prefix, or
+/// no prefix at all, as returned by `get_target`. The rest is empty when the
+/// path names the root of the volume, and has no leading separator otherwise.
+/// Returns `None` for paths not on a volume GUID.
+pub fn split_volume_guid(path: &[u16]) -> Option<(&[u16], &[u16])> {
+    const VERBATIM_PREFIX: [u16; 4] = [b'\\' as u16, b'\\' as u16, b'?' as u16, b'\\' as u16];
+    const VOLUME: [u16; 7] = [
+        b'V' as u16,
+        b'o' as u16,
+        b'l' as u16,
+        b'u' as u16,
+        b'm' as u16,
+        b'e' as u16,
+        b'{' as u16,
+    ];
+    // `Volume{` + `xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx` + `}`
+    const LEN: usize = VOLUME.len() + 36 + 1;
+    let path = path
+        .strip_prefix(&NT_PREFIX[..])
+        .or_else(|| path.strip_prefix(&VERBATIM_PREFIX[..]))
+        .unwrap_or(path);
+    let lower = |unit: u16| u8::try_from(unit).map_or(unit, |b| u16::from(b.to_ascii_lowercase()));
+    if path.len() < LEN || !path.iter().zip(&VOLUME).all(|(&a, &b)| lower(a) == lower(b)) {
+        return None;
+    }
+    let guid = &path[VOLUME.len()..LEN - 1];
+    let is_guid = guid.iter().enumerate().all(|(i, &unit)| match i {
+        8 | 13 | 18 | 23 => unit == u16::from(b'-'),
+        _ => u8::try_from(unit).is_ok_and(|b| b.is_ascii_hexdigit()),
+    });
+    if !is_guid || path[LEN - 1] != u16::from(b'}') {
+        return None;
+    }
+    match &path[LEN..] {
+        [] => Some((&path[..LEN], &[])),
+        [sep, rest @ ..] if *sep == u16::from(b'\\') => Some((&path[..LEN], rest)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::io;
+
+    use super::*;
+
+    fn header(tag: u32, data_len: u16) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&tag.to_le_bytes());
+        buf.extend_from_slice(&data_len.to_le_bytes());
+        buf.extend_from_slice(&0u16.to_le_bytes());
+        buf
+    }
+
+    pub(crate) fn mount_point(substitute_name: &str, print_name: &str) -> Vec<u8> {
+        let sub: Vec<u16> = substitute_name.encode_utf16().collect();
+        let print: Vec<u16> = print_name.encode_utf16().collect();
+        let sub_len = (sub.len() * 2) as u16;
+        let print_len = (print.len() * 2) as u16;
+        let data_len = MOUNT_POINT_HEADER_SIZE as u16 + sub_len + 2 + print_len + 2;
+        let mut buf = header(IO_REPARSE_TAG_MOUNT_POINT, data_len);
+        for field in [0, sub_len, sub_len + 2, print_len] {
+            buf.extend_from_slice(&field.to_le_bytes());
+        }
+        for unit in sub.iter().chain(&[0]).chain(&print).chain(&[0]) {
+            buf.extend_from_slice(&unit.to_le_bytes());
+        }
+        buf
+    }
+
+    fn symlink(substitute_name: &str, print_name: &str, flags: u32) -> Vec<u8> {
+        let sub: Vec<u16> = substitute_name.encode_utf16().collect();
+        let print: Vec<u16> = print_name.encode_utf16().collect();
+        let sub_len = (sub.len() * 2) as u16;
+        let print_len = (print.len() * 2) as u16;
+        let data_len = SYMLINK_HEADER_SIZE as u16 + sub_len + print_len;
+        let mut buf = header(IO_REPARSE_TAG_SYMLINK, data_len);
+        // Symbolic links usually store the print name first, unterminated.
+        for field in [print_len, sub_len, 0, print_len] {
+            buf.extend_from_slice(&field.to_le_bytes());
+        }
+        buf.extend_from_slice(&flags.to_le_bytes());
+        for unit in print.iter().chain(&sub) {
+            buf.extend_from_slice(&unit.to_le_bytes());
+        }
+        buf
+    }
+
+    #[test]
+    fn parse_symlink() {
+        let buf = symlink(r"..\target", r"..\target", SYMLINK_FLAG_RELATIVE);
+        let link = parse_symlink_buffer(&buf).unwrap();
+        assert!(link.relative);
+        assert_eq!(
+            String::from_utf16(&link.substitute_name.units().collect::<Vec<_>>()).unwrap(),
+            r"..\target"
+        );
+        assert_eq!(link.print_name, link.substitute_name);
+
+        let buf = symlink(r"\??\C:\target", r"C:\target", 0);
+        let link = parse_symlink_buffer(&buf).unwrap();
+        assert!(!link.relative);
+        assert_eq!(link.print_name.len(), 9);
+
+        let err = parse_symlink_buffer(&mount_point(r"\??\C:\target", r"C:\target")).unwrap_err();
+        assert_eq!(
+            err,
+            Error::NotSymlink {
+                tag: IO_REPARSE_TAG_MOUNT_POINT
+            }
+        );
+        assert_eq!(io::Error::from(err).kind(), io::ErrorKind::Other);
+        let err = parse_reparse_buffer(&buf).unwrap_err();
+        assert_eq!(
+            err,
+            Error::NotMountPoint {
+                tag: IO_REPARSE_TAG_SYMLINK
+            }
+        );
+
+        // Room for the offsets and lengths of a mount point, but not the flags.
+        let mut buf = header(IO_REPARSE_TAG_SYMLINK, 8);
+        buf.extend_from_slice(&[0; 8]);
+        let err = parse_symlink_buffer(&buf).unwrap_err();
+        assert_eq!(err, Error::SymlinkTruncated { len: 8 });
+        assert_eq!(io::Error::from(err).kind(), io::ErrorKind::InvalidData);
+    }
+
+    fn lx_symlink(target: &[u8]) -> Vec<u8> {
+        let mut buf = header(
+            IO_REPARSE_TAG_LX_SYMLINK,
+            (LX_SYMLINK_HEADER_SIZE + target.len()) as u16,
+        );
+        buf.extend_from_slice(&2u32.to_le_bytes());
+        buf.extend_from_slice(target);
+        buf
+    }
+
+    #[test]
+    fn parse_lx_symlink() {
+        let buf = lx_symlink("../target/ünïcode".as_bytes());
+        assert_eq!(parse_lx_symlink_buffer(&buf), Ok("../target/ünïcode"));
+        assert_eq!(parse_lx_symlink_buffer(&lx_symlink(b"")), Ok(""));
+
+        let err = parse_lx_symlink_buffer(&lx_symlink(b"target\xff")).unwrap_err();
+        assert_eq!(err, Error::InvalidUtf8 { valid_up_to: 6 });
+        assert_eq!(io::Error::from(err).kind(), io::ErrorKind::InvalidData);
+
+        let err = parse_lx_symlink_buffer(&mount_point(r"\??\C:\target", r"C:\target")).unwrap_err();
+        assert_eq!(
+            err,
+            Error::NotLxSymlink {
+                tag: IO_REPARSE_TAG_MOUNT_POINT
+            }
+        );
+        assert_eq!(io::Error::from(err).kind(), io::ErrorKind::Other);
+
+        let mut buf = header(IO_REPARSE_TAG_LX_SYMLINK, 2);
+        buf.extend_from_slice(&[2, 0]);
+        assert_eq!(parse_lx_symlink_buffer(&buf), Err(Error::LxSymlinkTruncated { len: 2 }));
+    }
+
+    #[test]
+    fn parse_mount_point() {
+        let buf = mount_point(r"\??\C:\target", r"C:\target");
+        let mp = parse_reparse_buffer(&buf).unwrap();
+        assert_eq!(
+            String::from_utf16(&mp.substitute_name.units().collect::<Vec<_>>()).unwrap(),
+            r"\??\C:\target"
+        );
+        assert_eq!(
+            String::from_utf16(&mp.print_name.units().collect::<Vec<_>>()).unwrap(),
+            r"C:\target"
+        );
+        assert_eq!(mp.print_name.len(), 9);
+    }
+
+    #[test]
+    fn parse_mount_point_astral() {
+        // U+1F980 is a surrogate pair, so two units long.
+        let buf = mount_point("\\??\\C:\\\u{1F980}", "C:\\\u{1F980}");
+        let mp = parse_reparse_buffer(&buf).unwrap();
+        assert_eq!(mp.print_name.len(), 5);
+        assert_eq!(
+            String::from_utf16(&mp.print_name.units().collect::<Vec<_>>()).unwrap(),
+            "C:\\\u{1F980}"
+        );
+    }
+
+    #[test]
+    fn parse_name_out_of_bounds() {
+        let mut buf = mount_point(r"\??\C:\target", r"C:\target");
+        // Push PrintNameOffset past the end of the path buffer.
+        buf[HEADER_SIZE + 4..HEADER_SIZE + 6].copy_from_slice(&0x1000u16.to_le_bytes());
+        let err = parse_reparse_buffer(&buf).unwrap_err();
+        assert!(matches!(err, Error::OutOfBounds { name: "PrintName", .. }), "{:?}", err);
+        assert_eq!(io::Error::from(err).kind(), io::ErrorKind::InvalidData);
+
+        let mut buf = mount_point(r"\??\C:\target", r"C:\target");
+        // Odd lengths would split a code unit.
+        buf[HEADER_SIZE + 2..HEADER_SIZE + 4].copy_from_slice(&3u16.to_le_bytes());
+        let err = parse_reparse_buffer(&buf).unwrap_err();
+        assert_eq!(err, Error::Misaligned { name: "SubstituteName" });
+        assert_eq!(io::Error::from(err).kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn parse_not_mount_point() {
+        let mut buf = mount_point(r"\??\C:\target", r"C:\target");
+        buf[..4].copy_from_slice(&0xA000_000Cu32.to_le_bytes());
+        let err = parse_reparse_buffer(&buf).unwrap_err();
+        assert_eq!(err, Error::NotMountPoint { tag: 0xA000_000C });
+        assert_eq!(io::Error::from(err).kind(), io::ErrorKind::Other);
+        assert_eq!(reparse_tag(&buf), Ok(0xA000_000C));
+    }
+
+    #[test]
+    fn parse_dedup() {
+        // Deduplicated files carry opaque data pointing into the chunk store.
+        let mut buf = header(crate::tags::DEDUP, 0x50);
+        buf.extend((0..0x50).map(|i| i as u8));
+        let tag = reparse_tag(&buf).unwrap();
+        assert_eq!(
+            crate::tags::ReparseTag::from(tag),
+            crate::tags::ReparseTag::Other(crate::tags::DEDUP)
+        );
+        let err = parse_reparse_buffer(&buf).unwrap_err();
+        assert_eq!(
+            err,
+            Error::NotMountPoint {
+                tag: crate::tags::DEDUP
+            }
+        );
+        // Not a junction, rather than malformed.
+        assert_eq!(io::Error::from(err).kind(), io::ErrorKind::Other);
+    }
+
+    #[test]
+    fn parse_cloud_placeholder() {
+        // Placeholders carry opaque provider data, here under IO_REPARSE_TAG_CLOUD_3.
+        let cloud = crate::tags::CLOUD | 0x3000;
+        let mut buf = header(cloud, 0x40);
+        buf.extend((0..0x40).map(|i| i as u8));
+        let tag = reparse_tag(&buf).unwrap();
+        assert!(crate::tags::is_cloud(tag));
+        assert_eq!(
+            crate::tags::ReparseTag::from(tag),
+            crate::tags::ReparseTag::Other(cloud)
+        );
+        let err = parse_reparse_buffer(&buf).unwrap_err();
+        assert_eq!(err, Error::NotMountPoint { tag: cloud });
+        assert_eq!(io::Error::from(err).kind(), io::ErrorKind::Other);
+    }
+
+    #[test]
+    fn parse_projfs_placeholder() {
+        // Placeholders carry opaque provider data, including a content ID.
+        let mut buf = header(crate::tags::PROJFS, 0x30);
+        buf.extend((0..0x30).map(|i| i as u8));
+        let tag = reparse_tag(&buf).unwrap();
+        assert!(!crate::tags::is_surrogate(tag) && crate::tags::is_directory_reparse(tag));
+        assert_eq!(
+            crate::tags::ReparseTag::from(tag),
+            crate::tags::ReparseTag::Other(crate::tags::PROJFS)
+        );
+        let err = parse_reparse_buffer(&buf).unwrap_err();
+        assert_eq!(
+            err,
+            Error::NotMountPoint {
+                tag: crate::tags::PROJFS
+            }
+        );
+        assert_eq!(io::Error::from(err).kind(), io::ErrorKind::Other);
+    }
+
+    #[test]
+    fn buffer_len_matches_header() {
+        let mut buf = header(0xA000_0003, 4);
+        buf.extend_from_slice(&[0; 4]);
+        assert_eq!(buffer_len(&buf).unwrap(), HEADER_SIZE + 4);
+        // Trailing bytes past the declared data are ignored.
+        buf.extend_from_slice(&[0; 16]);
+        assert_eq!(buffer_len(&buf).unwrap(), HEADER_SIZE + 4);
+    }
+
+    #[test]
+    fn data_len_of_mount_point() {
+        let buf = mount_point(r"\??\C:\target", r"C:\target");
+        // Both names, their terminators and the mount point header.
+        assert_eq!(data_len(&buf).unwrap(), 13 * 2 + 2 + 9 * 2 + 2 + 8);
+        assert_eq!(data_len(&buf[..4]), Err(Error::Truncated { len: 4, required: 8 }));
+
+        assert_eq!(utilization(0), HEADER_SIZE as f64 * 100.0 / MAXIMUM_BUFFER_SIZE as f64);
+        assert_eq!(utilization((MAXIMUM_BUFFER_SIZE - HEADER_SIZE) as u16), 100.0);
+    }
+
+    #[test]
+    fn buffer_len_oversized() {
+        // Declares more data than any reparse point may hold.
+        let mut buf = header(0xA000_0003, u16::MAX);
+        buf.resize(HEADER_SIZE + u16::MAX as usize, 0);
+        let err = io::Error::from(buffer_len(&buf).unwrap_err());
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains(&(HEADER_SIZE + u16::MAX as usize).to_string()));
+    }
+
+    #[test]
+    fn buffer_len_truncated() {
+        let buf = header(0xA000_0003, 64);
+        let err = io::Error::from(buffer_len(&buf).unwrap_err());
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("needs 72 bytes"));
+
+        let err = buffer_len(&buf[..4]).unwrap_err();
+        assert_eq!(
+            err,
+            Error::Truncated {
+                len: 4,
+                required: HEADER_SIZE
+            }
+        );
+        assert_eq!(reparse_tag(&buf[..4]), Err(err));
+    }
+
+    #[test]
+    fn diff_empty_print_name() {
+        let good = mount_point(r"\??\C:\target", r"C:\target");
+        assert_eq!(diff_reparse_buffers(&good, &good), None);
+
+        // As written before PrintName was populated.
+        let empty = mount_point(r"\??\C:\target", "");
+        let diff = diff_reparse_buffers(&good, &empty).unwrap();
+        assert_eq!(diff.field, Field::DataLength);
+        assert_eq!(diff.offset, 4);
+        // Only the length zeroed, with the same data length.
+        let mut empty = good.clone();
+        empty[HEADER_SIZE + 6..HEADER_SIZE + 8].copy_from_slice(&0u16.to_le_bytes());
+        let diff = diff_reparse_buffers(&good, &empty).unwrap();
+        assert_eq!(diff.field, Field::PrintNameLength);
+        assert_eq!(diff.offset, HEADER_SIZE + 6);
+
+        let mut other = good.clone();
+        *other.last_mut().unwrap() = 1;
+        assert_eq!(diff_reparse_buffers(&good, &other).unwrap().field, Field::PathBuffer);
+        assert_eq!(
+            diff_reparse_buffers(&good, &good[..20]),
+            Some(ReparseDiff {
+                field: Field::End,
+                offset: 20
+            })
+        );
+        // Past the header, other kinds of reparse points are opaque.
+        let mut link = good.clone();
+        link[..4].copy_from_slice(&IO_REPARSE_TAG_SYMLINK.to_le_bytes());
+        let mut other = link.clone();
+        other[HEADER_SIZE + 6] = 0;
+        assert_eq!(diff_reparse_buffers(&link, &other).unwrap().field, Field::Data);
+        assert_eq!(diff_reparse_buffers(&good, &link).unwrap().field, Field::Tag);
+    }
+
+    #[test]
+    fn strip_nt_prefix_only_at_start() {
+        let units = |s: &str| s.encode_utf16().collect::<Vec<_>>();
+        assert_eq!(strip_nt_prefix(&units(r"\??\C:\target")), &units(r"C:\target")[..]);
+        assert_eq!(strip_nt_prefix(&units(r"C:\??\target")), &units(r"C:\??\target")[..]);
+        assert_eq!(strip_nt_prefix(&units(r"\??")), &units(r"\??")[..]);
+    }
+
+    #[test]
+    fn split_volume_guid_substitute_name() {
+        const GUID: &str = "Volume{0b2c4d6e-1a3b-4c5d-8e9f-a0b1c2d3e4f5}";
+        let buf = mount_point(&format!(r"\??\{}\dir\sub", GUID), &format!(r"\\?\{}\dir\sub", GUID));
+        let mp = parse_reparse_buffer(&buf).unwrap();
+        let sub: Vec<u16> = mp.substitute_name.units().collect();
+        let (volume, rest) = split_volume_guid(&sub).unwrap();
+        assert_eq!(String::from_utf16(volume).unwrap(), GUID);
+        assert_eq!(String::from_utf16(rest).unwrap(), r"dir\sub");
+        // As returned by `get_target`, with the NT prefix stripped.
+        let (volume, rest) = split_volume_guid(strip_nt_prefix(&sub)).unwrap();
+        assert_eq!(String::from_utf16(volume).unwrap(), GUID);
+        assert_eq!(String::from_utf16(rest).unwrap(), r"dir\sub");
+
+        let units = |s: &str| s.encode_utf16().collect::<Vec<_>>();
+        for root in [format!(r"\??\{}\", GUID), format!(r"\\?\{}", GUID), GUID.to_lowercase()] {
+            let root_units = units(&root);
+            let (volume, rest) = split_volume_guid(&root_units).unwrap();
+            assert_eq!(volume.len(), GUID.len(), "{}", root);
+            assert!(rest.is_empty(), "{}", root);
+        }
+        for path in [
+            r"\??\C:\Volume{0b2c4d6e-1a3b-4c5d-8e9f-a0b1c2d3e4f5}",
+            r"\??\Volume{0b2c4d6e-1a3b-4c5d-8e9f-a0b1c2d3e4f}",
+            r"\??\Volume{0b2c4d6e-1a3b-4c5d-8e9f-a0b1c2d3e4fg}",
+            r"\??\Volume{0b2c4d6e-1a3b-4c5d-8e9f-a0b1c2d3e4f5}x",
+            r"\??\Volume{0b2c4d6e+1a3b-4c5d-8e9f-a0b1c2d3e4f5}",
+            r"\??\Volume",
+        ] {
+            assert_eq!(split_volume_guid(&units(path)), None, "{}", path);
+        }
+    }
+}
@@ -0,0 +1,251 @@
+//! Security identifiers and the self-relative security descriptors that
+//! [`CreateOptions`](crate::CreateOptions) applies to new junctions.
+//!
+//! Ref: <https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-dtyp/7d4dac05-9cef-4563-a058-f108abecce1d>
+
+use std::fmt;
+use std::str::FromStr;
+
+/// Revision of the SID, ACL and security descriptor structures written here.
+const SID_REVISION: u8 = 1;
+const ACL_REVISION: u8 = 2;
+const SECURITY_DESCRIPTOR_REVISION: u8 = 1;
+/// Size of `SECURITY_DESCRIPTOR_RELATIVE`, the header of a self-relative descriptor.
+const DESCRIPTOR_HEADER_SIZE: usize = 20;
+const ACL_HEADER_SIZE: usize = 8;
+/// Size of the `Header` and `Mask` of a `SYSTEM_MANDATORY_LABEL_ACE`, before its SID.
+const LABEL_ACE_HEADER_SIZE: usize = 8;
+const SYSTEM_MANDATORY_LABEL_ACE_TYPE: u8 = 0x11;
+/// Lower integrity levels may not write to the object.
+const SYSTEM_MANDATORY_LABEL_NO_WRITE_UP: u32 = 0x1;
+const SE_SACL_PRESENT: u16 = 0x0010;
+const SE_SELF_RELATIVE: u16 = 0x8000;
+/// Most sub-authorities a SID can have.
+const SID_MAX_SUB_AUTHORITIES: usize = 15;
+
+/// A security identifier, such as `S-1-5-32-544` for the built-in
+/// Administrators group.
+///
+/// # Example
+///
+/// ```rust
+/// use junction::Sid;
+/// let administrators: Sid = "S-1-5-32-544".parse().unwrap();
+/// assert_eq!(administrators.to_string(), "S-1-5-32-544");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Sid {
+    /// The binary form, as Windows stores it.
+    bytes: Vec<u8>,
+}
+
+impl Sid {
+    /// Creates a SID from its identifier authority and sub-authorities.
+    ///
+    /// Returns `None` if `authority` doesn't fit in 48 bits, or if there are
+    /// more than 15 sub-authorities.
+    pub fn new(authority: u64, sub_authorities: &[u32]) -> Option<Self> {
+        if authority >> 48 != 0 || sub_authorities.len() > SID_MAX_SUB_AUTHORITIES {
+            return None;
+        }
+        let mut bytes = vec![SID_REVISION, sub_authorities.len() as u8];
+        // The identifier authority is big-endian, unlike everything else.
+        bytes.extend_from_slice(&authority.to_be_bytes()[2..]);
+        for sub_authority in sub_authorities {
+            bytes.extend_from_slice(&sub_authority.to_le_bytes());
+        }
+        Some(Self { bytes })
+    }
+
+    /// The binary form of the SID, as in a security descriptor.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    fn authority(&self) -> u64 {
+        self.bytes[2..8]
+            .iter()
+            .fold(0, |authority, &byte| authority << 8 | u64::from(byte))
+    }
+
+    fn sub_authorities(&self) -> impl Iterator<Item = u32> + '_ {
+        self.bytes[8..]
+            .chunks_exact(4)
+            .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+    }
+}
+
+impl FromStr for Sid {
+    type Err = ParseSidError;
+
+    /// Parses the `S-1-<authority>-<sub-authority>...` string form.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split('-');
+        match (parts.next(), parts.next()) {
+            (Some("S" | "s"), Some("1")) => {}
+            _ => return Err(ParseSidError(())),
+        }
+        let authority = parts.next().ok_or(ParseSidError(()))?;
+        // Large authorities may be written in hexadecimal.
+        let authority = match authority.strip_prefix("0x").or_else(|| authority.strip_prefix("0X")) {
+            Some(hex) => u64::from_str_radix(hex, 16),
+            None => authority.parse(),
+        }
+        .map_err(|_| ParseSidError(()))?;
+        let sub_authorities = parts
+            .map(|part| part.parse::<u32>().map_err(|_| ParseSidError(())))
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::new(authority, &sub_authorities).ok_or(ParseSidError(()))
+    }
+}
+
+impl fmt::Display for Sid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "S-{}-{}", self.bytes[0], self.authority())?;
+        for sub_authority in self.sub_authorities() {
+            write!(f, "-{}", sub_authority)?;
+        }
+        Ok(())
+    }
+}
+
+/// The error returned when parsing a [`Sid`] fails.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseSidError(());
+
+impl fmt::Display for ParseSidError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid security identifier, expected the `S-1-...` form")
+    }
+}
+
+impl std::error::Error for ParseSidError {}
+
+/// The mandatory integrity level of an object, which processes running at a
+/// lower level may not write to.
+///
+/// Ref: <https://learn.microsoft.com/en-us/windows/win32/secauthz/mandatory-integrity-control>
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum IntegrityLevel {
+    /// `S-1-16-0`, given to anonymous logons.
+    Untrusted,
+    /// `S-1-16-4096`, used by sandboxed processes such as browsers.
+    Low,
+    /// `S-1-16-8192`, the level of ordinary user processes.
+    Medium,
+    /// `S-1-16-12288`, the level of elevated processes.
+    High,
+    /// `S-1-16-16384`, the level of services running as the system.
+    System,
+}
+
+impl IntegrityLevel {
+    /// The SID that labels objects with this integrity level.
+    pub fn sid(self) -> Sid {
+        const MANDATORY_LABEL_AUTHORITY: u64 = 16;
+        let rid = match self {
+            Self::Untrusted => 0x0000,
+            Self::Low => 0x1000,
+            Self::Medium => 0x2000,
+            Self::High => 0x3000,
+            Self::System => 0x4000,
+        };
+        Sid::new(MANDATORY_LABEL_AUTHORITY, &[rid]).unwrap()
+    }
+}
+
+/// Builds a self-relative security descriptor holding `owner` and, as a
+/// mandatory label in its SACL, `label`. Parts left out are not written.
+pub(crate) fn self_relative_descriptor(owner: Option<&Sid>, label: Option<IntegrityLevel>) -> Vec<u8> {
+    let mut control = SE_SELF_RELATIVE;
+    let mut body = Vec::new();
+    let mut owner_offset = 0;
+    let mut sacl_offset = 0;
+    if let Some(owner) = owner {
+        owner_offset = DESCRIPTOR_HEADER_SIZE + body.len();
+        body.extend_from_slice(owner.as_bytes());
+    }
+    if let Some(label) = label {
+        // SIDs are a multiple of 4 bytes long, so the ACL stays aligned.
+        sacl_offset = DESCRIPTOR_HEADER_SIZE + body.len();
+        control |= SE_SACL_PRESENT;
+        let sid = label.sid();
+        let ace_size = (LABEL_ACE_HEADER_SIZE + sid.as_bytes().len()) as u16;
+        let acl_size = ACL_HEADER_SIZE as u16 + ace_size;
+        // ACL: AclRevision, Sbz1, AclSize, AceCount, Sbz2
+        body.extend_from_slice(&[ACL_REVISION, 0]);
+        body.extend_from_slice(&acl_size.to_le_bytes());
+        body.extend_from_slice(&1u16.to_le_bytes());
+        body.extend_from_slice(&0u16.to_le_bytes());
+        // ACE: AceType, AceFlags, AceSize, Mask, SidStart
+        body.extend_from_slice(&[SYSTEM_MANDATORY_LABEL_ACE_TYPE, 0]);
+        body.extend_from_slice(&ace_size.to_le_bytes());
+        body.extend_from_slice(&SYSTEM_MANDATORY_LABEL_NO_WRITE_UP.to_le_bytes());
+        body.extend_from_slice(sid.as_bytes());
+    }
+    // Header: Revision, Sbz1, Control, OffsetOwner, OffsetGroup, OffsetSacl, OffsetDacl
+    let mut descriptor = vec![SECURITY_DESCRIPTOR_REVISION, 0];
+    descriptor.extend_from_slice(&control.to_le_bytes());
+    for offset in [owner_offset, 0, sacl_offset, 0] {
+        descriptor.extend_from_slice(&(offset as u32).to_le_bytes());
+    }
+    descriptor.extend_from_slice(&body);
+    descriptor
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sid_round_trip() {
+        let sid: Sid = "S-1-5-32-544".parse().unwrap();
+        assert_eq!(sid.as_bytes(), [1, 2, 0, 0, 0, 0, 0, 5, 32, 0, 0, 0, 0x20, 0x02, 0, 0]);
+        assert_eq!(sid.to_string(), "S-1-5-32-544");
+        assert_eq!("S-1-16-8192".parse::<Sid>().unwrap(), IntegrityLevel::Medium.sid());
+        assert_eq!("S-1-0x10-8192".parse::<Sid>().unwrap(), IntegrityLevel::Medium.sid());
+        // Everyone has no sub-authority beyond the well-known one.
+        assert_eq!("S-1-1-0".parse::<Sid>().unwrap().as_bytes().len(), 12);
+
+        for invalid in [
+            "",
+            "S-1",
+            "S-2-5-32",
+            "X-1-5-32",
+            "S-1-5-32-",
+            "S-1-5-4294967296",
+            "S-1-281474976710656",
+            "S-1-5-1-1-1-1-1-1-1-1-1-1-1-1-1-1-1-1",
+        ] {
+            assert!(invalid.parse::<Sid>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn descriptor_layout() {
+        let owner: Sid = "S-1-5-32-544".parse().unwrap();
+        let descriptor = self_relative_descriptor(Some(&owner), Some(IntegrityLevel::High));
+        let u16_at = |i: usize| u16::from_le_bytes([descriptor[i], descriptor[i + 1]]);
+        let u32_at =
+            |i: usize| u32::from_le_bytes([descriptor[i], descriptor[i + 1], descriptor[i + 2], descriptor[i + 3]]);
+        assert_eq!(u16_at(2), SE_SELF_RELATIVE | SE_SACL_PRESENT);
+        assert_eq!(u32_at(4), 20, "owner follows the header");
+        assert_eq!(&descriptor[20..36], owner.as_bytes());
+        assert_eq!(u32_at(8), 0, "no group");
+        assert_eq!(u32_at(12), 36, "SACL follows the owner");
+        assert_eq!(u32_at(16), 0, "no DACL");
+        // One label ACE holding the 12 byte SID of the integrity level.
+        assert_eq!(u16_at(36 + 2), 8 + 8 + 12);
+        assert_eq!(u16_at(36 + 4), 1);
+        assert_eq!(descriptor[44], SYSTEM_MANDATORY_LABEL_ACE_TYPE);
+        assert_eq!(u16_at(44 + 2), 8 + 12);
+        assert_eq!(&descriptor[52..], IntegrityLevel::High.sid().as_bytes());
+
+        let descriptor = self_relative_descriptor(Some(&owner), None);
+        assert_eq!(descriptor.len(), 20 + 16);
+        let descriptor = self_relative_descriptor(None, Some(IntegrityLevel::Low));
+        assert_eq!(descriptor[4..8], [0; 4]);
+        assert_eq!(descriptor[12..16], 20u32.to_le_bytes());
+    }
+}
@@ -0,0 +1,156 @@
+//! Reparse point tags.
+//!
+//! Ref: <https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-fscc/c8e77b37-3909-4fe6-a4ea-2b9d423b1ee4>
+
+/// Tag of mount points, which junctions are.
+pub const MOUNT_POINT: u32 = 0xA000_0003;
+/// Tag of symbolic links.
+pub const SYMLINK: u32 = 0xA000_000C;
+/// Tag of symbolic links created from inside WSL.
+pub const LX_SYMLINK: u32 = 0xA000_001D;
+/// Tag of files optimized by Data Deduplication, whose data lives in the
+/// volume's chunk store. Such files are not junctions, and are reported as
+/// [`ReparseTag::Other`].
+pub const DEDUP: u32 = 0x8000_0013;
+/// Tag of cloud files placeholders, such as those of OneDrive, without a
+/// provider-specific subtype. Such placeholders are not junctions, and are
+/// reported as [`ReparseTag::Other`].
+pub const CLOUD: u32 = 0x9000_001A;
+/// Tag of Projected File System placeholders, such as those of VFS for Git,
+/// whose contents the providing application fills in on access. Such
+/// placeholders are not junctions, and are reported as [`ReparseTag::Other`].
+pub const PROJFS: u32 = 0x9000_001C;
+/// Bits of a cloud files tag holding its subtype, from `IO_REPARSE_TAG_CLOUD_1`
+/// to `IO_REPARSE_TAG_CLOUD_F`.
+pub const CLOUD_MASK: u32 = 0x0000_F000;
+
+/// Whether `tag` is owned by Microsoft. Reparse points with other tags carry
+/// a GUID identifying their owner.
+///
+/// Decodes the `M` bit of the tag, like `IsReparseTagMicrosoft`.
+pub const fn is_microsoft(tag: u32) -> bool {
+    tag & 0x8000_0000 != 0
+}
+
+/// Whether `tag` is a name surrogate, standing in for another named entity
+/// in the filesystem, as mount points and symbolic links do.
+///
+/// Decodes the `N` bit of the tag, like `IsReparseTagNameSurrogate`.
+pub const fn is_surrogate(tag: u32) -> bool {
+    tag & 0x2000_0000 != 0
+}
+
+/// Whether a reparse point with `tag` may be set on a non-empty directory,
+/// as cloud files placeholders are.
+///
+/// Decodes the `D` bit of the tag.
+pub const fn is_directory_reparse(tag: u32) -> bool {
+    tag & 0x1000_0000 != 0
+}
+
+/// Whether `tag` is one of the `IO_REPARSE_TAG_CLOUD*` family, marking a
+/// cloud files placeholder whose data is fetched on access.
+pub const fn is_cloud(tag: u32) -> bool {
+    tag & !CLOUD_MASK == CLOUD
+}
+
+/// The kind of a reparse point, decoded from its tag.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ReparseTag {
+    /// A mount point, which is what junctions are.
+    MountPoint,
+    /// A symbolic link.
+    Symlink,
+    /// A symbolic link created from inside WSL, with a UTF-8 target.
+    LxSymlink,
+    /// Any other tag, such as those of cloud files, deduplicated files or
+    /// Projected File System placeholders.
+    Other(u32),
+}
+
+impl ReparseTag {
+    /// See [`is_surrogate`].
+    pub fn is_surrogate(self) -> bool {
+        is_surrogate(self.into())
+    }
+
+    /// See [`is_directory_reparse`].
+    pub fn is_directory_reparse(self) -> bool {
+        is_directory_reparse(self.into())
+    }
+
+    /// See [`is_cloud`].
+    pub fn is_cloud(self) -> bool {
+        is_cloud(self.into())
+    }
+}
+
+impl From<u32> for ReparseTag {
+    fn from(tag: u32) -> Self {
+        match tag {
+            MOUNT_POINT => Self::MountPoint,
+            SYMLINK => Self::Symlink,
+            LX_SYMLINK => Self::LxSymlink,
+            other => Self::Other(other),
+        }
+    }
+}
+
+impl From<ReparseTag> for u32 {
+    fn from(tag: ReparseTag) -> Self {
+        match tag {
+            ReparseTag::MountPoint => MOUNT_POINT,
+            ReparseTag::Symlink => SYMLINK,
+            ReparseTag::LxSymlink => LX_SYMLINK,
+            ReparseTag::Other(other) => other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flag_bits() {
+        for tag in [ReparseTag::MountPoint, ReparseTag::Symlink, ReparseTag::LxSymlink] {
+            assert!(tag.is_surrogate(), "{:?}", tag);
+            assert!(!tag.is_directory_reparse(), "{:?}", tag);
+        }
+        // IO_REPARSE_TAG_CLOUD
+        assert!(!is_surrogate(0x9000_001A));
+        assert!(is_directory_reparse(0x9000_001A));
+        assert!(!is_surrogate(DEDUP));
+        assert!(!is_directory_reparse(DEDUP));
+        assert_eq!(ReparseTag::from(DEDUP), ReparseTag::Other(DEDUP));
+        assert_eq!(ReparseTag::from(0xA000_001D), ReparseTag::LxSymlink);
+        for tag in [MOUNT_POINT, SYMLINK, LX_SYMLINK, DEDUP, CLOUD, PROJFS] {
+            assert!(is_microsoft(tag), "{:#x}", tag);
+        }
+        assert!(!is_microsoft(0x0000_0042));
+    }
+
+    #[test]
+    fn cloud_family() {
+        // IO_REPARSE_TAG_CLOUD through IO_REPARSE_TAG_CLOUD_F.
+        for subtype in 0..=0xF {
+            let tag = CLOUD | subtype << 12;
+            assert!(is_cloud(tag), "{:#x}", tag);
+            assert_eq!(ReparseTag::from(tag), ReparseTag::Other(tag));
+            assert!(is_directory_reparse(tag) && !is_surrogate(tag), "{:#x}", tag);
+        }
+        for tag in [
+            MOUNT_POINT,
+            SYMLINK,
+            LX_SYMLINK,
+            DEDUP,
+            PROJFS,
+            0x9000_001B,
+            0x9001_001A,
+        ] {
+            assert!(!is_cloud(tag), "{:#x}", tag);
+        }
+        assert!(!ReparseTag::MountPoint.is_cloud());
+    }
+}
@@ -2,7 +2,8 @@ use std::fs::{self, File};
 use std::io::{self, Write};
 use std::os::windows::fs::symlink_file;
 #[cfg(miri)]
-use std::path::{Path, PathBuf};
+use std::path::Path;
+use std::path::PathBuf;
 
 #[cfg(not(miri))]
 use tempfile::TempDir;
@@ -265,3 +266,2191 @@ fn create_with_verbatim_prefix_paths() {
     // get_target returns path without verbatim prefix
     assert_eq!(super::get_target(&junction).unwrap(), target);
 }
+
+#[test]
+fn create_with_long_verbatim_target() {
+    let tmpdir = create_tempdir();
+    let junction = tmpdir.path().join("junction");
+    // Longer than MAX_PATH, with a trailing dot that normalizing would strip.
+    // Junction targets need not exist.
+    let target = format!(r"\\?\C:\{}\{}\trailing.", "a".repeat(150), "b".repeat(150));
+    super::create(&target, &junction).unwrap();
+
+    let raw = super::raw_reparse_buffer(&junction).unwrap();
+    let mount_point = super::parse::parse_reparse_buffer(&raw).unwrap();
+    let substitute_name = String::from_utf16(&mount_point.substitute_name.units().collect::<Vec<_>>()).unwrap();
+    assert_eq!(substitute_name, format!(r"\??\{}", &target[4..]));
+}
+
+#[test]
+fn create_target_resolves_to_empty() {
+    let tmpdir = create_tempdir();
+    let junction = tmpdir.path().join("junction");
+
+    // The verbatim prefix alone is stripped to nothing before encoding.
+    match super::create(r"\\?\", &junction) {
+        Err(ref e) if e.kind() == io::ErrorKind::InvalidInput => {}
+        other => panic!("empty target should be rejected: {:?}", other),
+    }
+    assert!(!junction.exists(), "junction directory should not be created");
+}
+
+#[test]
+fn create_with_attributes() {
+    use std::os::windows::fs::MetadataExt;
+    const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+    const FILE_ATTRIBUTE_SYSTEM: u32 = 0x4;
+    const FILE_ATTRIBUTE_REPARSE_POINT: u32 = 0x400;
+
+    let tmpdir = create_tempdir();
+    let target = tmpdir.path().join("target");
+    let junction = tmpdir.path().join("junction");
+    fs::create_dir_all(&target).unwrap();
+
+    super::CreateOptions::new()
+        .attributes(FILE_ATTRIBUTE_HIDDEN | FILE_ATTRIBUTE_SYSTEM)
+        .create(&target, &junction)
+        .unwrap();
+
+    let attributes = fs::symlink_metadata(&junction).unwrap().file_attributes();
+    let expected = FILE_ATTRIBUTE_HIDDEN | FILE_ATTRIBUTE_SYSTEM | FILE_ATTRIBUTE_REPARSE_POINT;
+    assert_eq!(attributes & expected, expected, "attributes: {:#x}", attributes);
+    assert_eq!(&super::get_target(&junction).unwrap(), &target);
+    // The target keeps its own attributes.
+    let target_attributes = fs::metadata(&target).unwrap().file_attributes();
+    assert_eq!(target_attributes & FILE_ATTRIBUTE_HIDDEN, 0);
+}
+
+#[test]
+fn create_reserved_junction_names() {
+    let tmpdir = create_tempdir();
+    let target = tmpdir.path().join("target");
+    fs::create_dir_all(&target).unwrap();
+
+    for name in ["CON", "nul", "Prn", "AUX", "COM1", "lpt9", "NUL.txt", "con ", "CONOUT$"] {
+        let junction = tmpdir.path().join(name);
+        match super::create(&target, &junction) {
+            Err(ref e) if e.kind() == io::ErrorKind::InvalidInput => {}
+            other => panic!("{:?} is a reserved name: {:?}", name, other),
+        }
+    }
+    // Names that merely start like a device are fine.
+    for name in ["CONSOLE", "COM10", "nul_dir"] {
+        super::create(&target, tmpdir.path().join(name)).unwrap();
+    }
+}
+
+#[test]
+fn real_path_nested_junction() {
+    let tmpdir = create_tempdir();
+    let outer_target = tmpdir.path().join("outer_target");
+    let outer = tmpdir.path().join("outer");
+    let inner_target = tmpdir.path().join("inner_target");
+    fs::create_dir_all(&outer_target).unwrap();
+    fs::create_dir_all(&inner_target).unwrap();
+
+    super::create(&outer_target, &outer).unwrap();
+    // Lands in "outer_target" since "outer" is a junction.
+    let inner = outer.join("inner");
+    super::create(&inner_target, &inner).unwrap();
+
+    let real = super::real_path(&inner).unwrap();
+    assert!(!real.as_os_str().to_string_lossy().starts_with(r"\\?\"), "{:?}", real);
+    assert_eq!(real.file_name(), inner.file_name());
+    assert_eq!(
+        fs::canonicalize(real.parent().unwrap()).unwrap(),
+        fs::canonicalize(&outer_target).unwrap(),
+    );
+    // The leaf itself is not followed.
+    assert!(super::exists(&real).unwrap());
+    assert_eq!(super::get_target(&real).unwrap(), super::get_target(&inner).unwrap());
+}
+
+#[test]
+fn create_target_length_boundary() {
+    use std::os::windows::ffi::OsStrExt;
+    // `\??\` + target + PrintName + two nulls must fit in 16368 bytes.
+    const MAX_TARGET_LEN: usize = 4089;
+
+    let tmpdir = create_tempdir();
+    let base = fs::canonicalize(tmpdir.path()).unwrap();
+    let base = base.to_str().unwrap().strip_prefix(r"\\?\").unwrap().to_owned();
+    assert!(base.is_ascii());
+    let target_of_len = |len: usize| {
+        let mut target = base.clone();
+        let mut remaining = len - target.len();
+        while remaining > 0 {
+            // Each component also takes a separator; never leave a single unit over.
+            let mut n = (remaining - 1).min(200);
+            if remaining - 1 - n == 1 {
+                n -= 1;
+            }
+            target.push('\\');
+            target.push_str(&"a".repeat(n));
+            remaining -= n + 1;
+        }
+        assert_eq!(target.encode_utf16().count(), len);
+        PathBuf::from(target)
+    };
+
+    let target = target_of_len(MAX_TARGET_LEN);
+    let junction = tmpdir.path().join("fits");
+    super::create(&target, &junction).unwrap();
+    let stored = super::get_target(&junction).unwrap();
+    assert_eq!(
+        stored.as_os_str().encode_wide().count(),
+        MAX_TARGET_LEN,
+        "target was truncated"
+    );
+    assert_eq!(stored, target);
+
+    let target = target_of_len(MAX_TARGET_LEN + 1);
+    let junction = tmpdir.path().join("too_long");
+    match super::create(&target, &junction) {
+        Err(ref e) if e.kind() == io::ErrorKind::InvalidInput => {}
+        other => panic!("target should be too long: {:?}", other),
+    }
+    assert!(!junction.exists(), "nothing should be created for a too long target");
+}
+
+#[test]
+fn public_types_are_send_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<super::CreateOptions>();
+    assert_send_sync::<super::ReadOptions>();
+    assert_send_sync::<super::ConvertOptions>();
+    assert_send_sync::<super::DeleteOptions>();
+    assert_send_sync::<super::TargetCache>();
+}
+
+#[test]
+fn concurrent_junctions_in_shared_dir() {
+    use std::sync::{Arc, Barrier};
+    use std::thread;
+    const THREADS: usize = 16;
+    const ROUNDS: usize = 8;
+
+    let tmpdir = create_tempdir();
+    let root = fs::canonicalize(tmpdir.path()).unwrap();
+    let barrier = Arc::new(Barrier::new(THREADS));
+    let handles: Vec<_> = (0..THREADS)
+        .map(|i| {
+            let root = root.clone();
+            let barrier = Arc::clone(&barrier);
+            thread::spawn(move || {
+                let target = root.join(format!("target-{}", i));
+                let junction = root.join(format!("junction-{}", i));
+                fs::create_dir_all(&target).unwrap();
+                File::create(target.join("owner"))
+                    .unwrap()
+                    .write_all(i.to_string().as_bytes())
+                    .unwrap();
+                barrier.wait();
+                for _ in 0..ROUNDS {
+                    super::create(&target, &junction).unwrap();
+                    assert!(super::exists(&junction).unwrap());
+                    let resolved = super::get_target(&junction).unwrap();
+                    assert_eq!(fs::canonicalize(resolved).unwrap(), fs::canonicalize(&target).unwrap());
+                    let owner = fs::read_to_string(junction.join("owner")).unwrap();
+                    assert_eq!(owner, i.to_string(), "junction resolved to another thread's target");
+                    super::delete(&junction).unwrap();
+                    fs::remove_dir(&junction).unwrap();
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+}
+
+#[test]
+fn read_from_opened_file() {
+    use std::fs::OpenOptions;
+    use std::os::windows::fs::OpenOptionsExt;
+    const FILE_FLAG_OPEN_REPARSE_POINT: u32 = 0x0020_0000;
+    const FILE_FLAG_BACKUP_SEMANTICS: u32 = 0x0200_0000;
+
+    let tmpdir = create_tempdir();
+    let target = tmpdir.path().join("target");
+    let junction = tmpdir.path().join("junction");
+    fs::create_dir_all(&target).unwrap();
+    super::create(&target, &junction).unwrap();
+
+    let file = OpenOptions::new()
+        .read(true)
+        .custom_flags(FILE_FLAG_OPEN_REPARSE_POINT | FILE_FLAG_BACKUP_SEMANTICS)
+        .open(&junction)
+        .unwrap();
+    assert_eq!(super::get_target_from_file(&file).unwrap(), target);
+    assert_eq!(super::tag_from_file(&file).unwrap(), super::ReparseTag::MountPoint);
+
+    // Without the flag, the handle refers to the target, which is no reparse point.
+    let file = OpenOptions::new()
+        .read(true)
+        .custom_flags(FILE_FLAG_BACKUP_SEMANTICS)
+        .open(&junction)
+        .unwrap();
+    match super::tag_from_file(&file) {
+        Err(ref e) if e.raw_os_error() == Some(ERROR_NOT_A_REPARSE_POINT) => {}
+        other => panic!("target is not a reparse point: {:?}", other),
+    }
+}
+
+#[test]
+fn list_junctions_with_targets_mixed_dir() {
+    use std::os::windows::fs::symlink_dir;
+
+    let tmpdir = create_tempdir();
+    let target = tmpdir.path().join("target");
+    let dir = tmpdir.path().join("dir");
+    fs::create_dir_all(target.join("inner")).unwrap();
+    fs::create_dir_all(&dir).unwrap();
+
+    let junction = dir.join("junction");
+    super::create(&target, &junction).unwrap();
+    fs::create_dir_all(dir.join("plain")).unwrap();
+    File::create(dir.join("file")).unwrap().write_all(b"foo").unwrap();
+    // Needs either privileges or developer mode, it's fine to skip.
+    let _ = symlink_dir(&target, dir.join("symlink"));
+
+    let listed = super::list_junctions_with_targets(&dir).unwrap();
+    assert_eq!(listed, vec![(junction, target)]);
+}
+
+#[test]
+fn create_collapses_dot_components() {
+    let tmpdir = create_tempdir();
+    let target = tmpdir.path().join("target");
+    fs::create_dir_all(target.join("sub")).unwrap();
+    let expected = super::real_path(&target).unwrap();
+
+    let junction = tmpdir.path().join("junction");
+    super::create(tmpdir.path().join(r"sub\..\.\target"), &junction).unwrap();
+    assert_eq!(super::get_target(&junction).unwrap(), expected);
+
+    // Verbatim paths are not normalized by `GetFullPathNameW`.
+    let verbatim = fs::canonicalize(&target).unwrap().join(r"sub\..\.");
+    let junction = tmpdir.path().join("verbatim");
+    super::create(verbatim, &junction).unwrap();
+    assert_eq!(super::get_target(&junction).unwrap(), expected);
+}
+
+#[test]
+fn convert_to_junction_keeps_contents() {
+    let tmpdir = create_tempdir();
+    let dir = tmpdir.path().join("cache");
+    let store = tmpdir.path().join("store");
+    fs::create_dir_all(dir.join("nested")).unwrap();
+    fs::write(dir.join("a.txt"), "a").unwrap();
+    fs::write(dir.join("nested").join("b.txt"), "b").unwrap();
+
+    super::convert_to_junction(&dir, &store).unwrap();
+    assert!(super::exists(&dir).unwrap());
+    assert_eq!(
+        super::get_target(&dir).unwrap(),
+        super::real_path(store.join("cache")).unwrap()
+    );
+    assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "a");
+    assert_eq!(fs::read_to_string(dir.join("nested").join("b.txt")).unwrap(), "b");
+    assert_eq!(fs::read_to_string(store.join("cache").join("a.txt")).unwrap(), "a");
+}
+
+#[test]
+fn convert_to_junction_existing_store_entry() {
+    let tmpdir = create_tempdir();
+    let dir = tmpdir.path().join("cache");
+    let store = tmpdir.path().join("store");
+    fs::create_dir_all(dir.join("nested")).unwrap();
+    fs::create_dir_all(store.join("cache").join("nested")).unwrap();
+    fs::write(dir.join("nested").join("new.txt"), "new").unwrap();
+    fs::write(store.join("cache").join("nested").join("old.txt"), "old").unwrap();
+
+    match super::convert_to_junction(&dir, &store) {
+        Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+        other => panic!("existing store entry should be rejected: {:?}", other),
+    }
+    assert!(!super::exists(&dir).unwrap());
+    assert!(dir.join("nested").join("new.txt").exists());
+
+    super::ConvertOptions::new()
+        .merge(true)
+        .convert_to_junction(&dir, &store)
+        .unwrap();
+    assert!(super::exists(&dir).unwrap());
+    assert_eq!(fs::read_to_string(dir.join("nested").join("new.txt")).unwrap(), "new");
+    assert_eq!(fs::read_to_string(dir.join("nested").join("old.txt")).unwrap(), "old");
+}
+
+#[test]
+fn delete_with_restrictive_dacl() {
+    use std::process::Command;
+
+    const DELETE: u32 = 0x0001_0000;
+    const FILE_WRITE_ATTRIBUTES: u32 = 0x0100;
+
+    let tmpdir = create_tempdir();
+    let target = tmpdir.path().join("target");
+    let junction = tmpdir.path().join("junction");
+    fs::create_dir_all(&target).unwrap();
+    super::create(&target, &junction).unwrap();
+
+    // Deny writing data to Everyone, which GENERIC_WRITE would ask for.
+    let status = Command::new("icacls")
+        .arg(&junction)
+        .args(["/deny", "*S-1-1-0:(WD)"])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    super::DeleteOptions::new()
+        .access(DELETE | FILE_WRITE_ATTRIBUTES)
+        .delete(&junction)
+        .unwrap();
+    assert!(!super::exists(&junction).unwrap());
+    fs::remove_dir(&junction).unwrap();
+}
+
+#[test]
+fn create_rejects_device_path_target() {
+    let tmpdir = create_tempdir();
+    let junction = tmpdir.path().join("junction");
+    for target in [r"\\.\C:", r"\\.\C:\Windows", r"\\.\PhysicalDrive0"] {
+        match super::create(target, &junction) {
+            Err(ref e) if e.kind() == io::ErrorKind::InvalidInput && e.to_string().contains("device path") => {}
+            other => panic!("device path {} should be rejected: {:?}", target, other),
+        }
+        assert!(!junction.exists());
+    }
+}
+
+#[test]
+fn reparse_tag_histogram_mixed_tree() {
+    use std::os::windows::fs::symlink_dir;
+
+    let tmpdir = create_tempdir();
+    let target = tmpdir.path().join("target");
+    let root = tmpdir.path().join("root");
+    let nested = root.join("a").join("b");
+    fs::create_dir_all(&target).unwrap();
+    fs::create_dir_all(&nested).unwrap();
+    File::create(target.join("file")).unwrap();
+
+    super::create(&target, root.join("junction")).unwrap();
+    super::create(&target, nested.join("junction")).unwrap();
+    // Symbolic links need either privileges or developer mode.
+    let symlinks = [
+        symlink_file(target.join("file"), root.join("file_link")).is_ok(),
+        symlink_dir(&target, nested.join("dir_link")).is_ok(),
+    ];
+    let symlinks = symlinks.iter().filter(|&&ok| ok).count();
+
+    let histogram = super::reparse_tag_histogram(&root).unwrap();
+    assert_eq!(histogram.get(&super::tags::MOUNT_POINT), Some(&2));
+    assert_eq!(histogram.get(&super::tags::SYMLINK).copied().unwrap_or(0), symlinks);
+    assert_eq!(histogram.len(), if symlinks > 0 { 2 } else { 1 });
+}
+
+#[test]
+fn get_target_resolve_volume_guid() {
+    use std::process::Command;
+
+    let tmpdir = create_tempdir();
+    let dir = fs::canonicalize(tmpdir.path()).unwrap();
+    let drive: String = dir
+        .to_str()
+        .unwrap()
+        .trim_start_matches(r"\\?\")
+        .chars()
+        .take(3)
+        .collect();
+    // Skip if the volume has no GUID path, such as on some network drives.
+    let output = match Command::new("mountvol").args([drive.as_str(), "/L"]).output() {
+        Ok(output) if output.status.success() => output,
+        _ => return,
+    };
+    let volume = String::from_utf8_lossy(&output.stdout).trim().to_owned();
+    if !volume.starts_with(r"\\?\Volume{") {
+        return;
+    }
+
+    let target = tmpdir.path().join("target");
+    fs::create_dir_all(&target).unwrap();
+    let relative = fs::canonicalize(&target).unwrap();
+    let relative = relative.to_str().unwrap().trim_start_matches(r"\\?\")[drive.len()..].to_owned();
+    let junction = tmpdir.path().join("junction");
+    super::create(format!("{}{}", volume, relative), &junction).unwrap();
+
+    // The raw target keeps the volume GUID.
+    let raw = super::get_target(&junction).unwrap();
+    assert!(raw.to_str().unwrap().starts_with("Volume{"), "{}", raw.display());
+    let resolved = super::get_target_resolve_volume(&junction).unwrap();
+    assert_eq!(fs::canonicalize(resolved).unwrap(), fs::canonicalize(&target).unwrap());
+}
+
+#[test]
+fn create_with_owner() {
+    use std::process::Command;
+
+    // `"DOMAIN\user","S-1-5-21-..."`
+    let output = match Command::new("whoami").args(["/user", "/fo", "csv", "/nh"]).output() {
+        Ok(output) if output.status.success() => output,
+        _ => return,
+    };
+    let output = String::from_utf8_lossy(&output.stdout).into_owned();
+    let user = output.trim().rsplit(',').next().unwrap().trim_matches('"');
+    let owner: super::Sid = user.parse().unwrap();
+
+    let tmpdir = create_tempdir();
+    let target = tmpdir.path().join("target");
+    let junction = tmpdir.path().join("junction");
+    fs::create_dir_all(&target).unwrap();
+    super::CreateOptions::new()
+        .owner(owner.clone())
+        .create(&target, &junction)
+        .unwrap();
+    assert!(super::exists(&junction).unwrap());
+
+    let script = format!(
+        "(Get-Acl -LiteralPath '{}').GetOwner([System.Security.Principal.SecurityIdentifier]).Value",
+        junction.display()
+    );
+    let output = match Command::new("powershell")
+        .args(["-NoProfile", "-Command", &script])
+        .output()
+    {
+        Ok(output) if output.status.success() => output,
+        _ => return,
+    };
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), owner.to_string());
+}
+
+#[test]
+fn read_reparse_point_relative() {
+    use std::os::windows::fs::symlink_dir;
+
+    let tmpdir = create_tempdir();
+    let target = tmpdir.path().join("target");
+    let junction = tmpdir.path().join("junction");
+    fs::create_dir_all(&target).unwrap();
+    super::create(&target, &junction).unwrap();
+
+    let reparse_point = super::read_reparse_point(&junction).unwrap();
+    assert_eq!(reparse_point.tag, super::ReparseTag::MountPoint);
+    assert_eq!(reparse_point.target, super::get_target(&junction).unwrap());
+    assert!(!reparse_point.relative);
+
+    // Symbolic links need either privileges or developer mode.
+    let link = tmpdir.path().join("link");
+    if symlink_dir("target", &link).is_err() {
+        return;
+    }
+    let reparse_point = super::read_reparse_point(&link).unwrap();
+    assert_eq!(reparse_point.tag, super::ReparseTag::Symlink);
+    assert_eq!(reparse_point.target, PathBuf::from("target"));
+    assert!(reparse_point.relative);
+}
+
+#[test]
+fn create_rejects_root_junction() {
+    let tmpdir = create_tempdir();
+    let target = tmpdir.path().join("target");
+    fs::create_dir_all(&target).unwrap();
+    for junction in [r"C:\", r"\\?\C:\", "/"] {
+        match super::create(&target, junction) {
+            Err(ref e) if e.kind() == io::ErrorKind::InvalidInput && e.to_string().contains("root") => {}
+            other => panic!("root {} should be rejected: {:?}", junction, other),
+        }
+    }
+}
+
+#[test]
+fn diagnose_broken_junctions() {
+    use super::{Check, Severity};
+
+    let tmpdir = create_tempdir();
+    let target = tmpdir.path().join("target");
+    let junction = tmpdir.path().join("junction");
+    fs::create_dir_all(&target).unwrap();
+    super::create(&target, &junction).unwrap();
+    assert_eq!(super::diagnose(&junction).unwrap(), []);
+
+    // Dangling once its target is gone.
+    fs::remove_dir(&target).unwrap();
+    let diagnostics = super::diagnose(&junction).unwrap();
+    assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+    assert_eq!(diagnostics[0].check, Check::TargetResolves);
+    assert_eq!(diagnostics[0].severity, Severity::Error);
+
+    // A plain directory is not a reparse point at all.
+    let diagnostics = super::diagnose(tmpdir.path()).unwrap();
+    assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+    assert_eq!(diagnostics[0].check, Check::ReparsePoint);
+
+    let diagnostics = super::diagnose(tmpdir.path().join("missing")).unwrap();
+    assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+    assert_eq!(diagnostics[0].check, Check::Exists);
+}
+
+#[test]
+fn create_no_follow_parents() {
+    use std::os::windows::fs::symlink_dir;
+    use std::process::Command;
+
+    let tmpdir = create_tempdir();
+    let target = tmpdir.path().join("target");
+    let real = tmpdir.path().join("real");
+    fs::create_dir_all(&target).unwrap();
+    fs::create_dir_all(&real).unwrap();
+
+    // A plain parent is fine.
+    let junction = tmpdir.path().join("junction");
+    super::CreateOptions::new()
+        .no_follow_parents(true)
+        .create(&target, &junction)
+        .unwrap();
+    assert!(super::exists(&junction).unwrap());
+
+    let via_junction = tmpdir.path().join("via_junction");
+    super::create(&real, &via_junction).unwrap();
+    // Symbolic links need either privileges or developer mode.
+    let via_symlink = tmpdir.path().join("via_symlink");
+    let mut parents = vec![via_junction];
+    if symlink_dir(&real, &via_symlink).is_ok() {
+        parents.push(via_symlink);
+    }
+    for parent in &parents {
+        match super::CreateOptions::new()
+            .no_follow_parents(true)
+            .create(&target, parent.join("junction"))
+        {
+            Err(ref e) if e.kind() == io::ErrorKind::InvalidInput => {}
+            other => panic!("parent {} should be refused: {:?}", parent.display(), other),
+        }
+        assert!(!real.join("junction").exists());
+    }
+    // Followed by default.
+    super::create(&target, tmpdir.path().join("via_junction").join("junction")).unwrap();
+    assert!(super::exists(real.join("junction")).unwrap());
+
+    // A volume mount point is part of the path rather than a link to it.
+    let dir = fs::canonicalize(tmpdir.path()).unwrap();
+    let dir = dir.to_str().unwrap().trim_start_matches(r"\\?\").to_owned();
+    let output = match Command::new("mountvol").args([&dir[..3], "/L"]).output() {
+        Ok(output) if output.status.success() => output,
+        _ => return,
+    };
+    let volume = String::from_utf8_lossy(&output.stdout).trim().to_owned();
+    if !volume.starts_with(r"\\?\Volume{") {
+        return;
+    }
+    let mounted = tmpdir.path().join("mounted");
+    super::create(&volume, &mounted).unwrap();
+    let junction = mounted.join(&dir[3..]).join("mounted_junction");
+    super::CreateOptions::new()
+        .no_follow_parents(true)
+        .create(&target, &junction)
+        .unwrap();
+    assert!(super::exists(tmpdir.path().join("mounted_junction")).unwrap());
+}
+
+#[test]
+fn disable_enable_round_trip() {
+    let tmpdir = create_tempdir();
+    let target = tmpdir.path().join("target");
+    let junction = tmpdir.path().join("junction");
+    fs::create_dir_all(&target).unwrap();
+    File::create(target.join("file")).unwrap();
+    super::create(&target, &junction).unwrap();
+    let original = super::get_target(&junction).unwrap();
+
+    let disabled = super::disable(&junction).unwrap();
+    assert_eq!(disabled, original);
+    assert!(!super::exists(&junction).unwrap());
+    assert!(junction.is_dir(), "an empty directory is left behind");
+    assert!(!junction.join("file").exists());
+
+    super::enable(&junction, &disabled).unwrap();
+    assert_eq!(super::get_target(&junction).unwrap(), original);
+    assert!(junction.join("file").exists());
+}
+
+#[test]
+fn target_size_with_cycles() {
+    let tmpdir = create_tempdir();
+    let target = tmpdir.path().join("target");
+    let sub = target.join("sub");
+    let junction = tmpdir.path().join("junction");
+    fs::create_dir_all(&sub).unwrap();
+    fs::write(target.join("a"), [0; 100]).unwrap();
+    fs::write(sub.join("b"), [0; 20]).unwrap();
+    // Back up to the target itself, across to a sibling, and to nowhere.
+    super::create(&target, sub.join("up")).unwrap();
+    super::create(&sub, target.join("across")).unwrap();
+    super::create(tmpdir.path().join("missing"), target.join("broken")).unwrap();
+    super::create(&target, &junction).unwrap();
+
+    assert_eq!(super::target_size(&junction).unwrap(), 120);
+}
+
+#[test]
+fn create_on_drive_letters() {
+    let tmpdir = create_tempdir();
+    let junction = tmpdir.path().join("junction");
+    super::create_on_drive('c', r"Windows\System32", &junction).unwrap();
+    assert_eq!(
+        super::get_target(&junction).unwrap(),
+        PathBuf::from(r"C:\Windows\System32")
+    );
+
+    let other = tmpdir.path().join("other");
+    for drive in ['1', '?', 'ü'] {
+        match super::create_on_drive(drive, "Windows", &other) {
+            Err(ref e) if e.kind() == io::ErrorKind::InvalidInput => {}
+            result => panic!("drive {:?} should be rejected: {:?}", drive, result),
+        }
+    }
+    match super::create_on_drive('C', "D:relative", &other) {
+        Err(ref e) if e.kind() == io::ErrorKind::InvalidInput => {}
+        result => panic!("prefixed relative target should be rejected: {:?}", result),
+    }
+    assert!(!other.exists());
+}
+
+#[test]
+fn delete_many_mixed_results() {
+    let tmpdir = create_tempdir();
+    let target = tmpdir.path().join("target");
+    let junction = tmpdir.path().join("junction");
+    let dir = tmpdir.path().join("dir");
+    let missing = tmpdir.path().join("missing");
+    fs::create_dir_all(&target).unwrap();
+    fs::create_dir_all(&dir).unwrap();
+    super::create(&target, &junction).unwrap();
+
+    let results = super::delete_many(vec![junction.clone(), dir.clone(), missing.clone()]);
+    let paths: Vec<_> = results.iter().map(|(path, _)| path.clone()).collect();
+    assert_eq!(paths, [junction.clone(), dir, missing.clone()]);
+    assert!(results[0].1.is_ok());
+    assert!(results[1].1.is_err(), "a plain directory is not a junction");
+    assert_eq!(results[2].1.as_ref().unwrap_err().kind(), io::ErrorKind::NotFound);
+    assert!(junction.is_dir(), "the directory is left behind");
+    assert!(!super::exists(&junction).unwrap());
+
+    let other = tmpdir.path().join("other");
+    super::create(&target, &other).unwrap();
+    let results = super::DeleteOptions::new()
+        .remove_dir(true)
+        .delete_many(vec![other.clone(), missing]);
+    assert!(results[0].1.is_ok());
+    assert!(results[1].1.is_err());
+    assert!(!other.exists());
+    assert!(target.exists());
+}
+
+#[test]
+fn get_symlink_target_file_and_dir() {
+    use std::os::windows::fs::symlink_dir;
+
+    let tmpdir = create_tempdir();
+    let dir = tmpdir.path().join("dir");
+    let file = tmpdir.path().join("file");
+    fs::create_dir_all(&dir).unwrap();
+    File::create(&file).unwrap();
+    // Symbolic links need either privileges or developer mode.
+    let file_link = tmpdir.path().join("file_link");
+    let dir_link = tmpdir.path().join("dir_link");
+    if symlink_file(&file, &file_link).is_err() || symlink_dir(&dir, &dir_link).is_err() {
+        return;
+    }
+    assert_eq!(super::get_symlink_target(&file_link).unwrap(), file);
+    assert_eq!(super::get_symlink_target(&dir_link).unwrap(), dir);
+
+    let relative_link = dir.join("relative_link");
+    symlink_file(r"..\file", &relative_link).unwrap();
+    assert_eq!(
+        super::get_symlink_target(&relative_link).unwrap(),
+        PathBuf::from(r"..\file")
+    );
+
+    let junction = tmpdir.path().join("junction");
+    super::create(&dir, &junction).unwrap();
+    assert!(super::get_symlink_target(&junction).is_err());
+}
+
+#[test]
+fn get_symlink_target_unc() {
+    use std::os::windows::fs::symlink_dir;
+
+    let tmpdir = create_tempdir();
+    for (i, target) in [r"\\server\share\dir", r"\\?\UNC\server\share\dir"].iter().enumerate() {
+        let link = tmpdir.path().join(format!("link{}", i));
+        // Symbolic links need either privileges or developer mode.
+        if symlink_dir(target, &link).is_err() {
+            return;
+        }
+        assert_eq!(
+            super::get_symlink_target(&link).unwrap(),
+            PathBuf::from(r"\\server\share\dir")
+        );
+    }
+}
+
+#[test]
+fn historical_signatures() {
+    use std::path::Path;
+
+    // Dependents of 1.x call these with `&Path`, so they must keep compiling
+    // without a compatibility shim.
+    let _: fn(&Path, &Path) -> io::Result<()> = |target, junction| super::create(target, junction);
+    let _: fn(&Path) -> io::Result<()> = |junction| super::delete(junction);
+    let _: fn(&Path) -> io::Result<bool> = |junction| super::exists(junction);
+    let _: fn(&Path) -> io::Result<PathBuf> = |junction| super::get_target(junction);
+}
+
+#[test]
+fn diagnose_self_reference() {
+    use super::Check;
+
+    let tmpdir = create_tempdir();
+    let junction = tmpdir.path().join("junction");
+    let err = super::create(&junction, &junction).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    assert!(fs::symlink_metadata(&junction).is_err(), "nothing is created");
+    // Written on an empty directory by the raw API, as `create` refuses it.
+    fs::create_dir(&junction).unwrap();
+    super::enable(&junction, &junction).unwrap();
+    let diagnostics = super::diagnose(&junction).unwrap();
+    assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+    assert_eq!(diagnostics[0].check, Check::SelfReference);
+
+    // Through another junction, and with different casing.
+    let a = tmpdir.path().join("a");
+    let b = tmpdir.path().join("b");
+    super::create(tmpdir.path().join("B"), &a).unwrap();
+    super::create(&a, &b).unwrap();
+    let diagnostics = super::diagnose(&a).unwrap();
+    assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+    assert_eq!(diagnostics[0].check, Check::SelfReference);
+
+    let target = tmpdir.path().join("target");
+    let fine = tmpdir.path().join("fine");
+    fs::create_dir_all(&target).unwrap();
+    super::create(&target, &fine).unwrap();
+    assert_eq!(super::diagnose(&fine).unwrap(), []);
+}
+
+#[test]
+fn create_strict_target_trailing_space() {
+    let tmpdir = create_tempdir();
+    let target = tmpdir.path().join("target");
+    let junction = tmpdir.path().join("junction");
+    fs::create_dir_all(&target).unwrap();
+
+    let mut strict = super::CreateOptions::new();
+    strict.strict_target(true);
+    let mut spaced = target.clone().into_os_string();
+    spaced.push(" ");
+    match strict.create(&spaced, &junction) {
+        Err(ref e) if e.kind() == io::ErrorKind::InvalidInput => {}
+        other => panic!("trailing space should be rejected: {:?}", other),
+    }
+    assert!(!junction.exists());
+
+    strict.create(&target, &junction).unwrap();
+    assert_eq!(super::get_target(&junction).unwrap(), target);
+}
+
+#[test]
+#[cfg(feature = "testutil")]
+fn create_with_empty_print_name_reproduces_legacy() {
+    use super::Check;
+
+    let tmpdir = create_tempdir();
+    let target = tmpdir.path().join("target");
+    let junction = tmpdir.path().join("junction");
+    fs::create_dir_all(&target).unwrap();
+    super::create_with_empty_print_name(&target, &junction).unwrap();
+
+    let raw = super::raw_reparse_buffer(&junction).unwrap();
+    let mount_point = super::parse::parse_reparse_buffer(&raw).unwrap();
+    assert!(mount_point.print_name.is_empty());
+    // Only the PrintName is missing.
+    assert_eq!(super::get_target(&junction).unwrap(), target);
+    assert!(fs::metadata(&junction).unwrap().is_dir());
+    let diagnostics = super::diagnose(&junction).unwrap();
+    assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+    assert_eq!(diagnostics[0].check, Check::PrintName);
+}
+
+#[test]
+fn junction_survives_closed_handles() {
+    let tmpdir = create_tempdir();
+    let target = tmpdir.path().join("target");
+    let junction = tmpdir.path().join("junction");
+    fs::create_dir_all(&target).unwrap();
+    super::create(&target, &junction).unwrap();
+
+    // Each of these opens the junction itself and closes the handle again.
+    assert!(super::exists(&junction).unwrap());
+    super::read_reparse_point(&junction).unwrap();
+    super::raw_reparse_buffer(&junction).unwrap();
+    super::diagnose(&junction).unwrap();
+    super::real_path(&junction).unwrap();
+    super::list_junctions_with_targets(tmpdir.path()).unwrap();
+
+    assert!(fs::symlink_metadata(&junction).is_ok(), "the junction was deleted");
+    assert_eq!(super::get_target(&junction).unwrap(), target);
+}
+
+#[test]
+fn get_target_relative_to_base() {
+    let tmpdir = create_tempdir();
+    let base = tmpdir.path().join("root");
+    let under = base.join("lib");
+    let beside = tmpdir.path().join("data");
+    fs::create_dir_all(&under).unwrap();
+    fs::create_dir_all(&beside).unwrap();
+    let junction = base.join("junction");
+
+    super::create(&under, &junction).unwrap();
+    assert_eq!(
+        super::get_target_relative_to(&junction, &base).unwrap(),
+        PathBuf::from("lib")
+    );
+    super::delete(&junction).unwrap();
+    fs::remove_dir(&junction).unwrap();
+
+    super::create(&beside, &junction).unwrap();
+    assert_eq!(
+        super::get_target_relative_to(&junction, &base).unwrap(),
+        PathBuf::from(r"..\data")
+    );
+    // `get_target` still returns the stored, absolute path.
+    assert_eq!(super::get_target(&junction).unwrap(), beside);
+
+    // A base on another volume has nothing in common with the target.
+    let other_drive = if beside.starts_with("Z:") { r"Y:\" } else { r"Z:\" };
+    match super::get_target_relative_to(&junction, other_drive) {
+        Err(ref e) if e.kind() == io::ErrorKind::InvalidInput => {}
+        other => panic!("a different drive has no common prefix: {:?}", other),
+    }
+}
+
+#[cfg(feature = "watch")]
+#[test]
+fn watch_target_retargeted() {
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    let tmpdir = create_tempdir();
+    let first = tmpdir.path().join("first");
+    let second = tmpdir.path().join("second");
+    let junction = tmpdir.path().join("junction");
+    fs::create_dir_all(&first).unwrap();
+    fs::create_dir_all(&second).unwrap();
+    super::create(&first, &junction).unwrap();
+
+    let (sender, receiver) = mpsc::channel();
+    let watch = super::watch_target(&junction, move |target| sender.send(target).unwrap()).unwrap();
+    // Unrelated entries don't trigger the callback.
+    fs::create_dir(tmpdir.path().join("unrelated")).unwrap();
+    assert!(receiver.recv_timeout(Duration::from_millis(200)).is_err());
+
+    super::disable(&junction).unwrap();
+    super::enable(&junction, &second).unwrap();
+    assert_eq!(receiver.recv_timeout(Duration::from_secs(10)).unwrap(), second);
+
+    super::delete(&junction).unwrap();
+    fs::remove_dir(&junction).unwrap();
+    super::create(&first, &junction).unwrap();
+    assert_eq!(receiver.recv_timeout(Duration::from_secs(10)).unwrap(), first);
+
+    watch.stop();
+    super::disable(&junction).unwrap();
+    super::enable(&junction, &second).unwrap();
+    assert!(receiver.recv_timeout(Duration::from_millis(200)).is_err());
+}
+
+#[test]
+fn create_validate_target_open() {
+    let tmpdir = create_tempdir();
+    let target = tmpdir.path().join("target");
+    let link = tmpdir.path().join("link");
+    let file = tmpdir.path().join("file");
+    let junction = tmpdir.path().join("junction");
+    fs::create_dir_all(&target).unwrap();
+    fs::write(&file, b"").unwrap();
+    super::create(&target, &link).unwrap();
+
+    let mut validated = super::CreateOptions::new();
+    validated.validate_target_open(true);
+    match validated.create(tmpdir.path().join("missing"), &junction) {
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => {}
+        other => panic!("missing target should be rejected: {:?}", other),
+    }
+    assert!(!junction.exists());
+    match validated.create(&file, &junction) {
+        Err(ref e) if e.kind() == io::ErrorKind::InvalidInput => {}
+        other => panic!("file target should be rejected: {:?}", other),
+    }
+    assert!(!junction.exists());
+
+    // Stores the canonical path of the directory `link` leads to.
+    validated.create(&link, &junction).unwrap();
+    let canonical = fs::canonicalize(&target).unwrap();
+    let canonical = canonical.to_str().unwrap().trim_start_matches(r"\\?\");
+    assert_eq!(super::get_target(&junction).unwrap().to_str().unwrap(), canonical);
+}
+
+#[test]
+fn create_rejects_self_target_by_casing() {
+    let tmpdir = create_tempdir();
+    let junction = tmpdir.path().join("junction");
+    let target = tmpdir.path().join("JUNCTION");
+
+    match super::create(&target, &junction) {
+        Err(ref e) if e.kind() == io::ErrorKind::InvalidInput => {}
+        other => panic!("self-targeting junction should be rejected: {:?}", other),
+    }
+    assert!(!junction.exists());
+}
+
+#[test]
+fn reparse_data_len_matches_encoding() {
+    use std::os::windows::ffi::OsStrExt;
+
+    let tmpdir = create_tempdir();
+    let target = tmpdir.path().join("target");
+    let junction = tmpdir.path().join("junction");
+    fs::create_dir_all(&target).unwrap();
+
+    let report = super::create_report(&target, &junction).unwrap();
+    let len = super::reparse_data_len(&junction).unwrap();
+    assert_eq!(len + 8, report.in_buffer_size);
+    // Mount point header, `\??\` + target, target, and both terminators.
+    let units = super::get_target(&junction).unwrap().as_os_str().encode_wide().count() as u16;
+    assert_eq!(len, 8 + (4 + units) * 2 + 2 + units * 2 + 2);
+}
+
+#[test]
+fn create_astral_target_round_trips() {
+    let tmpdir = create_tempdir();
+    let target = tmpdir.path().join("target \u{1F980}");
+    let junction = tmpdir.path().join("junction");
+    fs::create_dir_all(&target).unwrap();
+
+    super::create(&target, &junction).unwrap();
+    assert_eq!(super::get_target(&junction).unwrap(), target);
+    fs::write(target.join("file"), b"crab").unwrap();
+    assert_eq!(fs::read(junction.join("file")).unwrap(), b"crab");
+}
+
+#[test]
+fn retarget_prefix_moved_store() {
+    let tmpdir = create_tempdir();
+    let old = tmpdir.path().join("store");
+    let new = tmpdir.path().join("moved");
+    let other = tmpdir.path().join("other");
+    let links = tmpdir.path().join("links");
+    fs::create_dir_all(old.join("a")).unwrap();
+    fs::create_dir_all(old.join("b")).unwrap();
+    fs::create_dir_all(&other).unwrap();
+    fs::create_dir_all(links.join("nested")).unwrap();
+    super::create(old.join("a"), links.join("a")).unwrap();
+    super::create(&old, links.join("nested").join("store")).unwrap();
+    super::create(&other, links.join("other")).unwrap();
+    super::create(old.join("b"), tmpdir.path().join("outside")).unwrap();
+    fs::rename(&old, &new).unwrap();
+
+    // Prefixes match regardless of casing.
+    let upper = PathBuf::from(old.to_str().unwrap().to_ascii_uppercase());
+    assert_eq!(super::retarget_prefix(&links, &upper, &new).unwrap(), 2);
+    assert_eq!(super::get_target(links.join("a")).unwrap(), new.join("a"));
+    assert_eq!(super::get_target(links.join("nested").join("store")).unwrap(), new);
+    assert_eq!(super::get_target(links.join("other")).unwrap(), other);
+    // Outside `root`, so left alone.
+    assert_eq!(super::get_target(tmpdir.path().join("outside")).unwrap(), old.join("b"));
+    assert!(links.join("a").exists());
+}
+
+#[test]
+fn get_target_win32_drive_letter() {
+    use std::process::Command;
+
+    let tmpdir = create_tempdir();
+    let target = tmpdir.path().join("target");
+    let junction = tmpdir.path().join("junction");
+    fs::create_dir_all(&target).unwrap();
+
+    super::create(&target, &junction).unwrap();
+    let win32 = super::get_target_win32(&junction).unwrap();
+    assert_eq!(win32, target);
+    assert_eq!(win32.to_str().unwrap().as_bytes()[1], b':');
+
+    // A target on the volume GUID maps back to the drive letter.
+    let canonical = fs::canonicalize(&target).unwrap();
+    let canonical = canonical.to_str().unwrap().trim_start_matches(r"\\?\");
+    let (drive, relative) = canonical.split_at(3);
+    let output = match Command::new("mountvol").args([drive, "/L"]).output() {
+        Ok(output) if output.status.success() => output,
+        _ => return,
+    };
+    let volume = String::from_utf8_lossy(&output.stdout).trim().to_owned();
+    if !volume.starts_with(r"\\?\Volume{") {
+        return;
+    }
+    let on_volume = tmpdir.path().join("on_volume");
+    super::create(format!("{}{}", volume, relative), &on_volume).unwrap();
+    let win32 = super::get_target_win32(&on_volume).unwrap();
+    assert_eq!(win32.to_str().unwrap().to_lowercase(), canonical.to_lowercase());
+}
+
+#[test]
+fn ensure_locked_creates_once() {
+    use std::sync::Arc;
+    use std::thread;
+
+    let tmpdir = create_tempdir();
+    let target = tmpdir.path().join("target");
+    let other = tmpdir.path().join("other");
+    let junction = tmpdir.path().join("junction");
+    fs::create_dir_all(&target).unwrap();
+    fs::create_dir_all(&other).unwrap();
+
+    let paths = Arc::new((target.clone(), junction.clone()));
+    let threads: Vec<_> = (0..8)
+        .map(|_| {
+            let paths = Arc::clone(&paths);
+            thread::spawn(move || super::CreateOptions::new().lock(true).ensure(&paths.0, &paths.1))
+        })
+        .collect();
+    let created = threads
+        .into_iter()
+        .map(|thread| thread.join().unwrap().unwrap())
+        .filter(|&created| created)
+        .count();
+    assert_eq!(created, 1);
+    assert_eq!(super::get_target(&junction).unwrap(), target);
+
+    match super::ensure(&other, &junction) {
+        Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+        other => panic!("a different target should be rejected: {:?}", other),
+    }
+}
+
+#[test]
+fn convert_symlink_to_junction_in_place() {
+    use std::os::windows::fs::{symlink_dir, symlink_file};
+
+    let tmpdir = create_tempdir();
+    let target = tmpdir.path().join("target");
+    let link = tmpdir.path().join("link");
+    let relative = tmpdir.path().join("relative");
+    let file_link = tmpdir.path().join("file_link");
+    fs::create_dir_all(&target).unwrap();
+    File::create(target.join("file")).unwrap();
+    // Symbolic links need either privileges or developer mode.
+    if symlink_dir(&target, &link).is_err() {
+        return;
+    }
+    symlink_dir("target", &relative).unwrap();
+    symlink_file(target.join("file"), &file_link).unwrap();
+
+    super::convert_symlink_to_junction(&link).unwrap();
+    assert_eq!(
+        super::read_reparse_point(&link).unwrap().tag,
+        super::ReparseTag::MountPoint
+    );
+    assert_eq!(super::get_target(&link).unwrap(), target);
+    assert!(link.join("file").exists());
+
+    for (path, kind) in [
+        (&relative, io::ErrorKind::InvalidInput),
+        (&file_link, io::ErrorKind::InvalidInput),
+        // Already a junction now.
+        (&link, io::ErrorKind::Other),
+    ] {
+        match super::convert_symlink_to_junction(path) {
+            Err(ref e) if e.kind() == kind => {}
+            other => panic!("{} should fail with {:?}: {:?}", path.display(), kind, other),
+        }
+    }
+    assert!(super::read_reparse_point(&relative).unwrap().relative);
+}
+
+#[test]
+fn apply_prebuilt_reparse_data() {
+    let tmpdir = create_tempdir();
+    let target = tmpdir.path().join("target");
+    let created = tmpdir.path().join("created");
+    fs::create_dir_all(&target).unwrap();
+    super::create(&target, &created).unwrap();
+
+    let data = super::build_reparse_data(&target).unwrap();
+    assert_eq!(data.report().in_buffer_size as usize, data.as_bytes().len());
+    for i in 0..3 {
+        let junction = tmpdir.path().join(format!("junction{}", i));
+        super::apply(&data, &junction).unwrap();
+        assert_eq!(super::get_target(&junction).unwrap(), target);
+        assert_eq!(super::diff_reparse(&created, &junction).unwrap(), None);
+    }
+    match super::apply(&data, tmpdir.path().join("junction0")) {
+        Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+        other => panic!("existing junction should be rejected: {:?}", other),
+    }
+
+    // A damaged buffer is caught before anything is created.
+    let mut damaged = data.clone();
+    damaged.bytes.truncate(damaged.bytes.len() - 4);
+    let junction = tmpdir.path().join("damaged");
+    match super::apply(&damaged, &junction) {
+        Err(ref e) if e.kind() == io::ErrorKind::InvalidData => {}
+        other => panic!("damaged buffer should be rejected: {:?}", other),
+    }
+    assert!(!junction.exists());
+}
+
+#[test]
+fn display_target_prefers_print_name() {
+    let tmpdir = create_tempdir();
+    let target = tmpdir.path().join("target");
+    let junction = tmpdir.path().join("junction");
+    let legacy = tmpdir.path().join("legacy");
+    fs::create_dir_all(&target).unwrap();
+
+    super::create(&target, &junction).unwrap();
+    let raw = super::raw_reparse_buffer(&junction).unwrap();
+    let print_name = super::parse::parse_reparse_buffer(&raw).unwrap().print_name;
+    assert!(!print_name.is_empty());
+    assert_eq!(super::display_target(&junction).unwrap(), target);
+
+    // Without a PrintName, the SubstituteName is shown without its prefix.
+    // Set directly, as `create_with_empty_print_name` needs `testutil`.
+    super::CreateOptions {
+        empty_print_name: true,
+        ..super::CreateOptions::new()
+    }
+    .create(&target, &legacy)
+    .unwrap();
+    assert_eq!(super::display_target(&legacy).unwrap(), target);
+
+    match super::display_target(tmpdir.path()) {
+        Err(_) => {}
+        Ok(shown) => panic!("a plain directory has no target: {}", shown.display()),
+    }
+}
+
+#[test]
+fn create_expand_short_names() {
+    use std::process::Command;
+
+    let tmpdir = create_tempdir();
+    let target = tmpdir.path().join("long target name");
+    let short = tmpdir.path().join("short");
+    let expanded = tmpdir.path().join("expanded");
+    fs::create_dir_all(&target).unwrap();
+
+    // `%~sI` expands to the short path, or the long one without 8.3 names.
+    let output = Command::new("cmd")
+        .arg("/c")
+        .arg(format!("for %I in (\"{}\") do @echo %~sI", target.display()))
+        .output()
+        .unwrap();
+    let short_target = PathBuf::from(String::from_utf8_lossy(&output.stdout).trim());
+    if !short_target.ends_with("LONGTA~1") {
+        return;
+    }
+
+    super::create(&short_target, &short).unwrap();
+    assert_eq!(super::get_target(&short).unwrap(), short_target);
+
+    let mut options = super::CreateOptions::new();
+    options.expand_short_names(true);
+    options.create(&short_target, &expanded).unwrap();
+    let stored = super::get_target(&expanded).unwrap();
+    assert!(stored.ends_with("long target name"), "{}", stored.display());
+    assert_eq!(fs::canonicalize(stored).unwrap(), fs::canonicalize(&target).unwrap());
+}
+
+#[test]
+fn find_junctions_usn_on_volume() {
+    let tmpdir = create_tempdir();
+    let target = tmpdir.path().join("target");
+    let junction = tmpdir.path().join("junction");
+    fs::create_dir_all(&target).unwrap();
+    super::create(&target, &junction).unwrap();
+
+    // Reading the journal needs administrator rights, and walking a whole
+    // volume instead would take too long here.
+    let drive = match fs::canonicalize(tmpdir.path()).unwrap().components().next() {
+        Some(std::path::Component::Prefix(prefix)) => match prefix.kind() {
+            std::path::Prefix::VerbatimDisk(drive) | std::path::Prefix::Disk(drive) => drive as char,
+            _ => return,
+        },
+        _ => return,
+    };
+    if File::open(format!(r"\\.\{drive}:")).is_err() {
+        return;
+    }
+
+    let dir = fs::canonicalize(tmpdir.path()).unwrap();
+    let junctions = super::find_junctions_usn(tmpdir.path()).unwrap();
+    let found = junctions.iter().find(|(path, _)| {
+        path.file_name() == junction.file_name() && fs::canonicalize(path.parent().unwrap()).ok().as_ref() == Some(&dir)
+    });
+    assert_eq!(found.map(|(_, stored)| stored), Some(&target));
+}
+
+#[test]
+fn matches_spec_target_and_print_name() {
+    use std::path::Path;
+
+    let tmpdir = create_tempdir();
+    let target = tmpdir.path().join("target");
+    let other = tmpdir.path().join("other");
+    let junction = tmpdir.path().join("junction");
+    let legacy = tmpdir.path().join("legacy");
+    fs::create_dir_all(&target).unwrap();
+    fs::create_dir_all(&other).unwrap();
+    super::create(&target, &junction).unwrap();
+    // Set directly, as `create_with_empty_print_name` needs `testutil`.
+    super::CreateOptions {
+        empty_print_name: true,
+        ..super::CreateOptions::new()
+    }
+    .create(&target, &legacy)
+    .unwrap();
+
+    let spec = |target: &Path, print_name: Option<&Path>| super::JunctionSpec {
+        target: target.to_path_buf(),
+        print_name: print_name.map(Path::to_path_buf),
+    };
+    let upper = PathBuf::from(target.as_os_str().to_ascii_uppercase());
+    assert!(super::matches_spec(&junction, &spec(&target, None)).unwrap());
+    assert!(super::matches_spec(&junction, &spec(&upper, None)).unwrap());
+    assert!(super::matches_spec(&junction, &spec(&target, Some(&target))).unwrap());
+    assert!(!super::matches_spec(&junction, &spec(&other, None)).unwrap());
+    assert!(!super::matches_spec(&junction, &spec(&target, Some(&other))).unwrap());
+
+    // The PrintName only matters when the spec has one.
+    assert!(super::matches_spec(&legacy, &spec(&target, None)).unwrap());
+    assert!(!super::matches_spec(&legacy, &spec(&target, Some(&target))).unwrap());
+
+    // A plain directory is not a junction to anything.
+    assert!(!super::matches_spec(&other, &spec(&target, None)).unwrap());
+    assert!(super::matches_spec(tmpdir.path().join("missing"), &spec(&target, None)).is_err());
+}
+
+#[test]
+fn apply_spec_converges() {
+    let tmpdir = create_tempdir();
+    let first = tmpdir.path().join("first");
+    let second = tmpdir.path().join("second");
+    let junction = tmpdir.path().join("junction");
+    fs::create_dir_all(&first).unwrap();
+    fs::create_dir_all(&second).unwrap();
+
+    let mut spec = super::JunctionSpec {
+        target: first.clone(),
+        print_name: None,
+    };
+    // Created, then left alone.
+    assert!(super::apply_spec(&junction, &spec).unwrap());
+    assert!(!super::apply_spec(&junction, &spec).unwrap());
+    assert_eq!(super::get_target(&junction).unwrap(), first);
+
+    // Retargeted in place.
+    spec.target = second.clone();
+    assert!(super::apply_spec(&junction, &spec).unwrap());
+    assert!(!super::apply_spec(&junction, &spec).unwrap());
+    assert_eq!(super::get_target(&junction).unwrap(), second);
+
+    // Only the PrintName is repaired.
+    spec.print_name = Some(PathBuf::from(r"D:\shown"));
+    assert!(super::apply_spec(&junction, &spec).unwrap());
+    assert!(!super::apply_spec(&junction, &spec).unwrap());
+    assert_eq!(super::get_target(&junction).unwrap(), second);
+    assert_eq!(super::display_target(&junction).unwrap(), PathBuf::from(r"D:\shown"));
+    assert!(super::matches_spec(&junction, &spec).unwrap());
+
+    // Created with the PrintName right away.
+    let other = tmpdir.path().join("other");
+    assert!(super::apply_spec(&other, &spec).unwrap());
+    assert!(!super::apply_spec(&other, &spec).unwrap());
+    assert_eq!(super::display_target(&other).unwrap(), PathBuf::from(r"D:\shown"));
+
+    // A plain directory is never replaced.
+    let err = super::apply_spec(&first, &spec).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+}
+
+#[test]
+fn target_within_allowed_root() {
+    let tmpdir = create_tempdir();
+    let sandbox = tmpdir.path().join("sandbox");
+    let inside = sandbox.join("data");
+    let outside = tmpdir.path().join("outside");
+    fs::create_dir_all(&inside).unwrap();
+    fs::create_dir_all(&outside).unwrap();
+
+    let contained = tmpdir.path().join("contained");
+    super::create(&inside, &contained).unwrap();
+    assert!(super::target_within(&contained, &sandbox).unwrap());
+    let upper = PathBuf::from(sandbox.as_os_str().to_ascii_uppercase());
+    assert!(super::target_within(&contained, upper).unwrap());
+
+    let escaping = tmpdir.path().join("escaping");
+    super::create(&outside, &escaping).unwrap();
+    assert!(!super::target_within(&escaping, &sandbox).unwrap());
+
+    // `..` components are resolved before comparing.
+    let dotted = tmpdir.path().join("dotted");
+    super::create(sandbox.join(r"data\..\..\outside"), &dotted).unwrap();
+    assert!(!super::target_within(&dotted, &sandbox).unwrap());
+
+    let root = tmpdir.path().join("root");
+    super::create(&sandbox, &root).unwrap();
+    assert!(super::target_within(&root, &sandbox).unwrap());
+
+    // A junction inside the sandbox leading out of it is followed.
+    let hop = sandbox.join("hop");
+    super::create(&outside, &hop).unwrap();
+    let chained = tmpdir.path().join("chained");
+    super::create(&hop, &chained).unwrap();
+    assert!(!super::target_within(&chained, &sandbox).unwrap());
+
+    // A sibling sharing the root's name as a prefix is not within it.
+    let sibling = tmpdir.path().join("sandbox2");
+    fs::create_dir_all(&sibling).unwrap();
+    let beside = tmpdir.path().join("beside");
+    super::create(&sibling, &beside).unwrap();
+    assert!(!super::target_within(&beside, &sandbox).unwrap());
+}
+
+#[test]
+fn ensure_many_prunes_partial_directory() {
+    use std::path::Path;
+
+    let tmpdir = create_tempdir();
+    let root = tmpdir.path().join("root");
+    let first = tmpdir.path().join("first");
+    let second = tmpdir.path().join("second");
+    fs::create_dir_all(&root).unwrap();
+    fs::create_dir_all(&first).unwrap();
+    fs::create_dir_all(&second).unwrap();
+
+    // Already right, pointing elsewhere, left out of the specs, and a plain
+    // directory that must be kept.
+    super::create(&first, root.join("kept")).unwrap();
+    super::create(&first, root.join("moved")).unwrap();
+    super::create(&first, root.join("stale")).unwrap();
+    fs::create_dir(root.join("plain")).unwrap();
+
+    let spec = |target: &Path| super::JunctionSpec {
+        target: target.to_path_buf(),
+        print_name: None,
+    };
+    let specs = [
+        (root.join("kept"), spec(&first)),
+        (root.join("moved"), spec(&second)),
+        (root.join("added"), spec(&second)),
+        (root.join("plain"), spec(&first)),
+    ];
+    let results = super::ensure_many(&specs, true);
+    let changes: Vec<_> = results
+        .iter()
+        .map(|(path, result)| {
+            (
+                path.file_name().unwrap().to_str().unwrap(),
+                result.as_ref().ok().copied(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        changes,
+        [
+            ("kept", Some(false)),
+            ("moved", Some(true)),
+            ("added", Some(true)),
+            ("plain", None),
+            ("stale", Some(true)),
+        ]
+    );
+    assert_eq!(results[3].1.as_ref().unwrap_err().kind(), io::ErrorKind::AlreadyExists);
+    assert_eq!(super::get_target(root.join("moved")).unwrap(), second);
+    assert_eq!(super::get_target(root.join("added")).unwrap(), second);
+    assert!(!root.join("stale").exists());
+    assert!(root.join("plain").is_dir());
+
+    // Converged, so a second run changes nothing.
+    let results = super::ensure_many(&specs[..3], true);
+    assert!(results.iter().all(|(_, result)| !result.as_ref().unwrap()));
+    assert!(root.join("plain").is_dir());
+}
+
+#[test]
+fn ensure_many_matches_names_ignoring_case() {
+    let tmpdir = create_tempdir();
+    let root = tmpdir.path().join("root");
+    let target = tmpdir.path().join("target");
+    fs::create_dir_all(&root).unwrap();
+    fs::create_dir_all(&target).unwrap();
+    super::create(&target, root.join("Ärger")).unwrap();
+
+    // The same directory and junction, spelled in another case.
+    let upper = PathBuf::from(root.to_str().unwrap().to_ascii_uppercase());
+    let specs = [
+        (
+            root.join("äRGER"),
+            super::JunctionSpec {
+                target: target.clone(),
+                print_name: None,
+            },
+        ),
+        (
+            upper.join("added"),
+            super::JunctionSpec {
+                target: target.clone(),
+                print_name: None,
+            },
+        ),
+    ];
+    let results = super::ensure_many(&specs, true);
+    let changes: Vec<_> = results
+        .iter()
+        .map(|(_, result)| result.as_ref().ok().copied())
+        .collect();
+    assert_eq!(changes, [Some(false), Some(true)]);
+    assert!(super::exists(root.join("Ärger")).unwrap());
+    assert!(super::exists(root.join("added")).unwrap());
+}
+
+#[test]
+fn resolved_target_follows_junction() {
+    let tmpdir = create_tempdir();
+    let target = tmpdir.path().join("target");
+    let junction = tmpdir.path().join("junction");
+    let chained = tmpdir.path().join("chained");
+    fs::create_dir_all(&target).unwrap();
+    super::create(&target, &junction).unwrap();
+    super::create(&junction, &chained).unwrap();
+
+    // Both agree on a simple junction, up to how the temporary directory is spelled.
+    let stored = super::get_target(&junction).unwrap();
+    let resolved = super::resolved_target(&junction).unwrap();
+    assert!(!resolved.as_os_str().to_string_lossy().starts_with(r"\\?\"));
+    assert_eq!(fs::canonicalize(&stored).unwrap(), fs::canonicalize(&resolved).unwrap());
+    assert!(resolved.ends_with("target"));
+
+    // Only the stored target stops at the next junction.
+    assert_eq!(super::get_target(&chained).unwrap(), junction);
+    assert_eq!(super::resolved_target(&chained).unwrap(), resolved);
+
+    let err = super::resolved_target(&target).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::Other);
+}
+
+#[test]
+#[cfg(feature = "testutil")]
+fn create_with_marker_verifies() {
+    let tmpdir = create_tempdir();
+    let target = tmpdir.path().join("target");
+    let other = tmpdir.path().join("other");
+    let junction = tmpdir.path().join("junction");
+
+    super::create_with_marker(&target, &junction, "junction-test-ok").unwrap();
+    assert!(super::verify_marker(&junction, "junction-test-ok").unwrap());
+    assert!(!super::verify_marker(&junction, "something else").unwrap());
+
+    // Retargeted somewhere without the marker.
+    fs::create_dir_all(&other).unwrap();
+    super::delete(&junction).unwrap();
+    super::create(&other, &junction).unwrap();
+    assert!(!super::verify_marker(&junction, "junction-test-ok").unwrap());
+}
+
+#[test]
+fn delete_junction_and_symlink() {
+    use std::os::windows::fs::symlink_dir;
+
+    let tmpdir = create_tempdir();
+    let target = tmpdir.path().join("target");
+    fs::create_dir_all(&target).unwrap();
+
+    let junction = tmpdir.path().join("junction");
+    super::create(&target, &junction).unwrap();
+    super::delete(&junction).unwrap();
+    assert!(!fs::symlink_metadata(&junction).unwrap().file_type().is_symlink());
+    assert!(!super::exists(&junction).unwrap());
+    assert!(junction.is_dir());
+
+    let symlink = tmpdir.path().join("symlink");
+    // Needs either privileges or developer mode, it's fine to skip.
+    if symlink_dir(&target, &symlink).is_err() {
+        return;
+    }
+    assert!(fs::symlink_metadata(&symlink).unwrap().file_type().is_symlink());
+    super::delete(&symlink).unwrap();
+    let metadata = fs::symlink_metadata(&symlink).unwrap();
+    assert!(!metadata.file_type().is_symlink());
+    assert!(metadata.is_dir());
+    assert!(target.is_dir());
+}
+
+#[test]
+fn file_id_identifies_junction() {
+    let tmpdir = create_tempdir();
+    let target = tmpdir.path().join("target");
+    fs::create_dir_all(&target).unwrap();
+    let first = tmpdir.path().join("first");
+    let second = tmpdir.path().join("second");
+    super::create(&target, &first).unwrap();
+    super::create(&target, &second).unwrap();
+
+    let (first_serial, first_index) = super::file_id(&first).unwrap();
+    let (second_serial, second_index) = super::file_id(&second).unwrap();
+    assert_eq!(first_serial, second_serial);
+    assert_ne!(first_index, second_index);
+    assert_ne!(super::file_id(&target).unwrap().1, first_index);
+    // Stable across reads, and through another spelling of the path.
+    assert_eq!(
+        super::file_id(tmpdir.path().join("./first")).unwrap(),
+        (first_serial, first_index)
+    );
+}
+
+#[test]
+fn create_rejects_identical_paths() {
+    let tmpdir = create_tempdir();
+    let junction = tmpdir.path().join("junction");
+
+    match super::create(&junction, &junction) {
+        Err(ref e) if e.kind() == io::ErrorKind::InvalidInput => {
+            assert_eq!(e.to_string(), "`target` and `junction` are the same path");
+        }
+        other => panic!("identical paths should be rejected: {:?}", other),
+    }
+    assert!(fs::symlink_metadata(&junction).is_err());
+}
+
+#[test]
+fn read_without_write_access() {
+    use std::process::Command;
+
+    let tmpdir = create_tempdir();
+    let target = tmpdir.path().join("target");
+    let junction = tmpdir.path().join("junction");
+    fs::create_dir_all(&target).unwrap();
+    super::create(&target, &junction).unwrap();
+
+    // Deny any write to Everyone, as a read-only volume would.
+    let status = Command::new("icacls")
+        .arg(&junction)
+        .args(["/deny", "*S-1-1-0:(W)"])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    assert!(super::exists(&junction).unwrap());
+    assert_eq!(super::get_target(&junction).unwrap(), target);
+}
+
+#[test]
+#[cfg(feature = "testutil")]
+fn encode_reparse_buffer_golden() {
+    #[rustfmt::skip]
+    const GOLDEN: [u8; 44] = [
+        // ReparseTag, ReparseDataLength, Reserved
+        0x03, 0x00, 0x00, 0xA0, 36, 0, 0, 0,
+        // SubstituteNameOffset, SubstituteNameLength, PrintNameOffset, PrintNameLength
+        0, 0, 16, 0, 18, 0, 8, 0,
+        // SubstituteName: \??\C:\a
+        b'\\', 0, b'?', 0, b'?', 0, b'\\', 0, b'C', 0, b':', 0, b'\\', 0, b'a', 0, 0, 0,
+        // PrintName: C:\a
+        b'C', 0, b':', 0, b'\\', 0, b'a', 0, 0, 0,
+    ];
+    assert_eq!(super::encode_reparse_buffer(r"C:\a").unwrap(), GOLDEN);
+    // Spelled differently, but resolved to the same target.
+    assert_eq!(super::encode_reparse_buffer(r"C:\b\..\a\.").unwrap(), GOLDEN);
+}
+
+#[test]
+fn delete_with_retry_waits_for_handle() {
+    use std::os::windows::fs::OpenOptionsExt;
+    use std::time::Duration;
+
+    const FILE_FLAG_BACKUP_SEMANTICS: u32 = 0x0200_0000;
+    const FILE_FLAG_OPEN_REPARSE_POINT: u32 = 0x0020_0000;
+
+    let tmpdir = create_tempdir();
+    let target = tmpdir.path().join("target");
+    let junction = tmpdir.path().join("junction");
+    fs::create_dir_all(&target).unwrap();
+    super::create(&target, &junction).unwrap();
+
+    // Held without sharing for a while, as a scanner would.
+    let held = fs::OpenOptions::new()
+        .read(true)
+        .share_mode(0)
+        .custom_flags(FILE_FLAG_BACKUP_SEMANTICS | FILE_FLAG_OPEN_REPARSE_POINT)
+        .open(&junction)
+        .unwrap();
+    assert!(super::delete(&junction).is_err());
+    let release = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(50));
+        drop(held);
+    });
+    super::delete_with_retry(&junction, 10, Duration::from_millis(10)).unwrap();
+    release.join().unwrap();
+    assert!(!super::exists(&junction).unwrap());
+}
+
+#[test]
+fn copy_tree_flatten_materializes_junctions() {
+    let tmpdir = create_tempdir();
+    let target = tmpdir.path().join("target");
+    let src = tmpdir.path().join("src");
+    let dst = tmpdir.path().join("dst");
+    fs::create_dir_all(target.join("nested")).unwrap();
+    fs::write(target.join("nested").join("file"), "in target").unwrap();
+    fs::create_dir_all(src.join("plain")).unwrap();
+    fs::write(src.join("plain").join("file"), "in src").unwrap();
+    super::create(&target, src.join("plain").join("junction")).unwrap();
+
+    super::copy_tree_flatten(&src, &dst).unwrap();
+    assert_eq!(fs::read_to_string(dst.join("plain").join("file")).unwrap(), "in src");
+    let materialized = dst.join("plain").join("junction");
+    assert!(!super::exists(&materialized).unwrap());
+    assert!(fs::symlink_metadata(&materialized).unwrap().is_dir());
+    assert_eq!(
+        fs::read_to_string(materialized.join("nested").join("file")).unwrap(),
+        "in target"
+    );
+    assert!(super::list_junctions_with_targets(dst.join("plain"))
+        .unwrap()
+        .is_empty());
+
+    // A junction back up the tree is reported rather than copied forever.
+    super::create(&src, src.join("plain").join("up")).unwrap();
+    let err = super::copy_tree_flatten(&src, tmpdir.path().join("cyclic")).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn create_print_name_from_input_keeps_casing() {
+    let tmpdir = create_tempdir();
+    fs::create_dir_all(tmpdir.path().join("Target")).unwrap();
+    let input = tmpdir.path().join("TARGET");
+    let junction = tmpdir.path().join("junction");
+
+    super::CreateOptions::new()
+        .validate_target_open(true)
+        .print_name_from_input(true)
+        .create(&input, &junction)
+        .unwrap();
+    // Resolved to the on-disk casing, but shown as written.
+    assert_eq!(super::get_target(&junction).unwrap().file_name().unwrap(), "Target");
+    assert_eq!(super::display_target(&junction).unwrap(), input);
+}
+
+#[test]
+fn create_osstr_keeps_target_as_given() {
+    let tmpdir = create_tempdir();
+    fs::create_dir_all(tmpdir.path().join("target")).unwrap();
+    // `create` would collapse the `..` and strip the trailing dot.
+    let target = format!(r"{}\other\..\target.", tmpdir.path().display());
+    let junction = tmpdir.path().join("junction");
+
+    super::create_osstr(&target, &junction).unwrap();
+    assert_eq!(super::get_target(&junction).unwrap().as_os_str(), target.as_str());
+
+    // Relative targets are still resolved.
+    let relative = tmpdir.path().join("relative");
+    super::create_osstr("target", &relative).unwrap();
+    assert!(super::get_target(&relative).unwrap().is_absolute());
+}
+
+#[test]
+fn create_inherit_target_times() {
+    use std::os::windows::fs::MetadataExt;
+
+    let tmpdir = create_tempdir();
+    let target = tmpdir.path().join("target");
+    let junction = tmpdir.path().join("junction");
+    fs::create_dir_all(&target).unwrap();
+    // Apart from the junction's own times, which are taken when it is created.
+    std::thread::sleep(std::time::Duration::from_millis(50));
+
+    super::CreateOptions::new()
+        .inherit_target_times(true)
+        .create(&target, &junction)
+        .unwrap();
+    let expected = fs::metadata(&target).unwrap();
+    let actual = fs::symlink_metadata(&junction).unwrap();
+    assert_eq!(actual.creation_time(), expected.creation_time());
+    assert_eq!(actual.last_write_time(), expected.last_write_time());
+}
+
+#[test]
+fn read_no_follow_parents() {
+    use std::os::windows::fs::symlink_dir;
+    use std::process::Command;
+
+    let tmpdir = create_tempdir();
+    let target = tmpdir.path().join("target");
+    let real = tmpdir.path().join("real");
+    fs::create_dir_all(&target).unwrap();
+    fs::create_dir_all(&real).unwrap();
+    super::create(&target, real.join("junction")).unwrap();
+    let mut options = super::ReadOptions::new();
+    options.no_follow_parents(true);
+    assert_eq!(options.get_target(real.join("junction")).unwrap(), target);
+
+    // A junction parent always works, a symbolic link one needs privileges.
+    let linked = tmpdir.path().join("linked");
+    super::create(&real, &linked).unwrap();
+    let mut parents = vec![linked];
+    let symlinked = tmpdir.path().join("symlinked");
+    if symlink_dir(&real, &symlinked).is_ok() {
+        parents.push(symlinked);
+    }
+    for parent in parents {
+        let junction = parent.join("junction");
+        assert_eq!(super::get_target(&junction).unwrap(), target);
+        for result in [
+            options.get_target(&junction).map(drop),
+            options.exists(&junction).map(drop),
+        ] {
+            match result {
+                Err(ref e) if e.kind() == io::ErrorKind::InvalidInput => {}
+                other => panic!("{} should not be followed: {:?}", parent.display(), other),
+            }
+        }
+    }
+
+    // A volume mount point is part of the path rather than a link to it.
+    let dir = fs::canonicalize(tmpdir.path()).unwrap();
+    let dir = dir.to_str().unwrap().trim_start_matches(r"\\?\").to_owned();
+    let output = match Command::new("mountvol").args([&dir[..3], "/L"]).output() {
+        Ok(output) if output.status.success() => output,
+        _ => return,
+    };
+    let volume = String::from_utf8_lossy(&output.stdout).trim().to_owned();
+    if !volume.starts_with(r"\\?\Volume{") {
+        return;
+    }
+    let mounted = tmpdir.path().join("mounted");
+    super::create(&volume, &mounted).unwrap();
+    let junction = mounted.join(&dir[3..]).join("real").join("junction");
+    assert_eq!(options.get_target(&junction).unwrap(), target);
+    assert!(options.exists(&junction).unwrap());
+}
+
+#[test]
+fn create_under_restrictive_inherited_acl() {
+    use std::process::Command;
+
+    let tmpdir = create_tempdir();
+    let target = tmpdir.path().join("target");
+    let parent = tmpdir.path().join("parent");
+    let junction = parent.join("junction");
+    fs::create_dir_all(&target).unwrap();
+    fs::create_dir_all(&parent).unwrap();
+
+    // Children inherit a denial of writing data to Everyone, the parent doesn't.
+    let status = Command::new("icacls")
+        .arg(&parent)
+        .args(["/deny", "*S-1-1-0:(CI)(IO)(WD)"])
+        .status();
+    if !status.is_ok_and(|status| status.success()) {
+        return;
+    }
+
+    match super::create(&target, &junction) {
+        Err(ref e) if e.kind() == io::ErrorKind::PermissionDenied => {
+            assert!(e.to_string().contains("inherits from its parent"), "{}", e);
+            assert!(!junction.exists());
+        }
+        // Backup and restore privileges bypass the ACL.
+        Ok(()) => {}
+        Err(e) => panic!("unexpected error: {:?}", e),
+    }
+}
+
+#[test]
+fn replace_dir_with_junction_stashes_contents() {
+    let tmpdir = create_tempdir();
+    let target = tmpdir.path().join("target");
+    let dir = tmpdir.path().join("dir");
+    fs::create_dir_all(&target).unwrap();
+    fs::create_dir_all(dir.join("nested")).unwrap();
+    fs::write(dir.join("a.txt"), "a").unwrap();
+    fs::write(dir.join("nested").join("b.txt"), "b").unwrap();
+    // Taken already, so the next free name is used.
+    fs::create_dir_all(tmpdir.path().join("dir.old")).unwrap();
+
+    let stash = super::replace_dir_with_junction(&target, &dir).unwrap();
+    assert_eq!(stash, tmpdir.path().join("dir.old.1"));
+    assert_eq!(super::get_target(&dir).unwrap(), target);
+    assert_eq!(fs::read_to_string(stash.join("a.txt")).unwrap(), "a");
+    assert_eq!(fs::read_to_string(stash.join("nested").join("b.txt")).unwrap(), "b");
+    assert!(!tmpdir.path().join("dir.new").exists());
+
+    // Already a junction now.
+    match super::replace_dir_with_junction(&target, &dir) {
+        Err(ref e) if e.kind() == io::ErrorKind::InvalidInput => {}
+        other => panic!("a junction is not a directory to replace: {:?}", other),
+    }
+}
+
+#[test]
+fn reparse_tag_when_reading_is_denied() {
+    use std::process::Command;
+
+    let tmpdir = create_tempdir();
+    let target = tmpdir.path().join("target");
+    let junction = tmpdir.path().join("junction");
+    fs::create_dir_all(&target).unwrap();
+    super::create(&target, &junction).unwrap();
+    assert_eq!(super::reparse_tag(&junction).unwrap(), super::ReparseTag::MountPoint);
+
+    // Deny reading data to Everyone, which GENERIC_READ asks for. Backup
+    // privileges bypass it, and the reparse data is read as usual. Without
+    // them, the tag comes from the directory listing.
+    let status = Command::new("icacls")
+        .arg(&junction)
+        .args(["/deny", "*S-1-1-0:(RD)"])
+        .status()
+        .unwrap();
+    assert!(status.success());
+    assert_eq!(super::reparse_tag(&junction).unwrap(), super::ReparseTag::MountPoint);
+    match super::get_target(&junction) {
+        Ok(read) => assert_eq!(read, target),
+        Err(e) => assert_eq!(e.kind(), io::ErrorKind::PermissionDenied),
+    }
+    match super::reparse_tag(&target) {
+        Err(ref e) if e.raw_os_error() == Some(ERROR_NOT_A_REPARSE_POINT) => {}
+        other => panic!("a plain directory has no tag: {:?}", other),
+    }
+}
+
+#[test]
+#[cfg(feature = "testutil")]
+fn create_no_nt_prefix_stores_target_as_is() {
+    use std::os::windows::ffi::OsStrExt;
+
+    let tmpdir = create_tempdir();
+    let target = tmpdir.path().join("target");
+    let junction = tmpdir.path().join("junction");
+    fs::create_dir_all(&target).unwrap();
+    super::create_no_nt_prefix(&target, &junction).unwrap();
+
+    let raw = super::raw_reparse_buffer(&junction).unwrap();
+    let mount_point = super::parse::parse_reparse_buffer(&raw).unwrap();
+    let substitute_name: Vec<u16> = mount_point.substitute_name.units().collect();
+    let expected: Vec<u16> = target.as_os_str().encode_wide().collect();
+    assert_eq!(substitute_name, expected);
+    assert_eq!(super::get_target(&junction).unwrap(), target);
+}
+
+#[test]
+fn resolution_chain_two_hops() {
+    use super::ReparseTag;
+
+    let tmpdir = create_tempdir();
+    let target = tmpdir.path().join("target");
+    let first = tmpdir.path().join("first");
+    let second = tmpdir.path().join("second");
+    fs::create_dir_all(&target).unwrap();
+    super::create(&target, &second).unwrap();
+    super::create(&second, &first).unwrap();
+
+    let chain = super::resolution_chain(&first, 10).unwrap();
+    assert_eq!(
+        chain,
+        vec![
+            (first.clone(), ReparseTag::MountPoint),
+            (second, ReparseTag::MountPoint)
+        ]
+    );
+    assert_eq!(super::resolution_chain(&first, 1).unwrap().len(), 1);
+    assert!(super::resolution_chain(&target, 10).unwrap().is_empty());
+}
+
+#[test]
+fn resolution_chain_through_unc_target() {
+    use super::ReparseTag;
+
+    let tmpdir = create_tempdir();
+    let target = tmpdir.path().join("target");
+    let first = tmpdir.path().join("first");
+    let second = tmpdir.path().join("second");
+    fs::create_dir_all(&target).unwrap();
+    super::create(&target, &second).unwrap();
+    // `second` through the administrative share of its drive, skipped where
+    // that isn't shared.
+    let local = fs::canonicalize(&second).unwrap();
+    let local = local.to_str().unwrap().trim_start_matches(r"\\?\").to_owned();
+    let shared = PathBuf::from(format!(r"\\localhost\{}${}", &local[..1], &local[2..]));
+    if fs::symlink_metadata(&shared).is_err() {
+        return;
+    }
+    super::create(&shared, &first).unwrap();
+    assert!(super::get_target(&first).unwrap().starts_with(r"UNC\localhost"));
+
+    let chain = super::resolution_chain(&first, 10).unwrap();
+    assert_eq!(
+        chain,
+        vec![(first, ReparseTag::MountPoint), (shared, ReparseTag::MountPoint)]
+    );
+}
+
+#[test]
+fn resolution_chain_cycle() {
+    let tmpdir = create_tempdir();
+    let a = tmpdir.path().join("a");
+    let b = tmpdir.path().join("b");
+    let target = tmpdir.path().join("target");
+    fs::create_dir_all(&target).unwrap();
+    // `b` is created to `a` before `a` is swapped for a junction to `b`.
+    super::create(&target, &a).unwrap();
+    super::create(&a, &b).unwrap();
+    super::delete(&a).unwrap();
+    super::enable(&a, &b).unwrap();
+
+    let err = super::resolution_chain(&a, 10).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn group_by_target_shares_targets() {
+    let tmpdir = create_tempdir();
+    let shared = tmpdir.path().join("shared");
+    let other = tmpdir.path().join("other");
+    let nested = tmpdir.path().join("nested");
+    fs::create_dir_all(&shared).unwrap();
+    fs::create_dir_all(&other).unwrap();
+    fs::create_dir_all(&nested).unwrap();
+    super::create(&shared, tmpdir.path().join("a")).unwrap();
+    super::create(&other, tmpdir.path().join("b")).unwrap();
+    // Differs in case and spelling, so still the same target.
+    let upper = PathBuf::from(shared.to_str().unwrap().to_ascii_uppercase());
+    super::create(upper.join("..").join("shared"), nested.join("c")).unwrap();
+    // Compared as stored once gone.
+    let gone = tmpdir.path().join("gone");
+    fs::create_dir_all(&gone).unwrap();
+    super::create(&gone, nested.join("d")).unwrap();
+    super::create(&gone, nested.join("e")).unwrap();
+    fs::remove_dir(&gone).unwrap();
+
+    let mut groups = super::group_by_target(tmpdir.path()).unwrap();
+    assert_eq!(groups.len(), 3);
+    let key = groups
+        .keys()
+        .find(|key| super::get_target(tmpdir.path().join("a")).unwrap() == **key)
+        .cloned();
+    let mut sharing = groups.remove(&key.unwrap()).unwrap();
+    sharing.sort();
+    assert_eq!(sharing, vec![tmpdir.path().join("a"), nested.join("c")]);
+    let mut gone_group = groups.remove(&gone).unwrap();
+    gone_group.sort();
+    assert_eq!(gone_group, vec![nested.join("d"), nested.join("e")]);
+    assert_eq!(groups.remove(&other).unwrap(), vec![tmpdir.path().join("b")]);
+}
+
+#[test]
+fn create_overwrite_refuses_populated_directory() {
+    let tmpdir = create_tempdir();
+    let target = tmpdir.path().join("target");
+    let junction = tmpdir.path().join("junction");
+    fs::create_dir_all(&target).unwrap();
+    fs::create_dir_all(&junction).unwrap();
+    File::create(junction.join("data.txt")).unwrap();
+
+    let err = super::CreateOptions::new()
+        .overwrite(true)
+        .create(&target, &junction)
+        .unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(145));
+    assert!(junction.join("data.txt").exists());
+
+    super::CreateOptions::new()
+        .overwrite(true)
+        .force_replace_directory(true)
+        .create(&target, &junction)
+        .unwrap();
+    assert_eq!(super::get_target(&junction).unwrap(), target);
+    assert!(!target.join("data.txt").exists());
+
+    // An existing junction is replaced without forcing.
+    let other = tmpdir.path().join("other");
+    fs::create_dir_all(&other).unwrap();
+    super::CreateOptions::new()
+        .overwrite(true)
+        .create(&other, &junction)
+        .unwrap();
+    assert_eq!(super::get_target(&junction).unwrap(), other);
+}
+
+#[test]
+fn status_variants() {
+    use std::os::windows::fs::symlink_dir;
+
+    let tmpdir = create_tempdir();
+    let target = tmpdir.path().join("target");
+    let junction = tmpdir.path().join("junction");
+    fs::create_dir_all(&target).unwrap();
+
+    assert_eq!(
+        super::status(tmpdir.path().join("missing")).unwrap(),
+        super::Status::Missing
+    );
+    assert_eq!(super::status(&target).unwrap(), super::Status::NotAReparsePoint);
+
+    super::create(&target, &junction).unwrap();
+    let buffer_used = super::create_report(&target, tmpdir.path().join("reported"))
+        .unwrap()
+        .in_buffer_size;
+    assert_eq!(
+        super::status(&junction).unwrap(),
+        super::Status::Junction {
+            target: target.clone(),
+            print_name: target.clone(),
+            target_exists: true,
+            buffer_used,
+        }
+    );
+    fs::remove_dir(&target).unwrap();
+    match super::status(&junction).unwrap() {
+        super::Status::Junction { target_exists, .. } => assert!(!target_exists),
+        other => panic!("not a junction: {:?}", other),
+    }
+
+    // Symbolic links need either privileges or developer mode.
+    let link = tmpdir.path().join("link");
+    if symlink_dir("junction", &link).is_err() {
+        return;
+    }
+    assert_eq!(
+        super::status(&link).unwrap(),
+        super::Status::Symlink {
+            target: PathBuf::from("junction"),
+            relative: true,
+        }
+    );
+}
+
+#[cfg(feature = "nt-api")]
+#[test]
+fn create_relative_to_handle_resolves() {
+    use std::fs::OpenOptions;
+    use std::os::windows::fs::OpenOptionsExt;
+    use std::os::windows::io::AsHandle;
+    const FILE_FLAG_BACKUP_SEMANTICS: u32 = 0x0200_0000;
+
+    let tmpdir = create_tempdir();
+    let target = tmpdir.path().join("target");
+    let parent = tmpdir.path().join("parent");
+    fs::create_dir_all(&target).unwrap();
+    fs::create_dir_all(&parent).unwrap();
+    File::create(target.join("file")).unwrap();
+    let handle = OpenOptions::new()
+        .read(true)
+        .custom_flags(FILE_FLAG_BACKUP_SEMANTICS)
+        .open(&parent)
+        .unwrap();
+
+    super::create_relative_to_handle(handle.as_handle(), "junction", &target).unwrap();
+    let junction = parent.join("junction");
+    assert_eq!(super::get_target(&junction).unwrap(), target);
+    assert!(junction.join("file").exists());
+
+    let err = super::create_relative_to_handle(handle.as_handle(), "junction", &target).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+    let err = super::create_relative_to_handle(handle.as_handle(), r"nested\junction", &target).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn unreachable_target_does_not_stall() {
+    let tmpdir = create_tempdir();
+    // TEST-NET-1 is never routed, so following the junction would wait for
+    // the SMB connection to time out.
+    let target = PathBuf::from(r"\\?\UNC\192.0.2.1\share\dir");
+    let junction = tmpdir.path().join("junction");
+    super::create(&target, &junction).unwrap();
+
+    let start = std::time::Instant::now();
+    assert!(super::exists(&junction).unwrap());
+    assert_eq!(
+        super::get_target(&junction).unwrap(),
+        PathBuf::from(r"UNC\192.0.2.1\share\dir")
+    );
+    assert!(
+        start.elapsed() < std::time::Duration::from_secs(5),
+        "{:?}",
+        start.elapsed()
+    );
+}
+
+#[test]
+fn prune_broken_keeps_valid_junctions() {
+    let tmpdir = create_tempdir();
+    let root = tmpdir.path().join("root");
+    let valid = tmpdir.path().join("valid");
+    let gone = tmpdir.path().join("gone");
+    let outside = tmpdir.path().join("outside");
+    for dir in [&root.join("nested"), &valid, &gone, &outside] {
+        fs::create_dir_all(dir).unwrap();
+    }
+    super::create(&valid, root.join("valid")).unwrap();
+    super::create(&gone, root.join("broken")).unwrap();
+    super::create(&gone, root.join("nested").join("broken")).unwrap();
+    // Under `root` only through a junction, which isn't followed.
+    super::create(&gone, outside.join("broken")).unwrap();
+    super::create(&outside, root.join("outside")).unwrap();
+    fs::remove_dir(&gone).unwrap();
+
+    assert!(super::is_broken(root.join("broken")).unwrap());
+    assert!(!super::is_broken(root.join("valid")).unwrap());
+    let mut removed = super::prune_broken(&root).unwrap();
+    removed.sort();
+    assert_eq!(removed, vec![root.join("broken"), root.join("nested").join("broken")]);
+    assert!(fs::symlink_metadata(root.join("broken")).is_err());
+    assert!(super::exists(root.join("valid")).unwrap());
+    assert!(super::exists(root.join("outside")).unwrap());
+    assert!(super::exists(outside.join("broken")).unwrap());
+}
+
+#[test]
+fn is_broken_on_unmounted_drive() {
+    let tmpdir = create_tempdir();
+    let drive = match ('D'..='Z').rev().find(|d| !PathBuf::from(format!(r"{}:\", d)).exists()) {
+        Some(drive) => drive,
+        None => return,
+    };
+    let junction = tmpdir.path().join("junction");
+    super::create(format!(r"{}:\target", drive), &junction).unwrap();
+
+    // The target may be back once the drive is, so it isn't called broken.
+    assert!(super::is_broken(&junction).is_err());
+    assert!(super::prune_broken(tmpdir.path()).unwrap().is_empty());
+    assert!(super::exists(&junction).unwrap());
+}
+
+#[test]
+fn create_junction_beyond_max_path() {
+    let tmpdir = create_tempdir();
+    let target = tmpdir.path().join("target");
+    fs::create_dir_all(&target).unwrap();
+    let parent = (0..6).fold(tmpdir.path().to_path_buf(), |path, _| path.join("d".repeat(50)));
+    fs::create_dir_all(&parent).unwrap();
+    let junction = parent.join("junction");
+    assert!(junction.as_os_str().len() > 260);
+
+    super::create(&target, &junction).unwrap();
+    assert!(super::exists(&junction).unwrap());
+    assert_eq!(super::get_target(&junction).unwrap(), target);
+    super::delete(&junction).unwrap();
+}
@@ -0,0 +1,28 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::internals;
+
+/// Name of the marker file written into the target.
+const MARKER: &str = "marker.txt";
+
+pub fn create_with_marker(target: &Path, junction: &Path, marker_contents: &str) -> io::Result<()> {
+    fs::create_dir_all(target)?;
+    fs::write(target.join(MARKER), marker_contents)?;
+    crate::create(target, junction)
+}
+
+pub fn verify_marker(junction: &Path, expected: &str) -> io::Result<bool> {
+    // Read through the junction, so this also checks that it resolves.
+    match fs::read_to_string(junction.join(MARKER)) {
+        Ok(contents) => Ok(contents == expected),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+pub fn encode_reparse_buffer(target: &Path) -> io::Result<Vec<u8>> {
+    // The same encoding `create` writes, from the same resolved target.
+    internals::build_reparse_data(target).map(|data| data.bytes)
+}
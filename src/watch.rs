@@ -0,0 +1,99 @@
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::{internals, ReadOptions};
+
+// Room for a few hundred changes at once; more are reported as an overflow.
+const BUFFER_LEN: usize = 16 * 1024;
+
+/// A running [`watch_target`](crate::watch_target), which stops when dropped.
+#[derive(Debug)]
+pub struct WatchHandle {
+    dir: Arc<File>,
+    stopped: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl WatchHandle {
+    /// Stops watching, waiting for a callback in progress to return.
+    pub fn stop(mut self) {
+        self.stop_thread();
+    }
+
+    fn stop_thread(&mut self) {
+        let thread = match self.thread.take() {
+            Some(thread) => thread,
+            None => return,
+        };
+        self.stopped.store(true, Ordering::SeqCst);
+        // The thread may be about to block again, so keep waking it up until
+        // it sees the flag.
+        while !thread.is_finished() {
+            let _ = internals::cancel_io(&self.dir);
+            thread::sleep(Duration::from_millis(1));
+        }
+        let _ = thread.join();
+    }
+}
+
+impl Drop for WatchHandle {
+    fn drop(&mut self) {
+        self.stop_thread();
+    }
+}
+
+pub fn watch_target<F>(junction: &Path, mut on_change: F) -> io::Result<WatchHandle>
+where
+    F: FnMut(PathBuf) + Send + 'static,
+{
+    let junction = internals::real_path(junction)?;
+    let (parent, name) = match (junction.parent(), junction.file_name()) {
+        (Some(parent), Some(name)) => (parent, name.to_owned()),
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "`junction` has no parent directory",
+            ))
+        }
+    };
+    let dir = Arc::new(internals::open_dir_for_changes(parent)?);
+    let mut last = internals::get_target(&junction, &ReadOptions::new()).ok();
+    let stopped = Arc::new(AtomicBool::new(false));
+
+    let thread = {
+        let dir = Arc::clone(&dir);
+        let stopped = Arc::clone(&stopped);
+        thread::spawn(move || {
+            let mut buf = vec![0u32; BUFFER_LEN / 4];
+            while !stopped.load(Ordering::SeqCst) {
+                let changed = match internals::read_directory_changes(&dir, &mut buf) {
+                    // An overflow may hide a change to the junction.
+                    Ok(None) => true,
+                    Ok(Some(names)) => names.iter().any(|changed| internals::eq_ignore_case(changed, &name)),
+                    // Cancelled by `stop`, or the directory is gone.
+                    Err(_) => return,
+                };
+                if !changed || stopped.load(Ordering::SeqCst) {
+                    continue;
+                }
+                // Skipped while the junction is missing or being rewritten.
+                if let Ok(target) = internals::get_target(&junction, &ReadOptions::new()) {
+                    if last.as_ref() != Some(&target) {
+                        last = Some(target.clone());
+                        on_change(target);
+                    }
+                }
+            }
+        })
+    };
+    Ok(WatchHandle {
+        dir,
+        stopped,
+        thread: Some(thread),
+    })
+}
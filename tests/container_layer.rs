@@ -7,11 +7,21 @@
 //! In a Dockerfile, run `create` in one RUN step and `verify` in the next.
 //! If PrintName is empty, the junction will break during layer serialization.
 
+#[cfg(windows)]
 use std::env;
+#[cfg(windows)]
 use std::fs;
+#[cfg(windows)]
 use std::path::Path;
+#[cfg(windows)]
 use std::process;
 
+#[cfg(not(windows))]
+fn main() {
+    eprintln!("container_layer: junctions only exist on Windows, nothing to do");
+}
+
+#[cfg(windows)]
 fn main() {
     let args: Vec<String> = env::args().collect();
     if args.len() < 2 {